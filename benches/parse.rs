@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use nix_config_parser::NixConfig;
+
+/// A machine-generated config of roughly `lines` settings, without comments
+/// or includes.
+fn generated_config(lines: usize) -> String {
+    let mut contents = String::new();
+    for i in 0..lines {
+        contents.push_str(&format!(
+            "setting-{i} = https://cache-{i}.example.com https://mirror-{i}.example.com\n"
+        ));
+    }
+    contents
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_string");
+
+    for lines in [1_000, 50_000] {
+        let plain = generated_config(lines);
+        // A single comment forces the general path
+        let commented = format!("# generated\n{plain}");
+
+        group.throughput(Throughput::Bytes(plain.len() as u64));
+        group.bench_with_input(BenchmarkId::new("plain", lines), &plain, |b, contents| {
+            b.iter(|| NixConfig::parse_string(contents.clone(), None).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("commented", lines),
+            &commented,
+            |b, contents| b.iter(|| NixConfig::parse_string(contents.clone(), None).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
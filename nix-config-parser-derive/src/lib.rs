@@ -0,0 +1,125 @@
+//! The `NixSettings` derive macro, re-exported by `nix-config-parser` with the
+//! `derive` feature. See the documentation there.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, LitStr};
+
+#[proc_macro_derive(NixSettings, attributes(nix))]
+pub fn derive_nix_settings(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+struct Field {
+    ident: syn::Ident,
+    ty: syn::Type,
+    name: String,
+    list: bool,
+    default: Option<Expr>,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "NixSettings can only be derived for structs",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "NixSettings can only be derived for structs with named fields",
+        ));
+    };
+
+    let fields = named
+        .named
+        .iter()
+        .map(parse_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let reads = fields.iter().map(|field| {
+        let Field {
+            ident,
+            ty,
+            name,
+            list,
+            default,
+        } = field;
+        let missing = match default {
+            Some(default) => quote!(::core::convert::Into::into(#default)),
+            None => quote!(::nix_config_parser::__private::missing::<#ty>(#name)?),
+        };
+        quote! {
+            #ident: match ::nix_config_parser::__private::read::<#ty>(config, #name, #list)? {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => #missing,
+            }
+        }
+    });
+    let writes = fields.iter().map(|field| {
+        let Field {
+            ident, name, list, ..
+        } = field;
+        quote!(::nix_config_parser::__private::write(config, #name, #list, &self.#ident);)
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::nix_config_parser::NixSettings for #ident #ty_generics #where_clause {
+            fn from_config(
+                config: &::nix_config_parser::NixConfig,
+            ) -> ::core::result::Result<Self, ::nix_config_parser::FromConfigError> {
+                ::core::result::Result::Ok(Self {
+                    #(#reads,)*
+                })
+            }
+
+            fn apply_to(&self, config: &mut ::nix_config_parser::NixConfig) {
+                #(#writes)*
+            }
+        }
+    })
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<Field> {
+    let ident = field.ident.clone().expect("named fields have an ident");
+    let mut parsed = Field {
+        name: ident.to_string().trim_start_matches("r#").replace('_', "-"),
+        ident,
+        ty: field.ty.clone(),
+        list: false,
+        default: None,
+    };
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("nix"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                parsed.name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("list") {
+                parsed.list = true;
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                parsed.default = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename = \"...\"`, `list` or `default = ...`"))
+            }
+        })?;
+    }
+
+    Ok(parsed)
+}
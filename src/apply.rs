@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use crate::{
+    parse_contents, Assignment, NixConfig, ParseError, ParseOptions, SettingInfo, ShadowedSetting,
+};
+
+impl NixConfig {
+    /// Parse `contents` and apply its settings on top of this config in place,
+    /// like Nix does for each config file it reads. Settings that are set again
+    /// are overridden, and `extra-<name>` appends to `<name>` if `<name>` is a
+    /// list setting that is already set (otherwise, it appends to
+    /// `extra-<name>`). Includes are followed as usual, and with
+    /// [`ParseOptions::unset_directives`], `!unset <name>` removes a setting.
+    ///
+    /// The `origin` parameter is [`Option`]al, and only influences potential
+    /// error messages and the recorded [`Origin`](crate::Origin)s.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let mut nix_conf = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org\ncores = 4".into(),
+    ///     None,
+    /// )?;
+    /// nix_conf.apply_string("extra-substituters = https://example.com\ncores = 8", None)?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.settings().get("substituters").unwrap(),
+    ///     "https://cache.nixos.org https://example.com"
+    /// );
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "8");
+    /// assert_eq!(nix_conf.settings().len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_string(
+        &mut self,
+        contents: &str,
+        origin: Option<&Path>,
+    ) -> Result<(), ParseError> {
+        self.apply_string_with_options(contents, origin, &ParseOptions::default())
+    }
+
+    /// Like [`NixConfig::apply_string`], using the provided [`ParseOptions`].
+    pub fn apply_string_with_options(
+        &mut self,
+        contents: &str,
+        origin: Option<&Path>,
+        options: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        let parsed = parse_contents(contents, origin, options)?;
+
+        for assignment in parsed.assignments {
+            self.apply_assignment(assignment);
+        }
+        self.skipped_includes.extend(parsed.skipped_includes);
+        self.truncated_includes.extend(parsed.truncated_includes);
+        self.includes.extend(parsed.includes);
+
+        Ok(())
+    }
+
+    fn apply_assignment(&mut self, assignment: Assignment) {
+        let Assignment {
+            name,
+            value,
+            origin,
+        } = assignment;
+
+        let Some(value) = value else {
+            self.settings.shift_remove(&name);
+            self.origins.shift_remove(&name);
+            self.groups.shift_remove(&name);
+            return;
+        };
+
+        if let Some(base) = name.strip_prefix("extra-") {
+            let is_list = SettingInfo::lookup(&name)
+                .is_some_and(|info| info.name() != name && info.setting_type().is_list());
+            let target = if self.settings.contains_key(base) {
+                base
+            } else {
+                name.as_str()
+            };
+
+            if is_list {
+                if let Some(existing) = self.settings.get_mut(target) {
+                    for item in value.split_whitespace() {
+                        existing.push_item(item);
+                    }
+                    self.origins.insert(target.to_owned(), origin);
+                    return;
+                }
+            }
+        }
+
+        let previous_origin = self.origins.insert(name.clone(), origin.clone());
+        let previous_value = self.settings.insert(name.clone(), value);
+        if let (Some(value), Some(previous_origin)) = (previous_value, previous_origin) {
+            self.shadowed.push(ShadowedSetting {
+                name,
+                value,
+                origin: previous_origin,
+                shadowed_by: origin,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Origin;
+
+    #[test]
+    fn applies_fragments_in_place() {
+        let mut nix_conf = NixConfig::new();
+
+        nix_conf
+            .apply_string("extra-trusted-users = alice\nsandbox = true", None)
+            .unwrap();
+        nix_conf
+            .apply_string("extra-trusted-users = bob\nsandbox = false", None)
+            .unwrap();
+
+        assert_eq!(
+            nix_conf.settings().get("extra-trusted-users"),
+            Some(&"alice bob".into())
+        );
+        assert_eq!(nix_conf.settings().get("sandbox"), Some(&"false".into()));
+        assert_eq!(nix_conf.shadowed_settings().len(), 1);
+        assert_eq!(
+            nix_conf.origin("extra-trusted-users"),
+            Some(&Origin::File {
+                path: None,
+                line: 1
+            })
+        );
+
+        // `extra-platforms` is a setting of its own, not an extension
+        nix_conf
+            .apply_string("extra-platforms = aarch64-linux", None)
+            .unwrap();
+        nix_conf
+            .apply_string("extra-platforms = x86_64-darwin", None)
+            .unwrap();
+        assert_eq!(
+            nix_conf.settings().get("extra-platforms"),
+            Some(&"x86_64-darwin".into())
+        );
+    }
+}
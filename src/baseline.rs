@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::{Diagnostic, Origin};
+
+/// The name of the file the `lint` command of the CLI reads its baseline
+/// from, unless given another one.
+pub const LINT_BASELINE_FILE_NAME: &str = ".nixconf-lint-baseline";
+
+/// The known diagnostics of a config, recorded so they can be suppressed
+/// while new problems are still reported. This allows adopting the lints on
+/// a large config gradually.
+///
+/// A diagnostic is identified by its [code](Diagnostic::code), the
+/// [setting](Diagnostic::name) it is about and the file it was found in, but
+/// not its line, so that unrelated edits to the file don't make it reappear.
+///
+/// The baseline is saved as text, one diagnostic per line:
+///
+/// ```text
+/// # code setting [file]
+/// deprecated-alias binary-caches /etc/nix/nix.conf
+/// identical-override cores /etc/nix/nix.conf
+/// ```
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::{LintBaseline, NixConfig};
+///
+/// let legacy = NixConfig::parse_string("binary-caches = https://cache.nixos.org".into(), None)?;
+/// let baseline = LintBaseline::record(&legacy.lint());
+///
+/// let edited = NixConfig::parse_string(
+///     "cores = 4\nbinary-caches = https://cache.nixos.org\nbuild-cores = 4".into(),
+///     None,
+/// )?;
+/// let diagnostics = baseline.suppress(edited.lint());
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(diagnostics[0].name(), "build-cores");
+///
+/// assert_eq!(baseline.to_string().parse::<LintBaseline>()?, baseline);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintBaseline {
+    entries: HashSet<BaselineEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct BaselineEntry {
+    code: String,
+    name: String,
+    path: Option<PathBuf>,
+}
+
+impl BaselineEntry {
+    fn of(diagnostic: &Diagnostic) -> Self {
+        Self {
+            code: diagnostic.code.clone(),
+            name: diagnostic.name.clone(),
+            path: match &diagnostic.origin {
+                Some(Origin::File { path, .. }) => path.clone(),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// An error reading a [`LintBaseline`].
+#[derive(thiserror::Error, Debug)]
+pub enum BaselineError {
+    #[error("failed to read lint baseline '{0}': {1}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("line {0} of the lint baseline is not `code setting [file]`: {1}")]
+    Invalid(usize, String),
+}
+
+impl LintBaseline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A baseline of `diagnostics`.
+    pub fn record<'a, I>(diagnostics: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Diagnostic>,
+    {
+        Self {
+            entries: diagnostics.into_iter().map(BaselineEntry::of).collect(),
+        }
+    }
+
+    /// Read the baseline saved at `path`.
+    pub fn load(path: &Path) -> Result<Self, BaselineError> {
+        std::fs::read_to_string(path)
+            .map_err(|e| BaselineError::Read(path.to_owned(), e))?
+            .parse()
+    }
+
+    /// Whether `diagnostic` is a known one.
+    pub fn contains(&self, diagnostic: &Diagnostic) -> bool {
+        self.entries.contains(&BaselineEntry::of(diagnostic))
+    }
+
+    /// Drop the known diagnostics, leaving the new ones.
+    pub fn suppress(&self, mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics.retain(|diagnostic| !self.contains(diagnostic));
+        diagnostics
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl std::str::FromStr for LintBaseline {
+    type Err = BaselineError;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        let mut entries = HashSet::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // The file goes last, as it may contain spaces.
+            let mut fields = line.splitn(3, char::is_whitespace);
+            let (Some(code), Some(name)) = (fields.next(), fields.next()) else {
+                return Err(BaselineError::Invalid(index + 1, line.to_owned()));
+            };
+            entries.insert(BaselineEntry {
+                code: code.to_owned(),
+                name: name.to_owned(),
+                path: fields
+                    .next()
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from),
+            });
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+/// The saved form of the baseline, sorted so it diffs well.
+impl fmt::Display for LintBaseline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort();
+
+        for entry in entries {
+            write!(f, "{} {}", entry.code, entry.name)?;
+            if let Some(path) = &entry.path {
+                write!(f, " {}", path.display())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NixConfigDocument;
+
+    #[test]
+    fn matches_by_file_and_setting() {
+        let path = Path::new("/etc/nix/nix conf.d/legacy.conf");
+        let doc = NixConfigDocument::parse_string(
+            "cores = 4\ncores = 4\nbinary-caches = https://cache.nixos.org".into(),
+            Some(path),
+        )
+        .unwrap();
+        let baseline = LintBaseline::record(&doc.lint());
+        assert_eq!(
+            baseline.to_string(),
+            [
+                "deprecated-alias binary-caches /etc/nix/nix conf.d/legacy.conf",
+                "identical-override cores /etc/nix/nix conf.d/legacy.conf",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            baseline.to_string().parse::<LintBaseline>().unwrap(),
+            baseline
+        );
+
+        // Moving the assignments around doesn't matter, but other files do.
+        let edited = NixConfigDocument::parse_string(
+            "binary-caches = https://cache.nixos.org\n\ncores = 4\ncores = 4".into(),
+            Some(path),
+        )
+        .unwrap();
+        assert!(baseline.suppress(edited.lint()).is_empty());
+        let other = NixConfigDocument::parse_string(
+            "binary-caches = https://cache.nixos.org".into(),
+            Some(Path::new("/etc/nix/nix.conf")),
+        )
+        .unwrap();
+        assert_eq!(baseline.suppress(other.lint()).len(), 1);
+
+        assert!(matches!(
+            "\n# comment\ndeprecated-alias".parse::<LintBaseline>(),
+            Err(BaselineError::Invalid(3, _))
+        ));
+    }
+}
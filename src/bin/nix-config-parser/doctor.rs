@@ -0,0 +1,351 @@
+//! `nix-config-parser doctor`: a health report of this machine's Nix
+//! configuration.
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, ExitCode};
+
+use nix_config_parser::{
+    paths, Dialect, EffectiveConfig, InstallMode, NixConfig, NixConfigDocument, ParseError,
+    SettingValue,
+};
+
+/// How urgently a finding needs attention. Findings are printed most severe
+/// first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        })
+    }
+}
+
+struct Finding {
+    severity: Severity,
+    check: &'static str,
+    message: String,
+}
+
+#[derive(Default)]
+struct Report {
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    fn push(&mut self, severity: Severity, check: &'static str, message: impl fmt::Display) {
+        self.findings.push(Finding {
+            severity,
+            check,
+            message: message.to_string(),
+        });
+    }
+}
+
+pub(crate) fn doctor(args: impl Iterator<Item = String>) -> Result<ExitCode, Box<dyn Error>> {
+    let mut runtime = true;
+
+    for arg in args {
+        match arg.as_str() {
+            "--offline" => runtime = false,
+            flag => return Err(format!("unknown argument `{flag}` for `doctor`").into()),
+        }
+    }
+
+    let mut report = Report::default();
+    let files = std::iter::once(paths::system_config_file())
+        .chain(paths::user_config_files())
+        .collect::<Vec<_>>();
+
+    for file in &files {
+        check_file(&mut report, file);
+    }
+
+    match crate::effective_config() {
+        Ok(effective) => {
+            check_effective(&mut report, effective.config());
+            if runtime {
+                check_runtime(&mut report, &effective);
+            }
+        }
+        Err(e) => report.push(Severity::Error, "parse", e),
+    }
+
+    report.findings.sort_by_key(|finding| finding.severity);
+
+    println!("Nix configuration files:");
+    for file in &files {
+        let status = if file.exists() { "" } else { " (not found)" };
+        println!("  {}{status}", file.display());
+    }
+    println!();
+
+    if report.findings.is_empty() {
+        println!("No problems found.");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for finding in &report.findings {
+        println!(
+            "{}: {} [{}]",
+            finding.severity, finding.message, finding.check
+        );
+    }
+
+    let count = |severity| {
+        report
+            .findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    };
+    println!();
+    println!(
+        "{} error(s), {} warning(s), {} note(s)",
+        count(Severity::Error),
+        count(Severity::Warning),
+        count(Severity::Note)
+    );
+
+    Ok(if count(Severity::Error) > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Checks that only concern a single file: whether it parses, its lints, its
+/// skipped includes and who can write to it.
+fn check_file(report: &mut Report, file: &Path) {
+    let nix_conf = match NixConfig::parse_file(file) {
+        Ok(nix_conf) => nix_conf,
+        Err(ParseError::FileNotFound(_)) => return,
+        Err(e) => {
+            report.push(Severity::Error, "parse", e);
+            return;
+        }
+    };
+
+    if let Ok(doc) = NixConfigDocument::parse_file(file) {
+        for diagnostic in doc.lint() {
+            report.push(Severity::Note, "lint", diagnostic);
+        }
+    }
+
+    for skipped in nix_conf.skipped_includes() {
+        report.push(
+            Severity::Note,
+            "include",
+            format!(
+                "{}: optional include of '{}' was skipped, as it could not be read",
+                skipped.origin(),
+                skipped.path().display()
+            ),
+        );
+    }
+
+    check_permissions(report, file);
+}
+
+#[cfg(unix)]
+fn check_permissions(report: &mut Report, file: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = std::fs::metadata(file) {
+        if metadata.permissions().mode() & 0o002 != 0 {
+            report.push(
+                Severity::Error,
+                "security",
+                format!(
+                    "{} is writable by every user, who could use it to change how Nix builds",
+                    file.display()
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_report: &mut Report, _file: &Path) {}
+
+/// Checks of the settings Nix would use: their values, conflicts between them
+/// and settings that weaken security.
+fn check_effective(report: &mut Report, nix_conf: &NixConfig) {
+    for problem in nix_conf.validate_values() {
+        report.push(Severity::Error, "validate", problem);
+    }
+
+    if nix_conf
+        .settings()
+        .get("auto-allocate-uids")
+        .and_then(SettingValue::as_bool)
+        == Some(true)
+    {
+        match nix_conf.id_range() {
+            Ok(range) => {
+                for overlap in range.system_overlaps() {
+                    report.push(
+                        Severity::Warning,
+                        "id-range",
+                        format!(
+                            "the IDs for build users ({range}) overlap with those of {overlap}"
+                        ),
+                    );
+                }
+            }
+            Err(e) => report.push(Severity::Error, "id-range", e),
+        }
+    }
+
+    // The daemon's socket is the surest sign, but it only exists while the
+    // daemon runs.
+    let install_mode = if Path::new("/nix/var/nix/daemon-socket/socket").exists() {
+        Some(InstallMode::MultiUser)
+    } else {
+        nix_conf.install_mode()
+    };
+    for finding in install_mode.map_or_else(Vec::new, |mode| nix_conf.check_install_mode(mode)) {
+        let severity = match finding.severity() {
+            nix_config_parser::Severity::Info => Severity::Note,
+            _ => Severity::Warning,
+        };
+        report.push(severity, "install-mode", finding);
+    }
+
+    for finding in nix_conf.check_paths_exist() {
+        report.push(Severity::Warning, "paths", finding);
+    }
+
+    for problem in nix_conf.validate_platforms() {
+        report.push(Severity::Warning, "platforms", problem);
+    }
+
+    for conflict in nix_conf.detect_conflicts() {
+        report.push(Severity::Warning, "conflict", conflict);
+    }
+
+    for name in nix_conf.unknown_settings(Dialect::Nix) {
+        report.push(
+            Severity::Note,
+            "unknown",
+            format!("'{name}' is not a known Nix setting"),
+        );
+    }
+
+    let setting = |name: &str| nix_conf.settings().get(name);
+    let origin = |name: &str| {
+        nix_conf
+            .origin(name)
+            .map(|origin| format!("{origin}: "))
+            .unwrap_or_default()
+    };
+
+    if setting("require-sigs").and_then(SettingValue::as_bool) == Some(false) {
+        report.push(
+            Severity::Warning,
+            "security",
+            format!(
+                "{}'require-sigs' is disabled, so store paths are substituted without checking their signatures",
+                origin("require-sigs")
+            ),
+        );
+    }
+    if setting("sandbox").and_then(SettingValue::as_bool) == Some(false) {
+        report.push(
+            Severity::Warning,
+            "security",
+            format!(
+                "{}'sandbox' is disabled, so builds can access the network and the whole file system",
+                origin("sandbox")
+            ),
+        );
+    }
+    if setting("accept-flake-config").and_then(SettingValue::as_bool) == Some(true) {
+        report.push(
+            Severity::Warning,
+            "security",
+            format!(
+                "{}'accept-flake-config' is enabled, so any flake can change settings such as 'substituters'",
+                origin("accept-flake-config")
+            ),
+        );
+    }
+    if let Some(trusted_users) = setting("trusted-users") {
+        if trusted_users.as_list().contains(&"*") {
+            report.push(
+                Severity::Warning,
+                "security",
+                format!(
+                    "{}'trusted-users' includes '*', which gives every user root-equivalent access to the store",
+                    origin("trusted-users")
+                ),
+            );
+        }
+    }
+}
+
+/// Compare the configuration read from the files with the one the installed
+/// `nix` reports, which differ for example if the daemon wasn't restarted
+/// after an edit.
+fn check_runtime(report: &mut Report, effective: &EffectiveConfig) {
+    let Some(runtime) = runtime_config() else {
+        report.push(
+            Severity::Note,
+            "runtime",
+            "could not run `nix config show`, skipped comparing with the running configuration",
+        );
+        return;
+    };
+
+    for (name, provenance) in effective.iter() {
+        if provenance.is_default() {
+            continue;
+        }
+        let Some(actual) = runtime.settings().get(name) else {
+            continue;
+        };
+
+        let expected = provenance.value();
+        if actual.as_list() != expected.as_list() {
+            report.push(
+                Severity::Warning,
+                "runtime",
+                format!(
+                    "{}: '{name}' is '{expected}', but Nix reports '{actual}'; does the Nix daemon need a restart?",
+                    provenance.origin()
+                ),
+            );
+        }
+    }
+}
+
+fn runtime_config() -> Option<NixConfig> {
+    let attempts: [&[&str]; 2] = [
+        &[
+            "--extra-experimental-features",
+            "nix-command",
+            "config",
+            "show",
+        ],
+        &["show-config"],
+    ];
+
+    attempts.iter().find_map(|args| {
+        let output = Command::new("nix").args(*args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let output = String::from_utf8(output.stdout).ok()?;
+        NixConfig::parse_string(output, None).ok()
+    })
+}
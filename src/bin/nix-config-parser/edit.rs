@@ -0,0 +1,214 @@
+//! `nix-config-parser edit`: edit a `nix.conf` in `$EDITOR` and install it
+//! only once it is valid, like `visudo` does for `sudoers`.
+
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use nix_config_parser::{paths, NixConfigDocument, RuleRegistry, Severity, WriteError};
+
+pub(crate) fn edit(args: impl Iterator<Item = String>) -> Result<ExitCode, Box<dyn Error>> {
+    let mut file = None;
+
+    for arg in args {
+        match arg.as_str() {
+            flag if flag.starts_with('-') => {
+                return Err(format!("unknown flag `{flag}` for `edit`").into())
+            }
+            _ if file.is_some() => return Err("`edit` takes at most one FILE".into()),
+            _ => file = Some(PathBuf::from(arg)),
+        }
+    }
+    let file = file.unwrap_or_else(paths::system_config_file);
+
+    // Symlinks are followed, so that the file they point to is replaced
+    // rather than the link.
+    let target = std::fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+    if target.starts_with("/nix/store") {
+        return Err(WriteError::InNixStore(file).into());
+    }
+    let original = match std::fs::read_to_string(&file) {
+        Ok(original) => original,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("failed to read '{}': {e}", file.display()).into()),
+    };
+
+    // The copy lives next to the file, so that it can be renamed over it.
+    // Creating it also keeps others from editing the file at the same time.
+    // Only the user can read it until it is installed, as it may hold secrets
+    // such as `access-tokens`.
+    let temp = temp_path(&target);
+    let mut temp_options = OpenOptions::new();
+    temp_options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut temp_options, 0o600);
+    temp_options
+        .open(&temp)
+        .and_then(|mut copy| copy.write_all(original.as_bytes()))
+        .map_err(|e| {
+            format!(
+                "failed to create '{}' (is the file being edited already?): {e}",
+                temp.display()
+            )
+        })?;
+
+    let result = edit_copy(&file, &target, &temp, &original);
+    // Once installed, the copy is gone.
+    let _ = std::fs::remove_file(&temp);
+    result
+}
+
+/// Let the user edit `temp` until it is valid or they give up, then install
+/// it as `target`, the file `file` resolves to.
+fn edit_copy(
+    file: &Path,
+    target: &Path,
+    temp: &Path,
+    original: &str,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let config = crate::lint_config(None)?;
+    let rules = RuleRegistry::new();
+
+    loop {
+        run_editor(temp)?;
+        let contents = std::fs::read_to_string(temp)?;
+        if contents == original {
+            eprintln!("{}: unchanged", file.display());
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        // Relative includes are relative to the file as Nix reads it.
+        let valid = match NixConfigDocument::parse_string(contents.clone(), Some(file)) {
+            Ok(doc) => {
+                let diagnostics = doc.lint_with_config(&config, &rules);
+                for diagnostic in &diagnostics {
+                    eprintln!("{}: {diagnostic}", diagnostic.severity());
+                }
+                diagnostics
+                    .iter()
+                    .all(|diagnostic| diagnostic.severity() != Severity::Error)
+            }
+            Err(e) if std::io::stderr().is_terminal() => {
+                eprintln!("{}", e.render_colored(&contents));
+                false
+            }
+            Err(e) => {
+                eprintln!("{}", e.render(&contents));
+                false
+            }
+        };
+
+        if !valid {
+            match ask("What now? (e)dit again, e(x)it without saving")? {
+                'e' => continue,
+                _ => return Ok(ExitCode::FAILURE),
+            }
+        }
+
+        print!("{}", diff_lines(original, &contents));
+        match ask("Install the new file? (y)es, (e)dit again, (n)o")? {
+            'y' => break,
+            'e' => continue,
+            _ => return Ok(ExitCode::FAILURE),
+        }
+    }
+
+    if let Ok(metadata) = std::fs::metadata(target) {
+        // Changing the owner may clear the mode's setuid bits, so it comes
+        // first.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            std::os::unix::fs::chown(temp, Some(metadata.uid()), Some(metadata.gid()))
+                .map_err(|e| format!("failed to keep the owner of '{}': {e}", file.display()))?;
+        }
+        std::fs::set_permissions(temp, metadata.permissions())?;
+    }
+    std::fs::rename(temp, target)
+        .map_err(|e| WriteError::FailedToWriteFile(target.to_owned(), e))?;
+    eprintln!("{}: installed", file.display());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn temp_path(file: &Path) -> PathBuf {
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "nix.conf".into());
+    file.with_file_name(format!(".{name}.edit"))
+}
+
+/// Open `path` in `$VISUAL` or `$EDITOR`, which may include arguments, or
+/// else `vi`.
+fn run_editor(path: &Path) -> Result<(), Box<dyn Error>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .map_err(|e| format!("failed to run `{editor}`: {e}"))?;
+    if !status.success() {
+        return Err(format!("`{editor}` exited with {status}").into());
+    }
+
+    Ok(())
+}
+
+/// Ask `question` on stderr and return the first character of the answer,
+/// lowercased, or `\0` for an empty answer or the end of input.
+fn ask(question: &str) -> Result<char, Box<dyn Error>> {
+    eprint!("{question}? ");
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(answer
+        .trim()
+        .chars()
+        .next()
+        .map_or('\0', |c| c.to_ascii_lowercase()))
+}
+
+/// The lines removed from `old` (prefixed with `-`) and added in `new`
+/// (prefixed with `+`), in order.
+fn diff_lines(old: &str, new: &str) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // `common[i][j]` is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`.
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            out.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+
+    out
+}
@@ -0,0 +1,53 @@
+//! The setting filters of `nix-config-parser show`.
+
+use nix_config_parser::{SettingInfo, SettingPattern, SettingValue};
+
+#[derive(Default)]
+pub(crate) struct Filter {
+    only: Vec<SettingPattern>,
+    exclude: Vec<SettingPattern>,
+    changed_only: bool,
+}
+
+impl Filter {
+    /// Only keep settings matching one of the comma-separated glob
+    /// `patterns`.
+    pub(crate) fn only(&mut self, patterns: &str) {
+        self.only.extend(split_patterns(patterns));
+    }
+
+    /// Drop settings matching one of the comma-separated glob `patterns`.
+    pub(crate) fn exclude(&mut self, patterns: &str) {
+        self.exclude.extend(split_patterns(patterns));
+    }
+
+    /// Drop settings that have their built-in default value.
+    pub(crate) fn changed_only(&mut self) {
+        self.changed_only = true;
+    }
+
+    pub(crate) fn matches(&self, name: &str, value: &SettingValue) -> bool {
+        if !self.only.is_empty() && !self.only.iter().any(|pattern| pattern.matches(name)) {
+            return false;
+        }
+        if self.exclude.iter().any(|pattern| pattern.matches(name)) {
+            return false;
+        }
+        if self.changed_only {
+            let default = SettingInfo::lookup(name).and_then(|info| info.default_value());
+            if default == Some(value.as_str()) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+fn split_patterns(patterns: &str) -> impl Iterator<Item = SettingPattern> + '_ {
+    patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(SettingPattern::from)
+}
@@ -0,0 +1,335 @@
+//! A small command line interface to `nix-config-parser`.
+
+mod doctor;
+mod edit;
+mod filter;
+mod table;
+
+use std::error::Error;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use nix_config_parser::{
+    paths, EffectiveConfig, LintBaseline, LintConfig, NixConfig, NixConfigDocument, RuleRegistry,
+    SerializeOptions, Severity, LINT_BASELINE_FILE_NAME,
+};
+
+const USAGE: &str = "\
+Usage: nix-config-parser <COMMAND>
+
+Commands:
+  show [OPTIONS] [FILE]     Print the configuration in FILE, or the effective
+                            configuration of this machine if no FILE is given,
+                            as a table of settings with their value, origin and
+                            whether they have their default value or override
+                            an earlier one. Options:
+                              --no-color   Don't color the table (also when
+                                           NO_COLOR is set or the output isn't
+                                           a terminal)
+                              --width N    Fit the table in N columns (default:
+                                           $COLUMNS, or 100)
+                              --only PATTERNS
+                                           Only print settings matching one
+                                           of the comma-separated PATTERNS,
+                                           in which `*` matches anything and
+                                           `?` any single character
+                              --exclude PATTERNS
+                                           Don't print settings matching one
+                                           of the PATTERNS
+                              --changed-only
+                                           Don't print settings that have
+                                           their default value
+                              --conf       Print a nix.conf instead of a table
+                              --annotate   Print a nix.conf in which each
+                                           setting is followed by a comment
+                                           saying where its value came from
+  lint [OPTIONS] [FILE...]  Check each FILE (by default, the system
+                            configuration file) for likely mistakes. Rules are
+                            configured by the .nixconf-lint.toml in the
+                            current directory or one of its parents, if any.
+                            Exits with status 1 if warnings or errors remain.
+                            Options:
+                              --fix        Fix the problems that can be fixed
+                                           safely in place
+                              --config PATH
+                                           Read the rule configuration from
+                                           PATH instead
+                              --baseline PATH
+                                           Don't report the known problems
+                                           recorded in PATH (default:
+                                           .nixconf-lint-baseline, if it
+                                           exists)
+                              --update-baseline
+                                           Record all current problems in the
+                                           baseline instead of reporting them
+                            A `# nixconf-lint: ignore CODE...` comment
+                            silences the listed rules for the line it ends, or
+                            for the next line.
+  doctor [--offline]        Check this machine's Nix configuration: whether it
+                            parses, invalid values, missing files, conflicts,
+                            lints, settings that weaken security and, unless
+                            --offline is given, differences with what the
+                            installed `nix` reports. Exits with status 1 on
+                            errors.
+  edit [FILE]               Edit FILE (by default, the system configuration
+                            file) in $VISUAL or $EDITOR. When the editor
+                            exits, the new contents are checked for syntax
+                            errors and lints, and only installed once they
+                            are free of errors and the changes are confirmed.
+  support                   Print a JSON support bundle of the configuration
+                            files, their includes and the effective
+                            configuration, with secrets redacted, to attach to
+                            bug reports. Requires the `support` feature.
+";
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<ExitCode, Box<dyn Error>> {
+    let mut args = args.into_iter();
+
+    match args.next().as_deref() {
+        Some("show") => show(args).map(|()| ExitCode::SUCCESS),
+        Some("lint") => lint(args),
+        Some("doctor") => doctor::doctor(args),
+        Some("edit") => edit::edit(args),
+        Some("support") => support(args),
+        Some("-h" | "--help" | "help") => {
+            print!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(command) => Err(format!("unknown command `{command}`\n\n{USAGE}").into()),
+        None => Err(format!("no command given\n\n{USAGE}").into()),
+    }
+}
+
+fn show(args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut conf = false;
+    let mut annotate = false;
+    let mut color = std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+    let mut width = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(100);
+    let mut filter = filter::Filter::default();
+    let mut file = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--conf" => conf = true,
+            "--annotate" => {
+                conf = true;
+                annotate = true;
+            }
+            "--no-color" => color = false,
+            "--only" => filter.only(&args.next().ok_or("`--only` takes PATTERNS")?),
+            "--exclude" => filter.exclude(&args.next().ok_or("`--exclude` takes PATTERNS")?),
+            "--changed-only" => filter.changed_only(),
+            "--width" => {
+                width = args
+                    .next()
+                    .and_then(|width| width.parse().ok())
+                    .ok_or("`--width` takes a number of columns")?;
+            }
+            flag if flag.starts_with('-') => {
+                return Err(format!("unknown flag `{flag}` for `show`").into())
+            }
+            _ if file.is_some() => return Err("`show` takes at most one FILE".into()),
+            _ => file = Some(PathBuf::from(arg)),
+        }
+    }
+
+    if conf {
+        let mut nix_conf = match file {
+            Some(file) => NixConfig::parse_file(&file)?,
+            None => effective_config()?.into_config(),
+        };
+        nix_conf
+            .settings_mut()
+            .retain(|name, value| filter.matches(name, value));
+
+        print!(
+            "{}",
+            nix_conf.to_string_with_options(&SerializeOptions::new().annotate(annotate))?
+        );
+        return Ok(());
+    }
+
+    let effective = match file {
+        Some(file) => {
+            // Report a missing or broken file, which `EffectiveConfig` would
+            // skip or only report without its path.
+            NixConfig::parse_file(&file)?;
+            EffectiveConfig::compute(&[file], std::iter::empty::<(&str, &str)>(), &[] as &[&str])?
+        }
+        None => effective_config()?,
+    };
+
+    print!(
+        "{}",
+        table::render(
+            effective
+                .iter()
+                .filter(|(name, provenance)| filter.matches(name, provenance.value())),
+            &table::TableOptions { color, width }
+        )
+    );
+
+    Ok(())
+}
+
+fn lint(args: impl Iterator<Item = String>) -> Result<ExitCode, Box<dyn Error>> {
+    let mut fix = false;
+    let mut config_file = None;
+    let mut baseline_file = None;
+    let mut update_baseline = false;
+    let mut files = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fix" => fix = true,
+            "--config" => {
+                config_file = Some(PathBuf::from(args.next().ok_or("`--config` takes a PATH")?))
+            }
+            "--baseline" => {
+                baseline_file = Some(PathBuf::from(
+                    args.next().ok_or("`--baseline` takes a PATH")?,
+                ))
+            }
+            "--update-baseline" => update_baseline = true,
+            flag if flag.starts_with('-') => {
+                return Err(format!("unknown flag `{flag}` for `lint`").into())
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        files.push(paths::system_config_file());
+    }
+
+    let config = lint_config(config_file)?;
+    let rules = RuleRegistry::new();
+    let lint = |doc: &NixConfigDocument| doc.lint_with_config(&config, &rules);
+
+    let baseline_file = baseline_file.unwrap_or_else(|| PathBuf::from(LINT_BASELINE_FILE_NAME));
+    let baseline = if update_baseline || !baseline_file.exists() {
+        LintBaseline::new()
+    } else {
+        LintBaseline::load(&baseline_file)?
+    };
+    let mut recorded = Vec::new();
+
+    let mut problems = 0;
+    for file in files {
+        let mut doc = NixConfigDocument::parse_file(&file)?;
+        let mut diagnostics = lint(&doc);
+
+        if fix {
+            // A fix can reveal new problems, such as a renamed alias now
+            // overriding its canonical name with the same value.
+            let mut fixed = 0;
+            loop {
+                let safe = diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.fix().is_some_and(|fix| fix.is_safe()));
+                match doc.apply_fixes(safe)? {
+                    0 => break,
+                    n => fixed += n,
+                }
+                diagnostics = lint(&doc);
+            }
+
+            if fixed > 0 {
+                std::fs::write(&file, doc.to_string())?;
+                eprintln!("{}: fixed {fixed} problem(s)", file.display());
+            }
+        }
+
+        if update_baseline {
+            recorded.extend(diagnostics);
+            continue;
+        }
+
+        let diagnostics = baseline.suppress(diagnostics);
+        for diagnostic in &diagnostics {
+            println!("{}: {diagnostic}", diagnostic.severity());
+        }
+        problems += diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity() != Severity::Info)
+            .count();
+    }
+
+    if update_baseline {
+        let baseline = LintBaseline::record(&recorded);
+        std::fs::write(&baseline_file, baseline.to_string())?;
+        eprintln!(
+            "{}: recorded {} known problem(s)",
+            baseline_file.display(),
+            baseline.len()
+        );
+    }
+
+    Ok(if problems == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// The lint configuration in `file`, or else the one found from the current
+/// directory.
+#[cfg(feature = "toml")]
+fn lint_config(file: Option<PathBuf>) -> Result<LintConfig, Box<dyn Error>> {
+    Ok(match file {
+        Some(file) => LintConfig::load(&file)?,
+        None => LintConfig::find(&std::env::current_dir()?)?.unwrap_or_default(),
+    })
+}
+
+#[cfg(not(feature = "toml"))]
+fn lint_config(file: Option<PathBuf>) -> Result<LintConfig, Box<dyn Error>> {
+    match file {
+        Some(_) => Err("`--config` needs nix-config-parser built with the `toml` feature".into()),
+        None => Ok(LintConfig::new()),
+    }
+}
+
+#[cfg(feature = "support")]
+fn support(mut args: impl Iterator<Item = String>) -> Result<ExitCode, Box<dyn Error>> {
+    if let Some(arg) = args.next() {
+        return Err(format!("unknown argument `{arg}` for `support`").into());
+    }
+
+    println!("{}", nix_config_parser::support::collect().to_json());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(feature = "support"))]
+fn support(_args: impl Iterator<Item = String>) -> Result<ExitCode, Box<dyn Error>> {
+    Err("`support` requires nix-config-parser to be built with the `support` feature".into())
+}
+
+/// The configuration Nix would use on this machine, from its configuration
+/// files and `NIX_CONFIG`.
+fn effective_config() -> Result<EffectiveConfig, Box<dyn Error>> {
+    let mut files = vec![paths::system_config_file()];
+    files.extend(paths::user_config_files());
+
+    Ok(EffectiveConfig::compute(
+        &files,
+        std::env::vars(),
+        &[] as &[&str],
+    )?)
+}
@@ -0,0 +1,121 @@
+//! The table `nix-config-parser show` prints by default.
+
+use nix_config_parser::{Origin, SettingProvenance};
+
+const HEADERS: [&str; 4] = ["SETTING", "VALUE", "ORIGIN", "STATUS"];
+const SEPARATOR: &str = "  ";
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+pub(crate) struct TableOptions {
+    pub(crate) color: bool,
+    /// The width to fit the table in, in characters. Values are truncated to
+    /// fit, but the other columns never are.
+    pub(crate) width: usize,
+}
+
+/// Render `settings` as an aligned table with their value, origin and whether
+/// they have their default value or overrode an earlier one.
+pub(crate) fn render<'a>(
+    settings: impl Iterator<Item = (&'a str, &'a SettingProvenance)>,
+    options: &TableOptions,
+) -> String {
+    let rows = settings
+        .map(|(name, provenance)| {
+            [
+                name.to_owned(),
+                provenance.value().to_string(),
+                provenance.origin().to_string(),
+                status(provenance).to_owned(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = HEADERS.map(|header| header.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    // Whatever is left after the other columns goes to the value, but always
+    // leave room for a little of it.
+    let others = widths[0] + widths[2] + widths[3] + 3 * SEPARATOR.len();
+    widths[1] = widths[1].min(
+        options
+            .width
+            .saturating_sub(others)
+            .max(HEADERS[1].len() + 2),
+    );
+
+    let mut out = String::new();
+    let header = HEADERS.map(ToOwned::to_owned);
+    push_row(&mut out, &header, &widths, options.color.then_some(BOLD));
+    for row in &rows {
+        let style = match row[3].as_str() {
+            "default" => Some(DIM),
+            "overridden" => Some(YELLOW),
+            _ => None,
+        };
+        push_row(&mut out, row, &widths, style.filter(|_| options.color));
+    }
+
+    out
+}
+
+/// `default` if the setting has Nix's built-in default, `overridden` if its
+/// value replaced one from an earlier configuration file, or `set`.
+fn status(provenance: &SettingProvenance) -> &'static str {
+    if provenance.is_default() {
+        "default"
+    } else if provenance
+        .overridden()
+        .iter()
+        .any(|(_, origin)| *origin != Origin::Default)
+    {
+        "overridden"
+    } else {
+        "set"
+    }
+}
+
+fn push_row(out: &mut String, row: &[String; 4], widths: &[usize; 4], style: Option<&str>) {
+    if let Some(style) = style {
+        out.push_str(style);
+    }
+
+    let mut line = String::new();
+    for (index, (cell, width)) in row.iter().zip(widths).enumerate() {
+        if index > 0 {
+            line.push_str(SEPARATOR);
+        }
+        let cell = truncate(cell, *width);
+        line.push_str(&cell);
+        if index < row.len() - 1 {
+            line.extend(std::iter::repeat_n(' ', width - cell.chars().count()));
+        }
+    }
+    out.push_str(&line);
+
+    if style.is_some() {
+        out.push_str(RESET);
+    }
+    out.push('\n');
+}
+
+/// Shorten `cell` to at most `width` characters, ending it with `…` if it
+/// had to be cut.
+fn truncate(cell: &str, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell.to_owned();
+    }
+
+    let mut truncated = cell
+        .chars()
+        .take(width.saturating_sub(1))
+        .collect::<String>();
+    truncated.push('…');
+    truncated
+}
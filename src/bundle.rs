@@ -0,0 +1,324 @@
+//! Portable copies of a Nix configuration, for machines that can't reach the
+//! files it references, such as air-gapped ones.
+//!
+//! A bundle is a directory holding a single `nix.conf`, with every include
+//! inlined, and a `files` directory holding copies of the files its settings
+//! reference, at their original absolute paths: `ssl-cert-file =
+//! /etc/ssl/ca.pem` becomes `ssl-cert-file = files/etc/ssl/ca.pem`.
+//!
+//! [`restore`] writes the files of a bundle back, under a new root if
+//! needed.
+//!
+//! Secrets stay behind: the values of secret settings such as `access-tokens`
+//! are [redacted](crate::support::REDACTED), and the files of `netrc-file`
+//! and `secret-key-files` are not copied, so those settings keep their
+//! original paths.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::existence::SANDBOX_PATHS;
+use crate::secret::{is_secret, redact};
+use crate::{
+    NixConfig, ParseError, SerializeError, SettingDiff, SettingInfo, SettingType, SettingValue,
+};
+
+/// The name of the inlined `nix.conf` in a bundle.
+pub const CONFIG_FILE_NAME: &str = "nix.conf";
+
+/// The directory of a bundle holding the copied files.
+pub const FILES_DIR_NAME: &str = "files";
+
+/// Settings whose files hold secrets, and are not copied into bundles.
+const SECRET_FILES: &[&str] = &["netrc-file", "secret-key-files"];
+
+/// A bundle written by [`collect`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bundle {
+    dir: PathBuf,
+    copied: Vec<PathBuf>,
+    excluded: Vec<PathBuf>,
+}
+
+impl Bundle {
+    /// The directory holding the bundle.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The original paths of the files copied into the bundle.
+    pub fn copied(&self) -> &[PathBuf] {
+        &self.copied
+    }
+
+    /// The paths of files holding secrets, which were not copied.
+    pub fn excluded(&self) -> &[PathBuf] {
+        &self.excluded
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Serialize(#[from] SerializeError),
+    #[error("failed to copy '{0}' into the bundle: {1}")]
+    FailedToCopy(PathBuf, #[source] std::io::Error),
+    #[error("failed to write '{0}': {1}")]
+    FailedToWriteFile(PathBuf, #[source] std::io::Error),
+    #[error("'{0}' does not parse to the bundled configuration")]
+    Mismatch(PathBuf, Vec<SettingDiff>),
+}
+
+/// Write a bundle of the `nix.conf` at `config_path` into `dir`, creating it
+/// if needed. Every file referenced by a path-valued setting must exist,
+/// except for paths inside the build sandbox, like those in `sandbox-paths`,
+/// which are left as they are.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::{bundle, NixConfig};
+///
+/// let temp_dir = tempfile::TempDir::new()?;
+/// let cert = temp_dir.path().join("ca.pem");
+/// std::fs::write(&cert, "-----BEGIN CERTIFICATE-----")?;
+/// let config = temp_dir.path().join("nix.conf");
+/// std::fs::write(
+///     &config,
+///     format!("ssl-cert-file = {}\nsecret-key-files = /etc/nix/key.sec\naccess-tokens = github.com=ghp_123", cert.display()),
+/// )?;
+///
+/// let bundle = bundle::collect(&config, &temp_dir.path().join("bundle"))?;
+/// assert_eq!(bundle.copied(), [cert.as_path()]);
+/// assert_eq!(bundle.excluded(), [std::path::Path::new("/etc/nix/key.sec")]);
+///
+/// let bundled = NixConfig::parse_file(&bundle.dir().join(bundle::CONFIG_FILE_NAME))?;
+/// let bundled_cert = std::path::Path::new(bundle::FILES_DIR_NAME)
+///     .join(cert.strip_prefix("/")?);
+/// assert_eq!(bundled.settings().get("ssl-cert-file").unwrap(), bundled_cert.to_str().unwrap());
+/// assert!(bundle.dir().join(bundled_cert).is_file());
+/// assert_eq!(bundled.settings().get("access-tokens").unwrap(), "github.com=<redacted>");
+/// # Ok(())
+/// # }
+/// ```
+pub fn collect(config_path: &Path, dir: &Path) -> Result<Bundle, BundleError> {
+    let mut nix_conf = NixConfig::parse_file(config_path)?;
+    let mut bundle = Bundle {
+        dir: dir.to_owned(),
+        copied: Vec::new(),
+        excluded: Vec::new(),
+    };
+
+    for (name, value) in nix_conf.settings_mut() {
+        if is_secret(name) {
+            *value = redact(name, value);
+            continue;
+        }
+
+        let Some(info) = SettingInfo::lookup(name) else {
+            continue;
+        };
+        if SANDBOX_PATHS.contains(&info.name()) {
+            continue;
+        }
+        let paths = match info.setting_type() {
+            SettingType::Path => vec![value.as_str()],
+            SettingType::PathList => value.as_list(),
+            _ => continue,
+        };
+
+        let mut bundled_paths = Vec::new();
+        for path in paths {
+            if !path.starts_with('/') {
+                bundled_paths.push(path.to_owned());
+                continue;
+            }
+            if SECRET_FILES.contains(&info.name()) {
+                bundle.excluded.push(PathBuf::from(path));
+                bundled_paths.push(path.to_owned());
+                continue;
+            }
+
+            let bundled = Path::new(FILES_DIR_NAME).join(path.trim_start_matches('/'));
+            copy(Path::new(path), &dir.join(&bundled))
+                .map_err(|e| BundleError::FailedToCopy(PathBuf::from(path), e))?;
+            if !bundle.copied.iter().any(|copied| copied == Path::new(path)) {
+                bundle.copied.push(PathBuf::from(path));
+            }
+            bundled_paths.push(bundled.to_string_lossy().into_owned());
+        }
+
+        *value = SettingValue::from(bundled_paths.join(" "));
+    }
+
+    let contents = nix_conf.to_string_checked()?;
+    let config = dir.join(CONFIG_FILE_NAME);
+    std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&config, contents))
+        .map_err(|e| BundleError::FailedToWriteFile(config, e))?;
+
+    Ok(bundle)
+}
+
+/// Restore the bundle in `dir`: copy its files under `root` (`/` to put
+/// them back where they were collected from), and write its `nix.conf` to
+/// `config_path` with the paths rewritten to point at the restored files.
+///
+/// The files keep the permissions they have in the bundle, and the
+/// `nix.conf` is made readable by everyone, like Nix expects. The written
+/// `nix.conf` is parsed back and must [canonicalize](NixConfig::canonicalize)
+/// to the same settings, or this fails with [`BundleError::Mismatch`].
+/// Redacted values are restored as they are, and need to be filled in
+/// separately. Returns the restored configuration.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::bundle;
+///
+/// let temp_dir = tempfile::TempDir::new()?;
+/// let cert = temp_dir.path().join("ca.pem");
+/// std::fs::write(&cert, "-----BEGIN CERTIFICATE-----")?;
+/// let config = temp_dir.path().join("nix.conf");
+/// std::fs::write(&config, format!("ssl-cert-file = {}\ncores = 4", cert.display()))?;
+/// let bundle = bundle::collect(&config, &temp_dir.path().join("bundle"))?;
+///
+/// let root = temp_dir.path().join("root");
+/// let restored_config = root.join("etc/nix/nix.conf");
+/// let restored = bundle::restore(bundle.dir(), &root, &restored_config)?;
+///
+/// let restored_cert = root.join(cert.strip_prefix("/")?);
+/// assert!(restored_cert.is_file());
+/// assert_eq!(
+///     restored.settings().get("ssl-cert-file").unwrap(),
+///     restored_cert.to_str().unwrap()
+/// );
+/// assert!(restored_config.is_file());
+/// # Ok(())
+/// # }
+/// ```
+pub fn restore(dir: &Path, root: &Path, config_path: &Path) -> Result<NixConfig, BundleError> {
+    let mut nix_conf = NixConfig::parse_file(&dir.join(CONFIG_FILE_NAME))?;
+    nix_conf.relocate_paths(FILES_DIR_NAME, &root.to_string_lossy());
+
+    let files = dir.join(FILES_DIR_NAME);
+    if files.is_dir() {
+        copy(&files, root).map_err(|e| BundleError::FailedToWriteFile(root.to_owned(), e))?;
+    }
+
+    let contents = nix_conf.to_string_checked()?;
+    write_config(config_path, &contents)
+        .map_err(|e| BundleError::FailedToWriteFile(config_path.to_owned(), e))?;
+
+    let written = NixConfig::parse_file(config_path)?;
+    let diff = written.canonicalize().diff(&nix_conf.canonicalize());
+    if !diff.is_empty() {
+        return Err(BundleError::Mismatch(config_path.to_owned(), diff));
+    }
+
+    Ok(written)
+}
+
+/// Write `contents` to the `nix.conf` at `path`, readable by everyone.
+fn write_config(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o644))?;
+    }
+
+    Ok(())
+}
+
+/// Copy the file or directory at `from` to `to`, creating the parent
+/// directories of `to`.
+fn copy(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    if !from.is_dir() {
+        return std::fs::copy(from, to).map(drop);
+    }
+
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        copy(&entry.path(), &to.join(entry.file_name()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inlines_includes_and_copies_directories() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let plugins = temp_dir.path().join("plugins");
+        std::fs::create_dir_all(plugins.join("lib")).unwrap();
+        std::fs::write(plugins.join("lib/plugin.so"), "").unwrap();
+        std::fs::write(
+            temp_dir.path().join("extra.conf"),
+            format!("plugin-files = {}\n", plugins.display()),
+        )
+        .unwrap();
+        let config = temp_dir.path().join("nix.conf");
+        std::fs::write(
+            &config,
+            "include extra.conf\nsandbox-paths = /bin/sh=/does/not/exist\n",
+        )
+        .unwrap();
+
+        let dir = temp_dir.path().join("bundle");
+        let bundle = collect(&config, &dir).unwrap();
+        assert_eq!(bundle.copied(), [plugins]);
+
+        let bundled = NixConfig::parse_file(&dir.join(CONFIG_FILE_NAME)).unwrap();
+        assert!(bundled.includes().is_empty());
+        assert_eq!(
+            bundled.settings().get("sandbox-paths").unwrap(),
+            "/bin/sh=/does/not/exist"
+        );
+        let plugin = dir
+            .join(bundled.settings().get("plugin-files").unwrap().as_str())
+            .join("lib/plugin.so");
+        assert!(plugin.is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restores_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let hook = temp_dir.path().join("post-build-hook.sh");
+        std::fs::write(&hook, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o750)).unwrap();
+        let config = temp_dir.path().join("nix.conf");
+        std::fs::write(&config, format!("post-build-hook = {}\n", hook.display())).unwrap();
+        let bundle = collect(&config, &temp_dir.path().join("bundle")).unwrap();
+
+        let root = temp_dir.path().join("root");
+        let restored_config = root.join("nix.conf");
+        let restored = restore(bundle.dir(), &root, &restored_config).unwrap();
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let restored_hook = restored.settings().get("post-build-hook").unwrap();
+        assert_eq!(mode(Path::new(restored_hook.as_str())), 0o750);
+        assert_eq!(mode(&restored_config), 0o644);
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::{parse_contents, read_file, NixConfig, ParseError, ParseOptions};
+
+/// A cache of parsed `nix.conf` files, for long-running processes that read
+/// the same configuration over and over.
+///
+/// A cached config is reused until the modification time or size of the file,
+/// or of any file it includes, changes. Optional includes that didn't exist
+/// are watched as well, so creating them invalidates the cache.
+#[derive(Debug, Default)]
+pub struct NixConfigCache {
+    options: ParseOptions,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    config: Arc<NixConfig>,
+    stamps: Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+impl NixConfigCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache that parses files using the provided [`ParseOptions`].
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            options,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The parsed config at `path`, from the cache if none of the files it was
+    /// parsed from changed since.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::sync::Arc;
+    ///
+    /// use nix_config_parser::NixConfigCache;
+    ///
+    /// let temp_dir = tempfile::TempDir::new()?;
+    /// let path = temp_dir.path().join("nix.conf");
+    /// std::fs::write(&path, "cores = 4\n")?;
+    ///
+    /// let mut cache = NixConfigCache::new();
+    /// let first = cache.get(&path)?;
+    /// let second = cache.get(&path)?;
+    /// assert!(Arc::ptr_eq(&first, &second));
+    ///
+    /// std::fs::write(&path, "cores = 16\n")?;
+    /// assert_eq!(cache.get(&path)?.settings().get("cores").unwrap(), "16");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get(&mut self, path: &Path) -> Result<Arc<NixConfig>, ParseError> {
+        if let Some(entry) = self.entries.get(path) {
+            if entry
+                .stamps
+                .iter()
+                .all(|(path, stamp)| FileStamp::of(path) == *stamp)
+            {
+                return Ok(Arc::clone(&entry.config));
+            }
+        }
+
+        let root_stamp = FileStamp::of(path);
+        let contents = read_file(path)?;
+        let parsed = parse_contents(&contents, Some(path), &self.options)?;
+
+        let mut stamps = vec![(path.to_owned(), root_stamp)];
+        for file in &parsed.included_files {
+            stamps.push((file.clone(), FileStamp::of(file)));
+        }
+        for skipped in &parsed.skipped_includes {
+            stamps.push((skipped.path().to_owned(), FileStamp::of(skipped.path())));
+        }
+
+        let config = Arc::new(NixConfig::from_parsed(parsed));
+        self.entries.insert(
+            path.to_owned(),
+            CacheEntry {
+                config: Arc::clone(&config),
+                stamps,
+            },
+        );
+
+        Ok(config)
+    }
+
+    /// Forget the cached config at `path`.
+    pub fn invalidate(&mut self, path: &Path) {
+        self.entries.remove(path);
+    }
+
+    /// Forget every cached config.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalidates_when_includes_change() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().join("nix.conf");
+        let included = temp_dir.path().join("included.conf");
+        let optional = temp_dir.path().join("optional.conf");
+
+        std::fs::write(&included, "cores = 4\n").unwrap();
+        std::fs::write(
+            &root,
+            format!(
+                "include {}\n!include {}\n",
+                included.display(),
+                optional.display()
+            ),
+        )
+        .unwrap();
+
+        let mut cache = NixConfigCache::new();
+        let first = cache.get(&root).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.get(&root).unwrap()));
+
+        std::fs::write(&included, "cores = 16\n").unwrap();
+        let second = cache.get(&root).unwrap();
+        assert_eq!(second.settings().get("cores"), Some(&"16".into()));
+
+        std::fs::write(&optional, "max-jobs = 2\n").unwrap();
+        let third = cache.get(&root).unwrap();
+        assert_eq!(third.settings().get("max-jobs"), Some(&"2".into()));
+        assert!(Arc::ptr_eq(&third, &cache.get(&root).unwrap()));
+
+        cache.invalidate(&root);
+        assert!(!Arc::ptr_eq(&third, &cache.get(&root).unwrap()));
+    }
+}
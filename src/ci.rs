@@ -0,0 +1,78 @@
+use crate::{NixConfig, ParseError};
+
+impl NixConfig {
+    /// The config Nix ends up with in a GitHub Actions job that installs Nix
+    /// using `install-nix-action` with this config as the base and `fragment`
+    /// as its `extra_nix_config` input, which gets appended to the `nix.conf`.
+    ///
+    /// The result can be checked like any other config, for example against
+    /// the `nixConfig` of the repository's flake to audit what CI actually
+    /// uses.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{DriftFinding, DriftReport, NixConfig};
+    ///
+    /// let base = NixConfig::parse_string("max-jobs = auto\ntrusted-users = root runner".into(), None)?;
+    /// let fragment = "
+    ///     experimental-features = nix-command flakes
+    ///     extra-substituters = https://example.cachix.org
+    /// ";
+    /// let ci = base.with_extra_nix_config(fragment)?;
+    ///
+    /// let flake_nix_config = NixConfig::parse_string(
+    ///     "extra-substituters = https://example.cachix.org https://other.cachix.org".into(),
+    ///     None,
+    /// )?;
+    /// let report = DriftReport::between(
+    ///     &flake_nix_config,
+    ///     &ci.subset(flake_nix_config.settings().keys()),
+    /// );
+    ///
+    /// assert_eq!(
+    ///     report.findings(),
+    ///     &[DriftFinding::ListItems {
+    ///         name: "extra-substituters".into(),
+    ///         missing: vec!["https://other.cachix.org".into()],
+    ///         extra: vec![],
+    ///     }]
+    /// );
+    /// assert!(ci.detect_conflicts().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_extra_nix_config(&self, fragment: &str) -> Result<NixConfig, ParseError> {
+        let mut merged = self.clone();
+        merged.apply_string(fragment, None)?;
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_fragment_like_install_nix_action() {
+        let base = NixConfig::parse_string(
+            "substituters = https://cache.nixos.org/\nsandbox = true".into(),
+            None,
+        )
+        .unwrap();
+
+        let ci = base
+            .with_extra_nix_config(
+                "  sandbox = false\n  extra-substituters = https://example.cachix.org\n",
+            )
+            .unwrap();
+
+        assert_eq!(
+            ci.settings().get("substituters"),
+            Some(&"https://cache.nixos.org/ https://example.cachix.org".into())
+        );
+        assert_eq!(ci.settings().get("sandbox"), Some(&"false".into()));
+        assert!(base.with_extra_nix_config("not a setting").is_err());
+    }
+}
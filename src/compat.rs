@@ -0,0 +1,227 @@
+//! Differential testing against Nix's own parser, to keep this crate parsing
+//! exactly like Nix does.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{NixConfig, SettingInfo, SettingValue};
+
+/// A way in which Nix and this crate disagree about a config, see [`compare`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "kebab-case")
+)]
+pub enum Mismatch {
+    /// Nix rejected the config, but this crate accepted it.
+    RejectedByNix { message: String },
+    /// This crate rejected the config, but Nix accepted it.
+    RejectedByParser { message: String },
+    /// Nix has a different value for the setting than this crate parsed, or
+    /// doesn't show it at all (`nix` is `None`).
+    Value {
+        name: String,
+        nix: Option<String>,
+        parsed: SettingValue,
+    },
+}
+
+/// Why the comparison couldn't be made.
+#[derive(thiserror::Error, Debug)]
+pub enum CompatError {
+    #[error("failed to set up a scratch config directory: {0}")]
+    Scratch(#[source] std::io::Error),
+    #[error("failed to run '{0}': {1}")]
+    Spawn(PathBuf, #[source] std::io::Error),
+}
+
+/// Have the `nix` binary parse `contents` as its only config file, and
+/// compare the result of `nix config show` with what this crate parses.
+///
+/// Nix is run with `NIX_CONF_DIR` pointing at a scratch directory holding
+/// `contents` as `nix.conf`, without user config files or `NIX_CONFIG`, and
+/// with the `nix-command` experimental feature enabled to be able to run
+/// `nix config show`. A relative `include` is resolved against the scratch
+/// directory, like it would be by Nix.
+///
+/// Only the settings in `contents` are compared, as Nix also shows every
+/// other setting with its default value. Lists are compared item by item, and
+/// `extra-<name>` only needs its items to be in `<name>`. Settings neither Nix
+/// nor the [registry](SettingInfo) know are skipped, as Nix ignores them with
+/// a warning. If both reject the config, there is no mismatch.
+///
+/// ```rust,no_run
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::compat;
+///
+/// let mismatches = compat::compare("nix", "cores = 4\nsubstituters = https://cache.nixos.org")?;
+/// assert!(mismatches.is_empty(), "{mismatches:?}");
+/// # Ok(())
+/// # }
+/// ```
+pub fn compare(nix: impl AsRef<Path>, contents: &str) -> Result<Vec<Mismatch>, CompatError> {
+    let scratch = ScratchDir::new().map_err(CompatError::Scratch)?;
+    let path = scratch.0.join("nix.conf");
+    std::fs::write(&path, contents).map_err(CompatError::Scratch)?;
+
+    let nix = nix.as_ref();
+    let output = Command::new(nix)
+        .args([
+            "--extra-experimental-features",
+            "nix-command",
+            "config",
+            "show",
+        ])
+        .env("NIX_CONF_DIR", &scratch.0)
+        .env("NIX_USER_CONF_FILES", scratch.0.join("user.conf"))
+        .env_remove("NIX_CONFIG")
+        .output()
+        .map_err(|e| CompatError::Spawn(nix.to_owned(), e))?;
+
+    let parsed = NixConfig::parse_file(&path);
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        return Ok(match parsed {
+            Ok(_) => vec![Mismatch::RejectedByNix { message }],
+            Err(_) => Vec::new(),
+        });
+    }
+
+    let parsed = match parsed {
+        Ok(parsed) => parsed.canonicalize(),
+        Err(e) => {
+            return Ok(vec![Mismatch::RejectedByParser {
+                message: e.to_string(),
+            }])
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let shown = stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(" =")?;
+            Some((name.trim(), value.trim()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut mismatches = Vec::new();
+    for (name, value) in parsed.settings() {
+        let base = name.strip_prefix("extra-").unwrap_or(name);
+        let nix_value = shown.get(base).copied();
+
+        let matches = match nix_value {
+            None => SettingInfo::lookup(name).is_none(),
+            Some(nix_value) => values_match(name, value, nix_value),
+        };
+
+        if !matches {
+            mismatches.push(Mismatch::Value {
+                name: name.clone(),
+                nix: nix_value.map(ToOwned::to_owned),
+                parsed: value.clone(),
+            });
+        }
+    }
+
+    Ok(mismatches)
+}
+
+/// Whether Nix's `nix_value` for `name` agrees with the `parsed` value.
+fn values_match(name: &str, parsed: &SettingValue, nix_value: &str) -> bool {
+    let is_list = SettingInfo::lookup(name).is_some_and(|info| info.setting_type().is_list());
+    if !is_list {
+        return parsed.as_str() == nix_value;
+    }
+
+    let parsed = parsed.as_list();
+    let mut nix_items = nix_value.split_whitespace().collect::<Vec<_>>();
+    if name.ends_with("experimental-features") && !parsed.contains(&"nix-command") {
+        // Enabled to be able to run `nix config show`
+        nix_items.retain(|item| *item != "nix-command");
+    }
+
+    if name.starts_with("extra-") {
+        parsed.iter().all(|item| nix_items.contains(item))
+    } else {
+        parsed == nix_items
+    }
+}
+
+/// A directory that is removed again when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new() -> std::io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir().join(format!(
+            "nix-config-parser-compat-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir(&dir)?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn reports_mismatches() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let nix = temp_dir.path().join("nix");
+        std::fs::write(
+            &nix,
+            r#"#!/bin/sh
+if grep -q illegal "$NIX_CONF_DIR/nix.conf"; then
+  echo "error: illegal configuration line" >&2
+  exit 1
+fi
+printf 'cores = 4\nexperimental-features = flakes nix-command\nmax-jobs = 1\nsubstituters = https://cache.nixos.org/ https://a.example.com\n'
+"#,
+        )
+        .unwrap();
+        std::fs::set_permissions(&nix, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mismatches = compare(
+            &nix,
+            "cores = 4\nmax-jobs = 2\nextra-experimental-features = flakes\nextra-substituters = https://a.example.com\nmade-up = 1\n",
+        )
+        .unwrap();
+        assert_eq!(
+            mismatches,
+            [Mismatch::Value {
+                name: "max-jobs".into(),
+                nix: Some("1".into()),
+                parsed: "2".into(),
+            }]
+        );
+
+        assert!(matches!(
+            &compare(&nix, "cores 4\n").unwrap()[..],
+            [Mismatch::RejectedByParser { .. }]
+        ));
+        assert!(compare(&nix, "illegal\n").unwrap().is_empty());
+        assert!(matches!(
+            &compare(&nix, "# illegal\n").unwrap()[..],
+            [Mismatch::RejectedByNix { message }] if message.contains("illegal")
+        ));
+    }
+}
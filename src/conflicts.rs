@@ -0,0 +1,326 @@
+use std::fmt;
+
+use crate::{NixConfig, SettingInfo, SettingType};
+
+/// Settings that only have an effect with an experimental feature enabled,
+/// and that feature. Boolean settings only need the feature when enabled,
+/// others when set to a non-empty value.
+const FEATURE_REQUIREMENTS: &[(&str, &str)] = &[
+    ("accept-flake-config", "flakes"),
+    ("commit-lockfile-summary", "flakes"),
+    ("flake-registry", "flakes"),
+    ("use-registries", "flakes"),
+    ("auto-allocate-uids", "auto-allocate-uids"),
+    ("start-id", "auto-allocate-uids"),
+    ("id-count", "auto-allocate-uids"),
+    ("use-cgroups", "cgroups"),
+];
+
+/// A combination of settings within a single [`NixConfig`] that contradict
+/// each other.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Conflict {
+    /// Both a setting and its legacy alias are set, to different values.
+    AliasMismatch { setting: String, alias: String },
+    /// The sandbox is disabled, but sandbox paths are configured.
+    SandboxPathsWithoutSandbox { paths_setting: String },
+    /// Substitution is disabled, but substituters are configured.
+    SubstitutersWithoutSubstitution { substituters_setting: String },
+    /// A setting is enabled that only has an effect with an experimental
+    /// feature that isn't enabled.
+    MissingExperimentalFeature { setting: String, feature: String },
+}
+
+impl Conflict {
+    /// The names of the settings involved in this conflict.
+    pub fn settings(&self) -> Vec<&str> {
+        match self {
+            Conflict::AliasMismatch { setting, alias } => vec![setting, alias],
+            Conflict::SandboxPathsWithoutSandbox { paths_setting } => {
+                vec!["sandbox", paths_setting]
+            }
+            Conflict::SubstitutersWithoutSubstitution {
+                substituters_setting,
+            } => vec!["substitute", substituters_setting],
+            Conflict::MissingExperimentalFeature { setting, .. } => {
+                vec![setting, "experimental-features"]
+            }
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::AliasMismatch { setting, alias } => write!(
+                f,
+                "'{alias}' is a legacy alias of '{setting}', but they are set to different values"
+            ),
+            Conflict::SandboxPathsWithoutSandbox { paths_setting } => {
+                write!(f, "'{paths_setting}' is set, but 'sandbox' is disabled")
+            }
+            Conflict::SubstitutersWithoutSubstitution {
+                substituters_setting,
+            } => write!(
+                f,
+                "'{substituters_setting}' is set, but 'substitute' is disabled"
+            ),
+            Conflict::MissingExperimentalFeature { setting, feature } => write!(
+                f,
+                "'{setting}' is enabled, but requires the '{feature}' experimental feature"
+            ),
+        }
+    }
+}
+
+impl NixConfig {
+    /// Detect combinations of settings in this config that contradict each
+    /// other, such as `sandbox = false` alongside `sandbox-paths`.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{Conflict, NixConfig};
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org\nbinary-caches = https://example.com".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.detect_conflicts(),
+    ///     vec![Conflict::AliasMismatch {
+    ///         setting: "substituters".into(),
+    ///         alias: "binary-caches".into(),
+    ///     }]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_conflicts(&self) -> Vec<Conflict> {
+        let settings = self.settings();
+        let mut conflicts = Vec::new();
+
+        for info in SettingInfo::all() {
+            let Some(value) = settings.get(info.name()) else {
+                continue;
+            };
+
+            for alias in info.aliases() {
+                if settings
+                    .get(*alias)
+                    .is_some_and(|alias_value| alias_value.as_list() != value.as_list())
+                {
+                    conflicts.push(Conflict::AliasMismatch {
+                        setting: info.name().into(),
+                        alias: alias.to_string(),
+                    });
+                }
+            }
+        }
+
+        if settings.get("sandbox").and_then(|v| v.as_bool()) == Some(false) {
+            for paths_setting in ["sandbox-paths", "extra-sandbox-paths"] {
+                if settings
+                    .get(paths_setting)
+                    .is_some_and(|v| !v.as_list().is_empty())
+                {
+                    conflicts.push(Conflict::SandboxPathsWithoutSandbox {
+                        paths_setting: paths_setting.into(),
+                    });
+                }
+            }
+        }
+
+        if settings.get("substitute").and_then(|v| v.as_bool()) == Some(false) {
+            for substituters_setting in ["substituters", "extra-substituters"] {
+                if settings
+                    .get(substituters_setting)
+                    .is_some_and(|v| !v.as_list().is_empty())
+                {
+                    conflicts.push(Conflict::SubstitutersWithoutSubstitution {
+                        substituters_setting: substituters_setting.into(),
+                    });
+                }
+            }
+        }
+
+        conflicts.extend(self.missing_experimental_features());
+
+        conflicts
+    }
+
+    /// Find settings that only have an effect with an experimental feature
+    /// that isn't enabled, such as `accept-flake-config = true` without the
+    /// `flakes` feature, as [`Conflict::MissingExperimentalFeature`]s.
+    /// Settings that have their built-in default value are not reported, as
+    /// they change nothing either way.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{Conflict, NixConfig};
+    ///
+    /// let mut nix_conf = NixConfig::parse_string(
+    ///     "experimental-features = nix-command\nauto-allocate-uids = true\nuse-registries = false"
+    ///         .into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.missing_experimental_features(),
+    ///     vec![Conflict::MissingExperimentalFeature {
+    ///         setting: "auto-allocate-uids".into(),
+    ///         feature: "auto-allocate-uids".into(),
+    ///     }]
+    /// );
+    ///
+    /// assert_eq!(nix_conf.enable_required_experimental_features(), ["auto-allocate-uids"]);
+    /// assert_eq!(
+    ///     nix_conf.settings().get("experimental-features").unwrap(),
+    ///     "nix-command auto-allocate-uids"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn missing_experimental_features(&self) -> Vec<Conflict> {
+        let mut missing = Vec::new();
+
+        for (name, value) in self.settings() {
+            let Some(info) = SettingInfo::lookup(name) else {
+                continue;
+            };
+            let Some((_, feature)) = FEATURE_REQUIREMENTS
+                .iter()
+                .find(|(setting, _)| *setting == info.name())
+            else {
+                continue;
+            };
+
+            if info.default_value() == Some(value.as_str()) {
+                continue;
+            }
+
+            let in_effect = match info.setting_type() {
+                SettingType::Boolean => value.as_bool() == Some(true),
+                _ => !value.is_empty(),
+            };
+            if in_effect && !self.has_experimental_feature(feature) {
+                missing.push(Conflict::MissingExperimentalFeature {
+                    setting: name.clone(),
+                    feature: feature.to_string(),
+                });
+            }
+        }
+
+        missing
+    }
+
+    /// Enable the experimental features reported by
+    /// [`NixConfig::missing_experimental_features`], returning the features
+    /// that were added. They are added to `experimental-features` if it is
+    /// set, otherwise to `extra-experimental-features`, so Nix's default
+    /// features are kept.
+    pub fn enable_required_experimental_features(&mut self) -> Vec<String> {
+        let mut added = Vec::new();
+        for conflict in self.missing_experimental_features() {
+            if let Conflict::MissingExperimentalFeature { feature, .. } = conflict {
+                if !added.contains(&feature) {
+                    added.push(feature);
+                }
+            }
+        }
+
+        if added.is_empty() {
+            return added;
+        }
+
+        let name = if self.settings().contains_key("experimental-features") {
+            "experimental-features"
+        } else {
+            "extra-experimental-features"
+        };
+        let features = self.settings_mut().entry(name.to_owned()).or_default();
+        for feature in &added {
+            features.push_item(feature);
+        }
+
+        added
+    }
+
+    fn has_experimental_feature(&self, feature: &str) -> bool {
+        ["experimental-features", "extra-experimental-features"]
+            .into_iter()
+            .filter_map(|name| self.settings().get(name))
+            .any(|value| value.as_list().contains(&feature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_conflicts() {
+        let nix_conf = NixConfig::parse_string(
+            r#"
+                substituters = https://cache.nixos.org https://example.com
+                binary-caches = https://cache.nixos.org   https://example.com
+                build-cores = 4
+                cores = 8
+                sandbox = false
+                extra-sandbox-paths = /bin/sh
+                substitute = no
+                extra-substituters = https://example.org
+                accept-flake-config = true
+                experimental-features = nix-command
+            "#
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            nix_conf.detect_conflicts(),
+            vec![
+                Conflict::AliasMismatch {
+                    setting: "cores".into(),
+                    alias: "build-cores".into(),
+                },
+                Conflict::SandboxPathsWithoutSandbox {
+                    paths_setting: "extra-sandbox-paths".into(),
+                },
+                Conflict::SubstitutersWithoutSubstitution {
+                    substituters_setting: "substituters".into(),
+                },
+                Conflict::SubstitutersWithoutSubstitution {
+                    substituters_setting: "extra-substituters".into(),
+                },
+                Conflict::MissingExperimentalFeature {
+                    setting: "accept-flake-config".into(),
+                    feature: "flakes".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn consistent_config_has_no_conflicts() {
+        let nix_conf = NixConfig::parse_string(
+            r#"
+                sandbox = true
+                sandbox-paths = /bin/sh
+                accept-flake-config = true
+                extra-experimental-features = flakes
+            "#
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        assert!(nix_conf.detect_conflicts().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! A corpus of tricky `nix.conf` inputs and how Nix parses them, to check that
+//! a set of [`ParseOptions`] (or a fork of this crate) still parses like Nix.
+//!
+//! ```rust
+//! use nix_config_parser::{conformance, Dialect, ParseOptions};
+//!
+//! for dialect in Dialect::ALL {
+//!     let failures = conformance::run(&ParseOptions::new().dialect(*dialect));
+//!     assert!(failures.is_empty(), "{failures:?}");
+//! }
+//!
+//! // `cores=4` is illegal for Nix
+//! let failures = conformance::run(&ParseOptions::lenient());
+//! assert!(failures.iter().any(|failure| failure.case().name() == "no-spaces-around-equals"));
+//! ```
+
+use std::fmt;
+
+use crate::{NixConfig, ParseOptions};
+
+/// An input in the corpus, see [`cases`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    name: &'static str,
+    input: &'static str,
+    expected: Expected,
+}
+
+/// How Nix parses the input of a [`Case`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expected {
+    /// The input is accepted, resulting in exactly these settings, in order.
+    Settings(&'static [(&'static str, &'static str)]),
+    /// The input is rejected.
+    Rejected,
+}
+
+impl Case {
+    /// A short, unique, kebab-case name for the case.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn input(&self) -> &'static str {
+        self.input
+    }
+
+    pub fn expected(&self) -> Expected {
+        self.expected
+    }
+}
+
+/// A [`Case`] that wasn't parsed like Nix does, see [`run`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    case: &'static Case,
+    actual: Result<Vec<(String, String)>, String>,
+}
+
+impl Failure {
+    pub fn case(&self) -> &'static Case {
+        self.case
+    }
+
+    /// The settings that were parsed, or the error if the input was rejected.
+    pub fn actual(&self) -> Result<&[(String, String)], &str> {
+        self.actual.as_deref().map_err(String::as_str)
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: expected ", self.case.name)?;
+        match self.case.expected {
+            Expected::Settings(settings) => write!(f, "{settings:?}")?,
+            Expected::Rejected => f.write_str("rejection")?,
+        }
+        match &self.actual {
+            Ok(settings) => write!(f, ", got {settings:?}"),
+            Err(e) => write!(f, ", got error: {e}"),
+        }
+    }
+}
+
+macro_rules! cases {
+    ($($name:literal: $input:literal => $expected:expr,)*) => {
+        &[$(Case { name: $name, input: $input, expected: $expected },)*]
+    };
+}
+
+const CASES: &[Case] = cases! {
+    "leading-whitespace": "  cores = 4" => Expected::Settings(&[("cores", "4")]),
+    "tabs-as-separators": "cores\t=\t4" => Expected::Settings(&[("cores", "4")]),
+    "crlf-line-endings": "cores = 4\r\nmax-jobs = 2\r\n" => Expected::Settings(&[("cores", "4"), ("max-jobs", "2")]),
+    "blank-and-comment-lines": "\n# comment\n   \n#cores = 8\ncores = 1" => Expected::Settings(&[("cores", "1")]),
+    "trailing-comment": "cores = 4 # four" => Expected::Settings(&[("cores", "4")]),
+    "hash-in-value": "substituters = https://cache.example.com/#fragment" => Expected::Settings(&[("substituters", "https://cache.example.com/")]),
+    "empty-value": "post-build-hook =" => Expected::Settings(&[("post-build-hook", "")]),
+    "collapsed-whitespace": "trusted-users =  root \t alice  " => Expected::Settings(&[("trusted-users", "root alice")]),
+    "quotes-are-literal": "netrc-file = \"/Users/First Last/.netrc\"" => Expected::Settings(&[("netrc-file", "\"/Users/First Last/.netrc\"")]),
+    "backslash-is-literal": "cores = 4 \\" => Expected::Settings(&[("cores", "4 \\")]),
+    "second-equals-is-value": "cores = = 4" => Expected::Settings(&[("cores", "= 4")]),
+    "later-assignment-wins": "cores = 4\ncores = 8" => Expected::Settings(&[("cores", "8")]),
+    "extra-kept-separate": "substituters = a\nextra-substituters = b" => Expected::Settings(&[("substituters", "a"), ("extra-substituters", "b")]),
+    "unknown-settings-kept": "build-corez = 4" => Expected::Settings(&[("build-corez", "4")]),
+    "optional-include-missing": "!include /does/not/exist/nix.conf\ncores = 2" => Expected::Settings(&[("cores", "2")]),
+    "no-spaces-around-equals": "cores=4" => Expected::Rejected,
+    "missing-equals": "cores 4" => Expected::Rejected,
+    "name-only": "cores" => Expected::Rejected,
+    "unset-is-not-a-directive": "!unset cores" => Expected::Rejected,
+    "include-missing": "include /does/not/exist/nix.conf" => Expected::Rejected,
+    "include-without-path": "include" => Expected::Rejected,
+    "include-extra-tokens": "include /does/not/exist/nix.conf please" => Expected::Rejected,
+};
+
+/// Every case in the corpus.
+pub fn cases() -> &'static [Case] {
+    CASES
+}
+
+/// Parse every case with `options`, returning those not parsed like Nix does.
+pub fn run(options: &ParseOptions) -> Vec<Failure> {
+    let mut failures = Vec::new();
+
+    for case in CASES {
+        let actual = NixConfig::parse_string_with_options(case.input.into(), None, options)
+            .map(|nix_conf| {
+                nix_conf
+                    .settings()
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .map_err(|e| e.to_string());
+
+        let conforms = match (&actual, case.expected) {
+            (Ok(actual), Expected::Settings(expected)) => {
+                actual.len() == expected.len()
+                    && actual.iter().zip(expected).all(
+                        |((name, value), (expected_name, expected_value))| {
+                            name == expected_name && value == expected_value
+                        },
+                    )
+            }
+            (Err(_), Expected::Rejected) => true,
+            _ => false,
+        };
+
+        if !conforms {
+            failures.push(Failure { case, actual });
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IncludeMode;
+
+    #[test]
+    fn reports_deviations() {
+        let names = cases().iter().map(Case::name).collect::<Vec<_>>();
+        let mut unique = names.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(names.len(), unique.len());
+
+        let failures = run(&ParseOptions::new().includes(IncludeMode::Keep));
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| failure.case().name())
+                .collect::<Vec<_>>(),
+            ["include-missing"]
+        );
+        assert_eq!(failures[0].actual(), Ok(&[][..]));
+        assert_eq!(
+            failures[0].to_string(),
+            "include-missing: expected rejection, got []"
+        );
+    }
+}
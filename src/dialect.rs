@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// An implementation of Nix. The implementations mostly agree on the format
+/// of `nix.conf`, but each of them supports some settings the others don't.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Dialect {
+    /// Upstream Nix, as developed by the NixOS project.
+    #[default]
+    Nix,
+    /// [Lix](https://lix.systems), a fork of Nix.
+    Lix,
+    /// [Determinate Nix](https://determinate.systems), a downstream
+    /// distribution of Nix.
+    Determinate,
+}
+
+impl Dialect {
+    /// Every known dialect.
+    pub const ALL: &'static [Dialect] = &[Dialect::Nix, Dialect::Lix, Dialect::Determinate];
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dialect::Nix => f.write_str("Nix"),
+            Dialect::Lix => f.write_str("Lix"),
+            Dialect::Determinate => f.write_str("Determinate Nix"),
+        }
+    }
+}
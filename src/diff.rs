@@ -0,0 +1,202 @@
+use crate::{NixConfig, SettingInfo, SettingValue};
+
+/// A single difference between two [`NixConfig`]s, as returned by
+/// [`NixConfig::diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "kebab-case")
+)]
+pub enum SettingDiff {
+    Added {
+        name: String,
+        value: SettingValue,
+    },
+    Removed {
+        name: String,
+        value: SettingValue,
+    },
+    Changed {
+        name: String,
+        old: SettingValue,
+        new: SettingValue,
+    },
+}
+
+impl SettingDiff {
+    pub fn name(&self) -> &str {
+        match self {
+            SettingDiff::Added { name, .. }
+            | SettingDiff::Removed { name, .. }
+            | SettingDiff::Changed { name, .. } => name,
+        }
+    }
+}
+
+impl NixConfig {
+    /// The changes needed to turn this config into `other`, comparing settings
+    /// by their exact names and values. Removals and changes come in the order
+    /// of this config, followed by additions in the order of `other`.
+    ///
+    /// Use [`NixConfig::canonicalize`] on both sides first to ignore
+    /// differences that don't matter to Nix, such as legacy aliases.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, SettingDiff};
+    ///
+    /// let old = NixConfig::parse_string("cores = 4\nmax-jobs = 2".into(), None)?;
+    /// let new = NixConfig::parse_string("cores = 8".into(), None)?;
+    ///
+    /// assert_eq!(
+    ///     old.diff(&new),
+    ///     vec![
+    ///         SettingDiff::Changed {
+    ///             name: "cores".into(),
+    ///             old: "4".into(),
+    ///             new: "8".into(),
+    ///         },
+    ///         SettingDiff::Removed {
+    ///             name: "max-jobs".into(),
+    ///             value: "2".into(),
+    ///         },
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn diff(&self, other: &NixConfig) -> Vec<SettingDiff> {
+        let mut diff = Vec::new();
+
+        for (name, value) in self.settings() {
+            match other.settings().get(name) {
+                Some(other_value) if other_value == value => {}
+                Some(other_value) => diff.push(SettingDiff::Changed {
+                    name: name.clone(),
+                    old: value.clone(),
+                    new: other_value.clone(),
+                }),
+                None => diff.push(SettingDiff::Removed {
+                    name: name.clone(),
+                    value: value.clone(),
+                }),
+            }
+        }
+
+        for (name, value) in other.settings() {
+            if !self.settings().contains_key(name) {
+                diff.push(SettingDiff::Added {
+                    name: name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+
+        diff
+    }
+
+    /// A copy of this config with legacy aliases renamed to their canonical
+    /// names, and `extra-<name>` folded into `<name>` where `<name>` is set as
+    /// well. If both a setting and one of its aliases are set, the one that
+    /// comes later wins, like it would in Nix.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "binary-caches = https://cache.nixos.org\nextra-substituters = https://example.com"
+    ///         .into(),
+    ///     None,
+    /// )?
+    /// .canonicalize();
+    ///
+    /// assert_eq!(
+    ///     nix_conf.settings().get("substituters").unwrap(),
+    ///     "https://cache.nixos.org https://example.com"
+    /// );
+    /// assert_eq!(nix_conf.settings().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn canonicalize(&self) -> NixConfig {
+        let mut canonical = NixConfig::new();
+
+        for (name, value) in self.settings() {
+            let canonical_name = match SettingInfo::lookup(name) {
+                Some(info) if info.aliases().contains(&name.as_str()) => info.name(),
+                _ => name,
+            };
+
+            canonical
+                .settings_mut()
+                .insert(canonical_name.to_owned(), value.clone());
+            if let Some(origin) = self.origin(name) {
+                canonical.set_origin(canonical_name, origin.clone());
+            }
+        }
+
+        let extras = canonical
+            .settings()
+            .keys()
+            .filter_map(|name| {
+                let info = SettingInfo::lookup(name)?;
+                (info.name() != name.as_str() && canonical.settings().contains_key(info.name()))
+                    .then(|| (name.clone(), info.name()))
+            })
+            .collect::<Vec<_>>();
+
+        for (extra, base) in extras {
+            let value = canonical
+                .settings_mut()
+                .shift_remove(&extra)
+                .unwrap_or_default();
+            if let Some(base_value) = canonical.settings_mut().get_mut(base) {
+                for item in value.as_list() {
+                    base_value.push_item(item);
+                }
+            }
+        }
+
+        canonical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_and_canonicalizes() {
+        let old = NixConfig::parse_string(
+            "build-cores = 4\nsubstituters = https://a.example.com\nextra-binary-caches = https://b.example.com\nkeep-outputs = true".into(),
+            None,
+        )
+        .unwrap();
+        let new = NixConfig::parse_string(
+            "cores = 4\nsubstituters = https://a.example.com https://b.example.com\nwarn-dirty = false".into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(old.diff(&new).len(), 6);
+        assert_eq!(
+            old.canonicalize().diff(&new.canonicalize()),
+            vec![
+                SettingDiff::Removed {
+                    name: "keep-outputs".into(),
+                    value: "true".into(),
+                },
+                SettingDiff::Added {
+                    name: "warn-dirty".into(),
+                    value: "false".into(),
+                },
+            ]
+        );
+    }
+}
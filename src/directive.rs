@@ -0,0 +1,117 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::Origin;
+
+/// A line claimed by a handler registered with
+/// [`ParseOptions::on_directive`](crate::ParseOptions::on_directive).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomDirective {
+    pub(crate) word: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) origin: Origin,
+}
+
+impl CustomDirective {
+    /// The word the line starts with, which the handler was registered for.
+    pub fn word(&self) -> &str {
+        &self.word
+    }
+
+    /// The rest of the line, split into tokens like a setting's value.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+}
+
+type Handler = dyn Fn(&CustomDirective) -> Result<(), String> + Send + Sync;
+
+/// The handlers registered with `ParseOptions::on_directive`. Handlers are
+/// compared by identity.
+#[derive(Clone, Default)]
+pub(crate) struct DirectiveHandlers(Vec<(String, Arc<Handler>)>);
+
+impl DirectiveHandlers {
+    pub(crate) fn insert(&mut self, word: String, handler: Arc<Handler>) {
+        self.0.retain(|(registered, _)| *registered != word);
+        self.0.push((word, handler));
+    }
+
+    pub(crate) fn get(&self, word: &str) -> Option<&Handler> {
+        self.0
+            .iter()
+            .find(|(registered, _)| registered == word)
+            .map(|(_, handler)| handler.as_ref())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for DirectiveHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.0.iter().map(|(word, _)| word))
+            .finish()
+    }
+}
+
+impl PartialEq for DirectiveHandlers {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .all(|((a, f), (b, g))| a == b && Arc::ptr_eq(f, g))
+    }
+}
+
+impl Eq for DirectiveHandlers {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{NixConfig, ParseError, ParseOptions};
+
+    #[test]
+    fn handles_directives_without_arguments() {
+        let resets = Arc::new(AtomicUsize::new(0));
+        let options = ParseOptions::new()
+            .on_directive("@reset", {
+                let resets = Arc::clone(&resets);
+                move |directive| {
+                    assert!(directive.args().is_empty());
+                    resets.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            })
+            .on_directive("@refuse", |_| Err("not today".into()));
+
+        let nix_conf = NixConfig::parse_string_with_options(
+            "@reset
+cores = 4"
+                .into(),
+            None,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(resets.load(Ordering::SeqCst), 1);
+        assert_eq!(nix_conf.get_str("cores"), Some("4"));
+
+        match NixConfig::parse_string_with_options("@refuse".into(), None, &options) {
+            Err(ParseError::DirectiveRejected(word, message, None)) => {
+                assert_eq!(word, "@refuse");
+                assert_eq!(message, "not today");
+            }
+            _ => panic!("the handler should have rejected the directive"),
+        }
+    }
+}
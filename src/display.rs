@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::secret::redact;
+use crate::{NixConfig, SettingValue};
+
+/// How many items of a list value are logged, before the rest are elided.
+const LOGGED_ITEMS: usize = 5;
+
+/// How many characters of a value are logged, before the rest are elided.
+const LOGGED_CHARS: usize = 256;
+
+impl NixConfig {
+    /// The settings as key/value pairs for structured logging, sorted by name.
+    /// Like when the config is [displayed](#impl-Display-for-NixConfig),
+    /// secrets are masked and long values are elided, so that the fields can
+    /// be attached to a log event as they are.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "cores = 4\naccess-tokens = github.com=ghp_secret".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.to_log_fields(),
+    ///     [
+    ///         ("access-tokens", "github.com=<redacted>".to_owned()),
+    ///         ("cores", "4".to_owned())
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_log_fields(&self) -> Vec<(&str, String)> {
+        let mut fields = self
+            .settings()
+            .iter()
+            .map(|(name, value)| (name.as_str(), log_value(name, value)))
+            .collect::<Vec<_>>();
+        fields.sort_by_key(|(name, _)| *name);
+        fields
+    }
+}
+
+/// The value of `name` as logged: redacted, with at most [`LOGGED_ITEMS`]
+/// items and about [`LOGGED_CHARS`] characters.
+fn log_value(name: &str, value: &SettingValue) -> String {
+    let value = redact(name, value);
+    let items = value.as_list();
+
+    let mut logged = items
+        .iter()
+        .take(LOGGED_ITEMS)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if let Some((end, _)) = logged.char_indices().nth(LOGGED_CHARS) {
+        logged.truncate(end);
+        logged.push_str("...");
+    }
+    if items.len() > LOGGED_ITEMS {
+        logged.push_str(&format!(" ... ({} more)", items.len() - LOGGED_ITEMS));
+    }
+
+    logged
+}
+
+/// A summary of the settings for logs, such as a service logging its
+/// effective configuration at startup: the settings are sorted by name, long
+/// lists are elided and secrets, such as the tokens in `access-tokens`, are
+/// masked. See also [`NixConfig::to_log_fields`].
+///
+/// This is not a `nix.conf`, use [`NixConfig::to_string_checked`] for that.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::NixConfig;
+///
+/// let nix_conf = NixConfig::parse_string(
+///     "cores = 4\naccess-tokens = github.com=ghp_secret\ntrusted-users = root alice bob carol dave erin frank"
+///         .into(),
+///     None,
+/// )?;
+///
+/// assert_eq!(
+///     nix_conf.to_string(),
+///     "access-tokens = github.com=<redacted>\ncores = 4\ntrusted-users = root alice bob carol dave ... (2 more)"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+impl fmt::Display for NixConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, value)) in self.to_log_fields().into_iter().enumerate() {
+            if index > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{name} = {value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_logged_values() {
+        let long = "x".repeat(LOGGED_CHARS + 10);
+        assert_eq!(
+            log_value("build-hook", &long.as_str().into()),
+            format!("{}...", &long[..LOGGED_CHARS])
+        );
+        assert_eq!(log_value("cores", &"".into()), "");
+        assert_eq!(
+            log_value("extra-access-tokens", &"a=1 b=2 c=3 d=4 e=5 f=6".into()),
+            "a=<redacted> b=<redacted> c=<redacted> d=<redacted> e=<redacted> ... (1 more)"
+        );
+    }
+}
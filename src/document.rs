@@ -0,0 +1,1009 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::pragma::parse_pragma;
+use crate::serialize::render_value;
+use crate::{
+    continued_line, illegal_line, parse_line, read_file, Directive, IncludeDirective, IncludeMode,
+    NixConfig, Origin, ParseError, ParseOptions, Pragma, SerializeError, SerializeOptions,
+    SettingName, SettingValue,
+};
+
+/// A `nix.conf` that remembers its exact text, including comments, blank lines,
+/// `include` directives and line endings, so it can be edited and written back
+/// without disturbing anything but the settings that were changed.
+///
+/// Included files are not read; `include` lines are kept as they are, unless
+/// [`IncludeMode::Error`] is used to refuse them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NixConfigDocument {
+    lines: Vec<DocumentLine>,
+    origin: Option<PathBuf>,
+    options: ParseOptions,
+}
+
+/// A line of the document, or several if they are joined by
+/// [line continuations](ParseOptions::line_continuations).
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct DocumentLine {
+    /// The text as written, including the line breaks of continued lines.
+    text: String,
+    /// The continued lines joined like the parser does, if there are any.
+    joined: Option<String>,
+    ending: &'static str,
+}
+
+impl NixConfigDocument {
+    /// Attempt to parse the `nix.conf` at the provided path.
+    pub fn parse_file(path: &Path) -> Result<Self, ParseError> {
+        Self::parse_file_with_options(path, &ParseOptions::default())
+    }
+
+    /// Attempt to parse the `nix.conf` at the provided path, using the provided
+    /// [`ParseOptions`].
+    pub fn parse_file_with_options(
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let path = &options.origin(path);
+        Self::parse_string_with_options(read_file(path)?, Some(path), options)
+    }
+
+    /// The options this document was parsed with.
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Attempt to parse the `nix.conf` out of the provided [`String`]. The
+    /// `origin` parameter is [`Option`]al, and only used for error reporting.
+    pub fn parse_string(contents: String, origin: Option<&Path>) -> Result<Self, ParseError> {
+        Self::parse_string_with_options(contents, origin, &ParseOptions::default())
+    }
+
+    /// Attempt to parse the `nix.conf` out of the provided [`String`], using the
+    /// provided [`ParseOptions`]. The same options are used to render values
+    /// changed later on.
+    pub fn parse_string_with_options(
+        contents: String,
+        origin: Option<&Path>,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let origin = origin.map(|origin| options.origin(origin));
+        let origin = origin.as_deref();
+        let mut lines = Vec::new();
+        let mut physical_lines = contents.split_inclusive('\n').map(|line| {
+            if let Some(text) = line.strip_suffix("\r\n") {
+                (text, "\r\n")
+            } else if let Some(text) = line.strip_suffix('\n') {
+                (text, "\n")
+            } else {
+                (line, "")
+            }
+        });
+
+        while let Some((text, ending)) = physical_lines.next() {
+            let mut line = DocumentLine::new(text.to_owned(), ending);
+            // Like `logical_lines`, but keeping the lines as written.
+            while let Some(mut joined) = continued_line(line.logical(), options).map(str::to_owned)
+            {
+                let Some((next, ending)) = physical_lines.next() else {
+                    line.joined = Some(joined);
+                    break;
+                };
+                line.text.push_str(line.ending);
+                line.text.push_str(next);
+                line.ending = ending;
+                joined.push(' ');
+                joined.push_str(next);
+                line.joined = Some(joined);
+            }
+
+            let parsed =
+                parse_line(line.logical(), options).map_err(|line| illegal_line(line, origin))?;
+            if let Directive::Include { path, .. } = parsed.directive {
+                if options.includes == IncludeMode::Error {
+                    return Err(ParseError::IncludeNotAllowed(
+                        path.as_ref().into(),
+                        origin.map(ToOwned::to_owned),
+                    ));
+                }
+            }
+
+            lines.push(line);
+        }
+
+        Ok(Self {
+            lines,
+            origin: origin.map(ToOwned::to_owned),
+            options: options.clone(),
+        })
+    }
+
+    /// The value of the setting `name`, as set by its last assignment in this
+    /// document.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<SettingValue> {
+        let index = self.last_assignment(name.as_ref())?;
+        match parse_line(self.lines[index].logical(), &self.options) {
+            Ok(line) => match line.directive {
+                Directive::Assignment { value, .. } => Some(value),
+                _ => None,
+            },
+            Err(_) => None,
+        }
+    }
+
+    /// Set `name` to `value`. If the setting is already assigned, its last
+    /// assignment is rewritten in place (keeping any comment at the end of the
+    /// line), otherwise a new line is appended.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let mut doc = NixConfigDocument::parse_string(
+    ///     "# Managed by hand\ncores = 4 # keep it low\n".into(),
+    ///     None,
+    /// )?;
+    /// doc.set("cores", "8")?;
+    /// doc.set("max-jobs", "auto")?;
+    ///
+    /// assert_eq!(
+    ///     doc.to_string(),
+    ///     "# Managed by hand\ncores = 8 # keep it low\nmax-jobs = auto\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), SerializeError> {
+        self.set_line(name, value).map(|_| ())
+    }
+
+    /// Like [`NixConfigDocument::set`], but also attach `comment` to the
+    /// setting, replacing the block of comment lines directly above it (if
+    /// any). Lines of `comment` that don't start with a `#` are prefixed with
+    /// `# `.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let mut doc = NixConfigDocument::parse_string("cores = 4\n".into(), None)?;
+    /// doc.set_with_comment(
+    ///     "substituters",
+    ///     "https://cache.nixos.org",
+    ///     "# added by nix-installer",
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     doc.to_string(),
+    ///     "cores = 4\n# added by nix-installer\nsubstituters = https://cache.nixos.org\n"
+    /// );
+    /// assert_eq!(
+    ///     doc.comment("substituters").as_deref(),
+    ///     Some("# added by nix-installer")
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_with_comment(
+        &mut self,
+        name: &str,
+        value: &str,
+        comment: &str,
+    ) -> Result<(), SerializeError> {
+        let index = self.set_line(name, value)?;
+        let start = self.comment_block_start(index);
+        let ending = self.lines[index].ending_or_default();
+
+        // Directly under the header, the comment would become part of it.
+        let under_header = start > 0 && self.lines[start - 1].text.trim_start().starts_with('#');
+        let separator = under_header.then(|| DocumentLine::new(String::new(), ending));
+        let comment_lines = comment.lines().map(|line| {
+            let text = if line.starts_with('#') {
+                line.to_owned()
+            } else {
+                format!("# {line}")
+            };
+            DocumentLine::new(text, ending)
+        });
+        self.lines
+            .splice(start..index, separator.into_iter().chain(comment_lines));
+
+        Ok(())
+    }
+
+    /// The comment associated with the setting `name`: the block of comment
+    /// lines directly above its last assignment, joined by newlines, or failing
+    /// that, the comment at the end of the assignment itself.
+    pub fn comment(&self, name: &str) -> Option<String> {
+        let index = self.last_assignment(name)?;
+        let start = self.comment_block_start(index);
+
+        if start < index {
+            let block = self.lines[start..index]
+                .iter()
+                .map(|line| line.text.trim())
+                .collect::<Vec<_>>();
+            return Some(block.join("\n"));
+        }
+
+        parse_line(self.lines[index].logical(), &self.options)
+            .ok()?
+            .comment
+            .map(|comment| comment.trim_end().to_owned())
+    }
+
+    /// The comment lines at the very top of the document, such as a
+    /// "generated by" header, trimmed and in order. Blank lines between them
+    /// are skipped; the header ends at the first line that is neither blank
+    /// nor a comment.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let mut doc = NixConfigDocument::parse_string(
+    ///     "# Generated by nix-installer.\n# Do not edit.\n\ncores = 4\n".into(),
+    ///     None,
+    /// )?;
+    /// assert_eq!(
+    ///     doc.leading_comments(),
+    ///     ["# Generated by nix-installer.", "# Do not edit."]
+    /// );
+    ///
+    /// doc.set_leading_comments(&["Generated by nix-installer v0.20.0."]);
+    /// assert_eq!(
+    ///     doc.to_string(),
+    ///     "# Generated by nix-installer v0.20.0.\n\ncores = 4\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn leading_comments(&self) -> Vec<&str> {
+        self.lines[..self.header_end()]
+            .iter()
+            .map(|line| line.text.trim())
+            .filter(|text| !text.is_empty())
+            .collect()
+    }
+
+    /// Replace the comment lines at the very top of the document (see
+    /// [`NixConfigDocument::leading_comments`]) with `comments`, one per line.
+    /// Lines that don't start with a `#` are turned into comments. A document
+    /// without a header gets a blank line between the new header and the
+    /// rest; an empty `comments` removes the header.
+    pub fn set_leading_comments(&mut self, comments: &[&str]) {
+        let end = self.header_end();
+        let ending = self
+            .lines
+            .first()
+            .map_or("\n", DocumentLine::ending_or_default);
+
+        let mut header = comments
+            .iter()
+            .flat_map(|comment| comment.split('\n'))
+            .map(|line| line.trim_end_matches('\r'))
+            .map(|line| {
+                let text = if line.starts_with('#') {
+                    line.to_owned()
+                } else {
+                    format!("# {line}").trim_end().to_owned()
+                };
+                DocumentLine::new(text, ending)
+            })
+            .collect::<Vec<_>>();
+        if end == 0 && !header.is_empty() && !self.lines.is_empty() {
+            header.push(DocumentLine::new(String::new(), ending));
+        }
+
+        self.lines.splice(..end, header);
+    }
+
+    /// Remove every assignment of `name`, returning the value it had.
+    pub fn remove(&mut self, name: &str) -> Option<SettingValue> {
+        let value = self.get(name)?;
+        let options = &self.options;
+        self.lines
+            .retain(|line| !is_assignment_of(line.logical(), name, options));
+        Some(value)
+    }
+
+    /// Remove assignments that set a setting to the value it already has from
+    /// an earlier line, returning how many were removed. A comment at the end
+    /// of a removed line is kept on a line of its own.
+    ///
+    /// Included files are not read, so an assignment following an `include`
+    /// is never considered redundant.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let mut doc = NixConfigDocument::parse_string(
+    ///     "# Added by the installer\ncores = 4\n\n# Added by the installer\ncores = 4 # again\n"
+    ///         .into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(doc.dedupe(), 1);
+    /// assert_eq!(
+    ///     doc.to_string(),
+    ///     "# Added by the installer\ncores = 4\n\n# Added by the installer\n# again\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dedupe(&mut self) -> usize {
+        let mut values = HashMap::new();
+        let mut removed = 0;
+
+        let options = &self.options;
+        self.lines.retain_mut(|line| {
+            let Ok(parsed) = parse_line(line.logical(), options) else {
+                return true;
+            };
+
+            match parsed.directive {
+                Directive::Assignment { name, value } => {
+                    if values.get(name.as_ref()) != Some(&value) {
+                        values.insert(name.into_owned(), value);
+                        return true;
+                    }
+                }
+                Directive::Unset { name } => {
+                    values.remove(name.as_ref());
+                    return true;
+                }
+                Directive::Include { .. } => {
+                    values.clear();
+                    return true;
+                }
+                Directive::None | Directive::Custom { .. } => return true,
+            }
+
+            removed += 1;
+            match parsed.comment {
+                Some(comment) => {
+                    let comment = comment.to_owned();
+                    line.keep_only_comment(&comment);
+                    true
+                }
+                None => false,
+            }
+        });
+
+        removed
+    }
+
+    /// The `include` and `!include` directives in this document, in order.
+    /// Edits never touch these lines, nor the files they point to; parse
+    /// those as documents of their own to edit them.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let mut doc = NixConfigDocument::parse_string(
+    ///     "include /etc/nix/machines.conf\n!include /etc/nix/local.conf\n".into(),
+    ///     None,
+    /// )?;
+    /// doc.set("cores", "4")?;
+    ///
+    /// assert_eq!(doc.includes().len(), 2);
+    /// assert!(doc.includes()[1].is_optional());
+    /// assert_eq!(
+    ///     doc.to_string(),
+    ///     "include /etc/nix/machines.conf\n!include /etc/nix/local.conf\ncores = 4\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn includes(&self) -> Vec<IncludeDirective> {
+        self.numbered_lines()
+            .filter_map(|(line_number, line)| {
+                match parse_line(line.logical(), &self.options).ok()?.directive {
+                    Directive::Include { path, optional, .. } => Some(IncludeDirective {
+                        path: path.as_ref().into(),
+                        optional,
+                        origin: Origin::File {
+                            path: self.origin.clone(),
+                            line: line_number,
+                        },
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The `# key: value` comments in this document, in order. See
+    /// [`Pragma`] for what counts as one.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let doc = NixConfigDocument::parse_string(
+    ///     [
+    ///         "# owner: platform-team",
+    ///         "# See https://nixos.org/manual/nix/stable/command-ref/conf-file",
+    ///         "",
+    ///         "max-jobs = 8",
+    ///         "cores = 4 # ticket: OPS-1234",
+    ///     ]
+    ///     .join("\n"),
+    ///     None,
+    /// )?;
+    ///
+    /// let pragmas = doc.pragmas();
+    /// assert_eq!(
+    ///     pragmas
+    ///         .iter()
+    ///         .map(|pragma| (pragma.key(), pragma.value(), pragma.setting()))
+    ///         .collect::<Vec<_>>(),
+    ///     [
+    ///         ("owner", "platform-team", Some("max-jobs")),
+    ///         ("ticket", "OPS-1234", Some("cores")),
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pragmas(&self) -> Vec<Pragma> {
+        let origin = |line: usize| Origin::File {
+            path: self.origin.clone(),
+            line,
+        };
+        let mut pragmas = Vec::new();
+        // The pragmas from here on still wait for the line they apply to.
+        let mut pending = 0;
+
+        for (line_number, line) in self.numbered_lines() {
+            let Ok(parsed) = parse_line(line.logical(), &self.options) else {
+                continue;
+            };
+            if let Some((key, value)) = parsed.comment.and_then(parse_pragma) {
+                pragmas.push(Pragma {
+                    key: key.to_owned(),
+                    value: value.to_owned(),
+                    setting: None,
+                    origin: origin(line_number),
+                    target: None,
+                });
+            }
+
+            if matches!(parsed.directive, Directive::None) {
+                continue;
+            }
+            let setting = match &parsed.directive {
+                Directive::Assignment { name, .. } => Some(name.to_string()),
+                _ => None,
+            };
+            for pragma in &mut pragmas[pending..] {
+                pragma.setting.clone_from(&setting);
+                pragma.target = Some(origin(line_number));
+            }
+            pending = pragmas.len();
+        }
+
+        pragmas
+    }
+
+    /// The settings assigned in this document, with their origins. Included
+    /// files are not read.
+    pub fn to_config(&self) -> NixConfig {
+        let mut config = NixConfig::new();
+
+        for (line_number, line) in self.numbered_lines() {
+            let Ok(line) = parse_line(line.logical(), &self.options) else {
+                continue;
+            };
+            match line.directive {
+                Directive::Assignment { name, value } => {
+                    config.settings_mut().insert(name.to_string(), value);
+                    config.set_origin(
+                        &name,
+                        Origin::File {
+                            path: self.origin.clone(),
+                            line: line_number,
+                        },
+                    );
+                }
+                Directive::Unset { name } => {
+                    config.settings_mut().shift_remove(name.as_ref());
+                }
+                _ => {}
+            }
+        }
+
+        config
+    }
+
+    /// Set `name` to `value`, returning the index of the line that holds it.
+    fn set_line(&mut self, name: &str, value: &str) -> Result<usize, SerializeError> {
+        if !SettingName::is_valid(name) {
+            return Err(SerializeError::InvalidSettingName(name.to_owned()));
+        }
+
+        let serialize_options = SerializeOptions::new()
+            .quoting(self.options.quoting)
+            .hash_escapes(self.options.hash_escapes);
+        let rendered = render_value(name, value, &serialize_options)?;
+        let assignment = if rendered.is_empty() {
+            format!("{name} =")
+        } else {
+            format!("{name} = {rendered}")
+        };
+
+        if let Some(index) = self.last_assignment(name) {
+            let line = &mut self.lines[index];
+            let indent = &line.text[..line.text.len() - line.text.trim_start().len()];
+            let comment = parse_line(line.logical(), &self.options)
+                .ok()
+                .and_then(|line| line.comment);
+            let text = match comment {
+                Some(comment) => format!("{indent}{assignment} {comment}"),
+                None => format!("{indent}{assignment}"),
+            };
+            line.set_text(text);
+            return Ok(index);
+        }
+
+        Ok(self.push_line(assignment))
+    }
+
+    /// Append a line of `text`, returning its index.
+    pub(crate) fn push_line(&mut self, text: String) -> usize {
+        let ending = match self.lines.last_mut() {
+            Some(last) => {
+                if last.ending.is_empty() {
+                    last.ending = "\n";
+                }
+                last.ending
+            }
+            None => "\n",
+        };
+        self.lines.push(DocumentLine::new(text, ending));
+
+        self.lines.len() - 1
+    }
+
+    /// Remove every assignment of `name` like [`NixConfigDocument::remove`],
+    /// along with the comment block attached to the last one (unless that is
+    /// the document's header), returning the value and the comment.
+    pub(crate) fn take(&mut self, name: &str) -> Option<(SettingValue, Option<String>)> {
+        let index = self.last_assignment(name)?;
+        let start = self.comment_block_start(index);
+        let comment = self.comment(name);
+
+        self.lines.drain(start..index);
+        let value = self.remove(name)?;
+
+        Some((value, comment))
+    }
+
+    /// The file this document was parsed from, if any.
+    pub(crate) fn origin_path(&self) -> Option<&Path> {
+        self.origin.as_deref()
+    }
+
+    /// Remove the assignment of `name` on the (1-based) line `line_number`,
+    /// keeping a comment at the end of it. Returns `false` if the line isn't
+    /// such an assignment.
+    pub(crate) fn remove_assignment_at(&mut self, line_number: usize, name: &str) -> bool {
+        let Some(index) = self.line_index(line_number) else {
+            return false;
+        };
+        let line = &mut self.lines[index];
+        let Ok(parsed) = parse_line(line.logical(), &self.options) else {
+            return false;
+        };
+        if !matches!(&parsed.directive, Directive::Assignment { name: n, .. } if n == name) {
+            return false;
+        }
+
+        match parsed.comment {
+            Some(comment) => {
+                let comment = comment.to_owned();
+                line.keep_only_comment(&comment);
+            }
+            None => {
+                self.lines.remove(index);
+            }
+        }
+        true
+    }
+
+    /// Rename the setting assigned on the (1-based) line `line_number` from
+    /// `from` to `to`, leaving the rest of the line as it is. Returns `false`
+    /// if the line isn't an assignment of `from`.
+    pub(crate) fn rename_at(&mut self, line_number: usize, from: &str, to: &str) -> bool {
+        let Some(index) = self.line_index(line_number) else {
+            return false;
+        };
+        let line = &mut self.lines[index];
+        if !is_assignment_of(line.logical(), from, &self.options) {
+            return false;
+        }
+
+        let indent_len = line.text.len() - line.text.trim_start().len();
+        if !line.text[indent_len..].starts_with(from) {
+            return false;
+        }
+        line.text
+            .replace_range(indent_len..indent_len + from.len(), to);
+        if let Some(joined) = &mut line.joined {
+            joined.replace_range(indent_len..indent_len + from.len(), to);
+        }
+        true
+    }
+
+    /// Sort the `include` directives on the (1-based) lines `start` to `end`
+    /// by path. Returns `false` if any of the lines isn't an `include`
+    /// directive.
+    pub(crate) fn sort_includes(&mut self, start: usize, end: usize) -> bool {
+        let (Some(start), Some(end)) = (self.line_index(start), self.line_index(end)) else {
+            return false;
+        };
+        let Some(lines) = self.lines.get_mut(start..=end) else {
+            return false;
+        };
+
+        let mut keyed = Vec::with_capacity(lines.len());
+        for line in lines.iter() {
+            match parse_line(line.logical(), &self.options).map(|line| line.directive) {
+                Ok(Directive::Include { path, .. }) => {
+                    keyed.push((path.into_owned(), line.text.clone(), line.joined.clone()))
+                }
+                _ => return false,
+            }
+        }
+        keyed.sort();
+
+        for (line, (_, text, joined)) in lines.iter_mut().zip(keyed) {
+            line.text = text;
+            line.joined = joined;
+        }
+        true
+    }
+
+    /// The directives of every line, in order.
+    pub(crate) fn directives(&self) -> impl Iterator<Item = Directive<'_>> {
+        self.lines
+            .iter()
+            .filter_map(|line| parse_line(line.logical(), &self.options).ok())
+            .map(|line| line.directive)
+    }
+
+    /// The codes of the lint rules ignored by `# nixconf-lint: ignore <code>...`
+    /// pragmas (separated by spaces or commas), by the 1-based line they
+    /// apply to.
+    pub(crate) fn lint_ignores(&self) -> HashMap<usize, Vec<String>> {
+        let mut ignores = HashMap::<usize, Vec<String>>::new();
+
+        for pragma in self.pragmas() {
+            let Some(Origin::File { line, .. }) = pragma.target else {
+                continue;
+            };
+            if pragma.key != "nixconf-lint" {
+                continue;
+            }
+            let Some(codes) = pragma.value.strip_prefix("ignore") else {
+                continue;
+            };
+            if !codes.is_empty() && !codes.starts_with(char::is_whitespace) {
+                continue;
+            }
+
+            ignores.entry(line).or_default().extend(
+                codes
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|code| !code.is_empty())
+                    .map(ToOwned::to_owned),
+            );
+        }
+
+        ignores
+    }
+
+    /// The lines, with the (1-based) number of the first physical line of
+    /// each.
+    fn numbered_lines(&self) -> impl Iterator<Item = (usize, &DocumentLine)> {
+        let mut line_number = 1;
+        self.lines.iter().map(move |line| {
+            let number = line_number;
+            line_number += 1 + line.text.matches('\n').count();
+            (number, line)
+        })
+    }
+
+    /// The index of the line starting at the (1-based) line `line_number`.
+    fn line_index(&self, line_number: usize) -> Option<usize> {
+        self.numbered_lines()
+            .position(|(number, _)| number == line_number)
+    }
+
+    fn last_assignment(&self, name: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| is_assignment_of(line.logical(), name, &self.options))
+    }
+
+    /// The index of the line after the last comment of the header, see
+    /// [`NixConfigDocument::leading_comments`].
+    fn header_end(&self) -> usize {
+        let mut end = 0;
+        for (index, line) in self.lines.iter().enumerate() {
+            let text = line.text.trim_start();
+            if text.starts_with('#') {
+                end = index + 1;
+            } else if !text.is_empty() {
+                break;
+            }
+        }
+        end
+    }
+
+    /// The index of the first line of the block of comment-only lines directly
+    /// above `index`, or `index` itself if there is none. A block starting at
+    /// the top of the document is its header, which doesn't belong to the
+    /// line below it.
+    fn comment_block_start(&self, index: usize) -> usize {
+        let mut start = index;
+        while start > 0 && self.lines[start - 1].text.trim_start().starts_with('#') {
+            start -= 1;
+        }
+        if start == 0 {
+            index
+        } else {
+            start
+        }
+    }
+}
+
+impl DocumentLine {
+    fn new(text: String, ending: &'static str) -> Self {
+        Self {
+            text,
+            joined: None,
+            ending,
+        }
+    }
+
+    /// The text as the parser sees it, with continued lines joined.
+    fn logical(&self) -> &str {
+        self.joined.as_deref().unwrap_or(&self.text)
+    }
+
+    /// Replace the text with a single line.
+    fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.joined = None;
+    }
+
+    /// Replace the line with `comment`, at the same indentation.
+    fn keep_only_comment(&mut self, comment: &str) {
+        let indent = &self.text[..self.text.len() - self.text.trim_start().len()];
+        self.set_text(format!("{indent}{}", comment.trim_end()));
+    }
+
+    fn ending_or_default(&self) -> &'static str {
+        if self.ending.is_empty() {
+            "\n"
+        } else {
+            self.ending
+        }
+    }
+}
+
+fn is_assignment_of(text: &str, name: &str, options: &ParseOptions) -> bool {
+    matches!(
+        parse_line(text, options),
+        Ok(line) if matches!(&line.directive, Directive::Assignment { name: n, .. } if n == name)
+    )
+}
+
+impl fmt::Display for NixConfigDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}{}", line.text, line.ending)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_untouched_text() {
+        let contents = "# System-wide settings\r\n\r\n  cores = 4   # trailing\r\n!include /etc/nix/extra.conf\r\nsandbox = true";
+        let doc = NixConfigDocument::parse_string(contents.into(), None).unwrap();
+
+        assert_eq!(doc.to_string(), contents);
+        assert_eq!(doc.get("cores"), Some("4".into()));
+        assert_eq!(doc.comment("cores").as_deref(), Some("# trailing"));
+        assert_eq!(doc.to_config().settings().len(), 2);
+    }
+
+    #[test]
+    fn joins_continued_lines() {
+        let contents =
+            "substituters = \\\r\n  https://a \\\r\n  https://b # caches\r\ncores = 4\r\n";
+        let mut doc = NixConfigDocument::parse_string_with_options(
+            contents.into(),
+            None,
+            &ParseOptions::lenient(),
+        )
+        .unwrap();
+
+        assert_eq!(doc.to_string(), contents);
+        assert_eq!(doc.get("substituters"), Some("https://a https://b".into()));
+        assert_eq!(doc.comment("substituters").as_deref(), Some("# caches"));
+        assert_eq!(
+            doc.to_config().origin("cores"),
+            Some(&Origin::File {
+                path: None,
+                line: 4
+            })
+        );
+
+        doc.set("substituters", "https://c").unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "substituters = https://c # caches\r\ncores = 4\r\n"
+        );
+
+        // Fixes refer to the lines as written.
+        let mut doc = NixConfigDocument::parse_string_with_options(
+            "substituters = \\\n  https://a\ncores = 4\ncores = 4\n".into(),
+            None,
+            &ParseOptions::lenient(),
+        )
+        .unwrap();
+        assert_eq!(doc.apply_fixes(&doc.lint()).unwrap(), 1);
+        assert_eq!(
+            doc.to_string(),
+            "substituters = \\\n  https://a\ncores = 4\n"
+        );
+    }
+
+    #[test]
+    fn adds_and_removes_headers() {
+        let mut doc = NixConfigDocument::parse_string("cores = 4\r\n".into(), None).unwrap();
+        assert!(doc.leading_comments().is_empty());
+
+        doc.set_leading_comments(&["# Managed by nix-installer", ""]);
+        assert_eq!(
+            doc.to_string(),
+            "# Managed by nix-installer\r\n#\r\n\r\ncores = 4\r\n"
+        );
+        assert_eq!(doc.leading_comments(), ["# Managed by nix-installer", "#"]);
+
+        doc.set_leading_comments(&[]);
+        assert_eq!(doc.to_string(), "\r\ncores = 4\r\n");
+    }
+
+    #[test]
+    fn edits_stay_in_the_including_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let included_conf = temp_dir.path().join("included.conf");
+
+        std::fs::write(&included_conf, "cores = 8\n").unwrap();
+        let contents = format!("cores = 2\ninclude {}\n", included_conf.display());
+        std::fs::write(&main_conf, &contents).unwrap();
+
+        let mut doc = NixConfigDocument::parse_file(&main_conf).unwrap();
+        doc.set("cores", "4").unwrap();
+
+        assert_eq!(
+            doc.to_string(),
+            format!("cores = 4\ninclude {}\n", included_conf.display())
+        );
+        assert_eq!(doc.includes()[0].path(), included_conf);
+        assert_eq!(
+            std::fs::read_to_string(&included_conf).unwrap(),
+            "cores = 8\n"
+        );
+    }
+
+    #[test]
+    fn replaces_attached_comments() {
+        let mut doc = NixConfigDocument::parse_string(
+            "# Header\n\n# old comment\n# spanning lines\nsubstituters = https://a.example.com\nsandbox = true"
+                .into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.comment("substituters").as_deref(),
+            Some("# old comment\n# spanning lines")
+        );
+
+        doc.set_with_comment("substituters", "https://b.example.com", "new comment")
+            .unwrap();
+        doc.set_with_comment("trusted-users", "root", "# first\nsecond")
+            .unwrap();
+
+        assert_eq!(
+            doc.to_string(),
+            "# Header\n\n# new comment\nsubstituters = https://b.example.com\nsandbox = true\n# first\n# second\ntrusted-users = root\n"
+        );
+        assert_eq!(doc.comment("sandbox"), None);
+
+        assert_eq!(doc.remove("sandbox"), Some("true".into()));
+        assert!(doc.set("bad name", "value").is_err());
+    }
+
+    #[test]
+    fn keeps_the_header_apart_from_the_first_setting() {
+        let mut doc = NixConfigDocument::parse_string(
+            "# Managed by Ansible\n# Do not edit\ncores = 4\n".into(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(doc.comment("cores"), None);
+
+        doc.set_with_comment("cores", "8", "more cores").unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "# Managed by Ansible\n# Do not edit\n\n# more cores\ncores = 8\n"
+        );
+        assert_eq!(doc.comment("cores").as_deref(), Some("# more cores"));
+
+        doc.set_with_comment("cores", "16", "even more cores")
+            .unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "# Managed by Ansible\n# Do not edit\n\n# even more cores\ncores = 16\n"
+        );
+    }
+
+    #[test]
+    fn reads_lint_ignores() {
+        let doc = NixConfigDocument::parse_string(
+            [
+                "# nixconf-lint: ignore deprecated-alias,identical-override",
+                "# Keep this until the fleet is migrated.",
+                "",
+                "binary-caches = https://cache.nixos.org",
+                "cores = 4 #nixconf-lint: ignore  unknown-setting",
+                "# nixconf-lint: ignored cores",
+                "max-jobs = 2",
+            ]
+            .join("\n"),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.lint_ignores(),
+            HashMap::from([
+                (
+                    4,
+                    vec![
+                        "deprecated-alias".to_owned(),
+                        "identical-override".to_owned()
+                    ]
+                ),
+                (5, vec!["unknown-setting".to_owned()]),
+            ])
+        );
+    }
+}
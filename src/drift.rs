@@ -0,0 +1,358 @@
+use crate::{NixConfig, SettingDiff, SettingInfo, SettingValue};
+
+/// A single way in which an actual config deviates from the expected one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "kebab-case")
+)]
+pub enum DriftFinding {
+    /// The setting is expected, but not set.
+    Missing {
+        name: String,
+        expected: SettingValue,
+    },
+    /// The setting is set, but not expected.
+    Extra { name: String, actual: SettingValue },
+    /// The setting is set to a different value than expected.
+    Changed {
+        name: String,
+        expected: SettingValue,
+        actual: SettingValue,
+    },
+    /// The list setting is missing some expected items, or has additional
+    /// items.
+    ListItems {
+        name: String,
+        missing: Vec<String>,
+        extra: Vec<String>,
+    },
+}
+
+impl DriftFinding {
+    pub fn name(&self) -> &str {
+        match self {
+            DriftFinding::Missing { name, .. }
+            | DriftFinding::Extra { name, .. }
+            | DriftFinding::Changed { name, .. }
+            | DriftFinding::ListItems { name, .. } => name,
+        }
+    }
+}
+
+/// How an actual config has drifted from the expected one, for example for
+/// fleet compliance checks.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DriftReport {
+    findings: Vec<DriftFinding>,
+}
+
+impl DriftReport {
+    /// Compare `actual` against `expected`. Both configs are canonicalized
+    /// first (see [`NixConfig::canonicalize`]), and list settings known to the
+    /// registry are compared item by item. A list with the same items in a
+    /// different order is reported as [`DriftFinding::Changed`], as the order
+    /// of items such as substituters matters to Nix.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{DriftFinding, DriftReport, NixConfig};
+    ///
+    /// let expected = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org https://example.com".into(),
+    ///     None,
+    /// )?;
+    /// let actual = NixConfig::parse_string("binary-caches = https://cache.nixos.org".into(), None)?;
+    ///
+    /// let report = DriftReport::between(&expected, &actual);
+    ///
+    /// assert_eq!(
+    ///     report.findings(),
+    ///     &[DriftFinding::ListItems {
+    ///         name: "substituters".into(),
+    ///         missing: vec!["https://example.com".into()],
+    ///         extra: vec![],
+    ///     }]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn between(expected: &NixConfig, actual: &NixConfig) -> Self {
+        let findings = expected
+            .canonicalize()
+            .diff(&actual.canonicalize())
+            .into_iter()
+            .map(|diff| match diff {
+                SettingDiff::Removed { name, value } => DriftFinding::Missing {
+                    name,
+                    expected: value,
+                },
+                SettingDiff::Added { name, value } => DriftFinding::Extra {
+                    name,
+                    actual: value,
+                },
+                SettingDiff::Changed { name, old, new } => {
+                    list_drift(&name, &old, &new).unwrap_or(DriftFinding::Changed {
+                        name,
+                        expected: old,
+                        actual: new,
+                    })
+                }
+            })
+            .collect();
+
+        Self { findings }
+    }
+
+    pub fn findings(&self) -> &[DriftFinding] {
+        &self.findings
+    }
+
+    pub fn into_findings(self) -> Vec<DriftFinding> {
+        self.findings
+    }
+
+    /// Whether the actual config matches the expected one.
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl NixConfig {
+    /// Check that `other` has at least everything this config sets, for
+    /// example that a host's config includes a fleet-wide baseline.
+    ///
+    /// Both configs are canonicalized first (see [`NixConfig::canonicalize`]).
+    /// List settings known to the registry only need to contain every item of
+    /// this config's value, in `<name>` or `extra-<name>`; other settings must
+    /// have the same value. On failure, returns a [`DriftFinding::Missing`],
+    /// [`DriftFinding::Changed`] or [`DriftFinding::ListItems`] (without
+    /// `extra` items) for each setting that isn't covered.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{DriftFinding, NixConfig};
+    ///
+    /// let baseline = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org https://example.com".into(),
+    ///     None,
+    /// )?;
+    /// let host = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org\nextra-substituters = https://example.com\ncores = 4"
+    ///         .into(),
+    ///     None,
+    /// )?;
+    /// assert!(baseline.is_subset_of(&host).is_ok());
+    ///
+    /// let host = NixConfig::parse_string("substituters = https://cache.nixos.org".into(), None)?;
+    /// assert_eq!(
+    ///     baseline.is_subset_of(&host),
+    ///     Err(vec![DriftFinding::ListItems {
+    ///         name: "substituters".into(),
+    ///         missing: vec!["https://example.com".into()],
+    ///         extra: vec![],
+    ///     }])
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_subset_of(&self, other: &NixConfig) -> Result<(), Vec<DriftFinding>> {
+        let required = self.canonicalize();
+        let available = other.canonicalize();
+
+        let mut findings = Vec::new();
+        for (name, expected) in required.settings() {
+            let list_name = SettingInfo::lookup(name)
+                .filter(|info| info.setting_type().is_list())
+                .map(|info| info.name());
+
+            let Some(list_name) = list_name else {
+                match available.settings().get(name) {
+                    Some(actual) if actual == expected => {}
+                    Some(actual) => findings.push(DriftFinding::Changed {
+                        name: name.clone(),
+                        expected: expected.clone(),
+                        actual: actual.clone(),
+                    }),
+                    None => findings.push(DriftFinding::Missing {
+                        name: name.clone(),
+                        expected: expected.clone(),
+                    }),
+                }
+                continue;
+            };
+
+            let extra_name = format!("extra-{list_name}");
+            let values = [list_name, extra_name.as_str()]
+                .into_iter()
+                .filter_map(|name| available.settings().get(name))
+                .collect::<Vec<_>>();
+            if values.is_empty() && !expected.is_empty() {
+                findings.push(DriftFinding::Missing {
+                    name: name.clone(),
+                    expected: expected.clone(),
+                });
+                continue;
+            }
+
+            let items = values
+                .iter()
+                .flat_map(|value| value.as_list())
+                .collect::<Vec<_>>();
+            let missing = expected
+                .as_list()
+                .into_iter()
+                .filter(|item| !items.contains(item))
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                findings.push(DriftFinding::ListItems {
+                    name: name.clone(),
+                    missing,
+                    extra: vec![],
+                });
+            }
+        }
+
+        if findings.is_empty() {
+            Ok(())
+        } else {
+            Err(findings)
+        }
+    }
+}
+
+fn list_drift(name: &str, expected: &SettingValue, actual: &SettingValue) -> Option<DriftFinding> {
+    if !SettingInfo::lookup(name)?.setting_type().is_list() {
+        return None;
+    }
+
+    let expected = expected.as_list();
+    let actual = actual.as_list();
+
+    let missing = expected
+        .iter()
+        .filter(|item| !actual.contains(item))
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>();
+    let extra = actual
+        .iter()
+        .filter(|item| !expected.contains(item))
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() && extra.is_empty() {
+        return None;
+    }
+
+    Some(DriftFinding::ListItems {
+        name: name.to_owned(),
+        missing,
+        extra,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categorizes_findings() {
+        let expected = NixConfig::parse_string(
+            r#"
+                experimental-features = nix-command flakes
+                trusted-users = root @wheel
+                substituters = https://a.example.com https://b.example.com
+                keep-outputs = true
+                cores = 4
+            "#
+            .into(),
+            None,
+        )
+        .unwrap();
+        let actual = NixConfig::parse_string(
+            r#"
+                experimental-features = nix-command flakes
+                trusted-users = root alice
+                substituters = https://b.example.com https://a.example.com
+                build-cores = 8
+                warn-dirty = false
+            "#
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let report = DriftReport::between(&expected, &actual);
+
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.findings(),
+            &[
+                DriftFinding::ListItems {
+                    name: "trusted-users".into(),
+                    missing: vec!["@wheel".into()],
+                    extra: vec!["alice".into()],
+                },
+                DriftFinding::Changed {
+                    name: "substituters".into(),
+                    expected: "https://a.example.com https://b.example.com".into(),
+                    actual: "https://b.example.com https://a.example.com".into(),
+                },
+                DriftFinding::Missing {
+                    name: "keep-outputs".into(),
+                    expected: "true".into(),
+                },
+                DriftFinding::Changed {
+                    name: "cores".into(),
+                    expected: "4".into(),
+                    actual: "8".into(),
+                },
+                DriftFinding::Extra {
+                    name: "warn-dirty".into(),
+                    actual: "false".into(),
+                },
+            ]
+        );
+
+        assert!(DriftReport::between(&expected, &expected).is_clean());
+    }
+
+    #[test]
+    fn checks_subsets() {
+        let baseline = NixConfig::parse_string(
+            "extra-trusted-users = @wheel\nsandbox = true\nkeep-outputs = true\ncores = 4".into(),
+            None,
+        )
+        .unwrap();
+        let host = NixConfig::parse_string(
+            "trusted-users = root @wheel\nbuild-use-sandbox = true\ncores = 8\nwarn-dirty = false"
+                .into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            baseline.is_subset_of(&host),
+            Err(vec![
+                DriftFinding::Missing {
+                    name: "keep-outputs".into(),
+                    expected: "true".into(),
+                },
+                DriftFinding::Changed {
+                    name: "cores".into(),
+                    expected: "4".into(),
+                    actual: "8".into(),
+                },
+            ])
+        );
+        assert!(host.is_subset_of(&host).is_ok());
+        assert!(NixConfig::new().is_subset_of(&host).is_ok());
+    }
+}
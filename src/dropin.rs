@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{NixConfig, ParseError, ParseOptions};
+
+/// Where to look for a config file and its drop-ins, following the systemd
+/// convention: each root directory may hold the base file (such as
+/// `nix.conf`) and a `<name>.d` directory of `*.conf` drop-ins.
+///
+/// Roots are listed from highest to lowest priority. Of the files with the
+/// same name, only the one in the highest-priority root is used, and it
+/// masks the others. A file that is a symlink to `/dev/null` masks the others
+/// without being read, disabling them. The winning base file is read first,
+/// followed by the winning drop-ins in lexicographic order of their names.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::DropInLayout;
+///
+/// let temp_dir = tempfile::TempDir::new()?;
+/// let (etc, vendor) = (temp_dir.path().join("etc"), temp_dir.path().join("usr/lib"));
+/// for root in [&etc, &vendor] {
+///     std::fs::create_dir_all(root.join("nix.conf.d"))?;
+/// }
+/// std::fs::write(vendor.join("nix.conf"), "cores = 2\nmax-jobs = 2\n")?;
+/// std::fs::write(vendor.join("nix.conf.d/50-cache.conf"), "substituters = https://vendor.example.com\n")?;
+/// std::fs::write(etc.join("nix.conf.d/50-cache.conf"), "substituters = https://local.example.com\n")?;
+/// std::fs::write(etc.join("nix.conf.d/90-jobs.conf"), "max-jobs = 8\n")?;
+///
+/// let layout = DropInLayout::new("nix.conf").root(&etc).root(&vendor);
+///
+/// let files = layout.resolve()?;
+/// assert_eq!(files.len(), 3);
+/// assert_eq!(files[1].path(), etc.join("nix.conf.d/50-cache.conf"));
+/// assert_eq!(files[1].masks(), [vendor.join("nix.conf.d/50-cache.conf")]);
+///
+/// let nix_conf = layout.load()?;
+/// assert_eq!(nix_conf.settings().get("substituters").unwrap(), "https://local.example.com");
+/// assert_eq!(nix_conf.settings().get("max-jobs").unwrap(), "8");
+/// assert_eq!(nix_conf.settings().get("cores").unwrap(), "2");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DropInLayout {
+    name: String,
+    roots: Vec<PathBuf>,
+}
+
+/// A file picked by [`DropInLayout::resolve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DropIn {
+    name: String,
+    path: PathBuf,
+    masks: Vec<PathBuf>,
+    is_base: bool,
+    is_disabled: bool,
+}
+
+impl DropIn {
+    /// The file name shared by the winning file and the files it masks.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The winning file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The files of the same name in lower-priority roots, which are not
+    /// read, from highest to lowest priority.
+    pub fn masks(&self) -> &[PathBuf] {
+        &self.masks
+    }
+
+    /// Whether this is the base file rather than a drop-in.
+    pub fn is_base(&self) -> bool {
+        self.is_base
+    }
+
+    /// Whether the winning file is a symlink to `/dev/null`, which disables
+    /// the files it masks without being read itself.
+    pub fn is_disabled(&self) -> bool {
+        self.is_disabled
+    }
+}
+
+impl DropInLayout {
+    /// A layout for the config file `name` without any roots yet.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// `nix.conf` in `/etc/nix`, `/run/nix` and `/usr/lib/nix`, in that order
+    /// of priority.
+    pub fn systemd() -> Self {
+        Self::new("nix.conf")
+            .root("/etc/nix")
+            .root("/run/nix")
+            .root("/usr/lib/nix")
+    }
+
+    /// Add a root with a lower priority than the roots added before.
+    pub fn root(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.roots.push(dir.into());
+        self
+    }
+
+    /// The files to read, in order: the base file, if any root has one,
+    /// followed by the drop-ins by name. Roots that don't exist are skipped.
+    pub fn resolve(&self) -> Result<Vec<DropIn>, ParseError> {
+        let mut files = Vec::new();
+
+        let base = self
+            .roots
+            .iter()
+            .map(|root| root.join(&self.name))
+            .filter(|path| exists(path))
+            .collect::<Vec<_>>();
+        if let Some((path, masks)) = base.split_first() {
+            files.push(DropIn {
+                name: self.name.clone(),
+                is_disabled: is_dev_null(path),
+                path: path.clone(),
+                masks: masks.to_vec(),
+                is_base: true,
+            });
+        }
+
+        let drop_in_dir = format!("{}.d", self.name);
+        let mut drop_ins = BTreeMap::<String, Vec<PathBuf>>::new();
+        for root in &self.roots {
+            let dir = root.join(&drop_in_dir);
+            let failed = |e| ParseError::FailedToReadFile(dir.clone(), e);
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(failed(e)),
+            };
+
+            for entry in entries {
+                let path = entry.map_err(failed)?.path();
+                let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                if name.ends_with(".conf") && (path.is_file() || is_dev_null(&path)) {
+                    drop_ins.entry(name.to_owned()).or_default().push(path);
+                }
+            }
+        }
+
+        for (name, paths) in drop_ins {
+            let (path, masks) = paths.split_first().expect("every drop-in name has a file");
+            files.push(DropIn {
+                name,
+                is_disabled: is_dev_null(path),
+                path: path.clone(),
+                masks: masks.to_vec(),
+                is_base: false,
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Read the [resolved](DropInLayout::resolve) files in order, later ones
+    /// overriding earlier ones like in
+    /// [`NixConfig::apply_string`](crate::NixConfig::apply_string).
+    pub fn load(&self) -> Result<NixConfig, ParseError> {
+        self.load_with_options(&ParseOptions::default())
+    }
+
+    /// Like [`DropInLayout::load`], using the provided [`ParseOptions`].
+    pub fn load_with_options(&self, options: &ParseOptions) -> Result<NixConfig, ParseError> {
+        let mut nix_conf = NixConfig::new();
+        for file in self.resolve()? {
+            if file.is_disabled {
+                continue;
+            }
+            let contents = crate::read_file(&file.path)?;
+            nix_conf.apply_string_with_options(&contents, Some(&file.path), options)?;
+        }
+        Ok(nix_conf)
+    }
+}
+
+fn exists(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+fn is_dev_null(path: &Path) -> bool {
+    std::fs::read_link(path).is_ok_and(|target| target == Path::new("/dev/null"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn dev_null_disables_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let (etc, run) = (temp_dir.path().join("etc"), temp_dir.path().join("run"));
+        for root in [&etc, &run] {
+            std::fs::create_dir_all(root.join("nix.conf.d")).unwrap();
+        }
+        std::fs::write(run.join("nix.conf"), "cores = 2\n").unwrap();
+        std::fs::write(run.join("nix.conf.d/10-sandbox.conf"), "sandbox = false\n").unwrap();
+        std::fs::write(run.join("nix.conf.d/notes.txt"), "not a config").unwrap();
+        std::os::unix::fs::symlink("/dev/null", etc.join("nix.conf.d/10-sandbox.conf")).unwrap();
+
+        let layout = DropInLayout::new("nix.conf")
+            .root(&etc)
+            .root(&run)
+            .root(temp_dir.path().join("missing"));
+        let files = layout.resolve().unwrap();
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| (file.name(), file.is_base(), file.is_disabled()))
+                .collect::<Vec<_>>(),
+            [("nix.conf", true, false), ("10-sandbox.conf", false, true)]
+        );
+        assert_eq!(files[1].masks(), [run.join("nix.conf.d/10-sandbox.conf")]);
+
+        let nix_conf = layout.load().unwrap();
+        assert_eq!(nix_conf.settings().get("cores").unwrap(), "2");
+        assert!(nix_conf.settings().get("sandbox").is_none());
+    }
+}
@@ -0,0 +1,547 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::{
+    json, parse_contents, read_file, Assignment, Dialect, NixConfig, Origin, ParseError,
+    ParseOptions, SettingInfo, SettingType, SettingValue,
+};
+
+/// How the value of a single setting in an [`EffectiveConfig`] came to be.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettingProvenance {
+    value: SettingValue,
+    origin: Origin,
+    overridden: Vec<(SettingValue, Origin)>,
+}
+
+impl SettingProvenance {
+    /// The final value of the setting.
+    pub fn value(&self) -> &SettingValue {
+        &self.value
+    }
+
+    /// Where the final value (or, for list settings extended using
+    /// `extra-<name>`, the last addition to it) came from.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// The values this setting had before it got its final value, oldest
+    /// first.
+    pub fn overridden(&self) -> &[(SettingValue, Origin)] {
+        &self.overridden
+    }
+
+    /// Whether the setting still has Nix's built-in default value.
+    pub fn is_default(&self) -> bool {
+        self.origin == Origin::Default
+    }
+}
+
+/// The configuration Nix would actually use, resolved offline from the same
+/// sources Nix reads, along with where each value came from.
+///
+/// Settings are resolved to their canonical names, so legacy aliases such as
+/// `binary-caches` end up as `substituters`, and `extra-<name>` settings are
+/// appended to `<name>`.
+#[derive(Clone, Debug, Default)]
+pub struct EffectiveConfig {
+    config: NixConfig,
+    provenance: IndexMap<String, SettingProvenance>,
+}
+
+impl EffectiveConfig {
+    /// Resolve the effective configuration in the same order as Nix:
+    ///
+    /// 1. Built-in defaults (for settings of [`Dialect::Nix`] with a
+    ///    platform-independent default)
+    /// 2. Each of `files` in order, typically
+    ///    [`paths::system_config_file`](crate::paths::system_config_file)
+    ///    followed by [`paths::user_config_files`](crate::paths::user_config_files),
+    ///    which already honors `NIX_USER_CONF_FILES`
+    /// 3. The contents of `NIX_CONFIG`, if set in `env`
+    /// 4. Settings passed in `cli_args`, as `--option <name> <value>`,
+    ///    `--<name> <value>`, `--extra-<name> <value>`, or `--<name>` and
+    ///    `--no-<name>` for boolean settings. Other arguments are ignored.
+    ///
+    /// Like Nix, files that don't exist are skipped.
+    ///
+    /// Only `NIX_CONFIG` is read from `env`; `NIX_CONF_DIR`,
+    /// `NIX_USER_CONF_FILES` and the XDG variables in it have no effect, as
+    /// they only decide which files to read. To resolve those from the same
+    /// (for example, captured) environment, pass the files
+    /// [`paths::system_config_file_from`](crate::paths::system_config_file_from)
+    /// and [`paths::user_config_files_from`](crate::paths::user_config_files_from)
+    /// find in it:
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::collections::HashMap;
+    ///
+    /// use nix_config_parser::{paths, EffectiveConfig};
+    ///
+    /// let temp_dir = tempfile::TempDir::new()?;
+    /// let user_conf = temp_dir.path().join("user.conf");
+    /// std::fs::write(&user_conf, "cores = 4")?;
+    ///
+    /// let captured = HashMap::from([
+    ///     ("NIX_CONF_DIR", temp_dir.path().display().to_string()),
+    ///     ("NIX_USER_CONF_FILES", user_conf.display().to_string()),
+    /// ]);
+    /// let lookup = |name: &str| captured.get(name).cloned();
+    ///
+    /// let mut files = vec![paths::system_config_file_from(lookup)];
+    /// files.extend(paths::user_config_files_from(lookup));
+    /// let effective = EffectiveConfig::compute(&files, &captured, &[] as &[&str])?;
+    ///
+    /// assert_eq!(effective.config().settings().get("cores").unwrap(), "4");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{EffectiveConfig, Origin};
+    ///
+    /// let effective = EffectiveConfig::compute(
+    ///     &["/does/not/exist/nix.conf"],
+    ///     [("NIX_CONFIG", "extra-substituters = https://example.com")],
+    ///     &["build", "--max-jobs", "4"],
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     effective.config().settings().get("substituters").unwrap(),
+    ///     "https://cache.nixos.org/ https://example.com"
+    /// );
+    ///
+    /// let max_jobs = effective.provenance("max-jobs").unwrap();
+    /// assert_eq!(max_jobs.value(), "4");
+    /// assert_eq!(max_jobs.origin(), &Origin::CommandLine);
+    /// assert!(!max_jobs.is_default());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compute<P, E, K, V, A>(files: &[P], env: E, cli_args: &[A]) -> Result<Self, ParseError>
+    where
+        P: AsRef<Path>,
+        E: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+        A: AsRef<str>,
+    {
+        let env = env
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().to_owned(), v.as_ref().to_owned()))
+            .collect::<HashMap<_, _>>();
+
+        let mut effective = Self::default();
+
+        for info in SettingInfo::all() {
+            if !info.is_supported_by(Dialect::Nix) {
+                continue;
+            }
+
+            if let Some(default) = info.default_value() {
+                effective.apply(info.name(), default.into(), Origin::Default);
+            }
+        }
+
+        for file in files.iter().map(AsRef::as_ref) {
+            let contents = match read_file(file) {
+                Ok(contents) => contents,
+                Err(ParseError::FileNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+
+            effective.apply_all(
+                parse_contents(&contents, Some(file), &ParseOptions::default())?.assignments,
+            );
+        }
+
+        if let Some(contents) = env.get("NIX_CONFIG") {
+            let mut assignments =
+                parse_contents(contents, None, &ParseOptions::default())?.assignments;
+            for assignment in &mut assignments {
+                if let Origin::File { path: None, line } = assignment.origin {
+                    assignment.origin = Origin::Environment {
+                        variable: "NIX_CONFIG".into(),
+                        line,
+                    };
+                }
+            }
+
+            effective.apply_all(assignments);
+        }
+
+        effective.apply_cli_args(cli_args);
+
+        for (name, provenance) in &effective.provenance {
+            effective
+                .config
+                .settings_mut()
+                .insert(name.clone(), provenance.value.clone());
+            effective.config.set_origin(name, provenance.origin.clone());
+        }
+
+        Ok(effective)
+    }
+
+    /// The final value of every setting.
+    pub fn config(&self) -> &NixConfig {
+        &self.config
+    }
+
+    pub fn into_config(self) -> NixConfig {
+        self.config
+    }
+
+    /// How the setting `name` got its final value.
+    pub fn provenance(&self, name: impl AsRef<str>) -> Option<&SettingProvenance> {
+        self.provenance.get(name.as_ref())
+    }
+
+    /// The provenance of every setting, in the order they were first set.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SettingProvenance)> {
+        self.provenance
+            .iter()
+            .map(|(name, provenance)| (name.as_str(), provenance))
+    }
+
+    /// Render the whole resolution as a JSON report, for support bundles and
+    /// diagnostic tools. The report is an object with a `settings` array
+    /// holding, for every setting in the order they were first set:
+    ///
+    /// - `name` and final `value`
+    /// - `default`: Nix's built-in default, or `null` if it has none (or it
+    ///   depends on the platform)
+    /// - `origin`: where the final value came from
+    /// - `is-default`: whether the final value is the built-in default
+    /// - `overridden`: whether any earlier value was replaced
+    /// - `history`: every value the setting had, oldest first and ending with
+    ///   the final one, each with its `value` and `origin`
+    ///
+    /// Origins are objects with a `kind` of `default`, `file` (with `path`,
+    /// which may be `null`, and `line`), `environment` (with `variable` and
+    /// `line`) or `command-line`.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::EffectiveConfig;
+    ///
+    /// let effective = EffectiveConfig::compute(
+    ///     &["/does/not/exist/nix.conf"],
+    ///     [("NIX_CONFIG", "require-sigs = false")],
+    ///     &[] as &[&str],
+    /// )?;
+    ///
+    /// let report = effective.to_report_json();
+    /// assert!(report.contains(concat!(
+    ///     r#"{"name":"require-sigs","value":"false","default":"true","#,
+    ///     r#""origin":{"kind":"environment","variable":"NIX_CONFIG","line":1},"#,
+    ///     r#""is-default":false,"overridden":true,"#,
+    /// )));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_report_json(&self) -> String {
+        let mut out = String::from(r#"{"settings":["#);
+
+        for (index, (name, provenance)) in self.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+
+            out.push_str(r#"{"name":"#);
+            json::string(&mut out, name);
+            out.push_str(r#","value":"#);
+            json::string(&mut out, &provenance.value);
+            out.push_str(r#","default":"#);
+            json::optional_string(
+                &mut out,
+                SettingInfo::lookup(name).and_then(|info| info.default_value()),
+            );
+            out.push_str(r#","origin":"#);
+            json::origin(&mut out, &provenance.origin);
+            out.push_str(r#","is-default":"#);
+            out.push_str(if provenance.is_default() {
+                "true"
+            } else {
+                "false"
+            });
+            out.push_str(r#","overridden":"#);
+            out.push_str(if provenance.overridden.is_empty() {
+                "false"
+            } else {
+                "true"
+            });
+
+            out.push_str(r#","history":["#);
+            let history = provenance
+                .overridden
+                .iter()
+                .map(|(value, origin)| (value, origin))
+                .chain([(&provenance.value, &provenance.origin)]);
+            for (index, (value, origin)) in history.enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(r#"{"value":"#);
+                json::string(&mut out, value);
+                out.push_str(r#","origin":"#);
+                json::origin(&mut out, origin);
+                out.push('}');
+            }
+            out.push_str("]}");
+        }
+
+        out.push_str("]}");
+        out
+    }
+
+    /// Replace every value, including overridden ones, with `f(name, value)`.
+    #[cfg(feature = "support")]
+    pub(crate) fn map_values(&mut self, f: impl Fn(&str, &SettingValue) -> SettingValue) {
+        for (name, provenance) in &mut self.provenance {
+            provenance.value = f(name, &provenance.value);
+            for (value, _) in &mut provenance.overridden {
+                *value = f(name, value);
+            }
+        }
+        for (name, value) in self.config.settings_mut() {
+            *value = f(name, value);
+        }
+    }
+
+    fn apply_all(&mut self, assignments: Vec<Assignment>) {
+        for Assignment {
+            name,
+            value,
+            origin,
+        } in assignments
+        {
+            match value {
+                Some(value) => self.apply(&name, value, origin),
+                None => self.unset(&name),
+            }
+        }
+    }
+
+    fn apply_cli_args<A: AsRef<str>>(&mut self, cli_args: &[A]) {
+        let mut args = cli_args.iter().map(AsRef::as_ref);
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            }
+
+            let Some(flag) = arg.strip_prefix("--") else {
+                continue;
+            };
+
+            if flag == "option" {
+                if let (Some(name), Some(value)) = (args.next(), args.next()) {
+                    self.apply(name, value.into(), Origin::CommandLine);
+                }
+            } else if let Some(info) = SettingInfo::lookup(flag) {
+                if info.setting_type() == SettingType::Boolean {
+                    self.apply(flag, "true".into(), Origin::CommandLine);
+                } else if let Some(value) = args.next() {
+                    self.apply(flag, value.into(), Origin::CommandLine);
+                }
+            } else if let Some(name) = flag.strip_prefix("no-") {
+                if SettingInfo::lookup(name)
+                    .is_some_and(|info| info.setting_type() == SettingType::Boolean)
+                {
+                    self.apply(name, "false".into(), Origin::CommandLine);
+                }
+            }
+        }
+    }
+
+    fn unset(&mut self, name: &str) {
+        let canonical = SettingInfo::lookup(name).map_or(name, |info| info.name());
+        self.provenance.shift_remove(canonical);
+    }
+
+    fn apply(&mut self, name: &str, value: SettingValue, origin: Origin) {
+        let info = SettingInfo::lookup(name);
+        let canonical = info.map_or(name, |info| info.name());
+        let append = info.is_some_and(|info| {
+            name != info.name() && !info.aliases().contains(&name) && name.starts_with("extra-")
+        });
+
+        match self.provenance.get_mut(canonical) {
+            Some(provenance) => {
+                let new_value = if append {
+                    let mut new_value = provenance.value.clone();
+                    for item in value.split_whitespace() {
+                        new_value.push_item(item);
+                    }
+                    new_value
+                } else {
+                    value
+                };
+
+                let old_value = std::mem::replace(&mut provenance.value, new_value);
+                let old_origin = std::mem::replace(&mut provenance.origin, origin);
+                provenance.overridden.push((old_value, old_origin));
+            }
+            None => {
+                let value = if append {
+                    value
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        .into()
+                } else {
+                    value
+                };
+
+                self.provenance.insert(
+                    canonical.to_owned(),
+                    SettingProvenance {
+                        value,
+                        origin,
+                        overridden: Vec::new(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_in_nix_precedence_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let system_conf = temp_dir.path().join("nix.conf");
+        let user_conf = temp_dir.path().join("user.conf");
+
+        std::fs::write(
+            &system_conf,
+            "binary-caches = https://a.example.com\nkeep-outputs = true\ncores = 2",
+        )
+        .unwrap();
+        std::fs::write(
+            &user_conf,
+            "extra-substituters = https://b.example.com\ncores = 4",
+        )
+        .unwrap();
+
+        let effective = EffectiveConfig::compute(
+            &[
+                system_conf.clone(),
+                temp_dir.path().join("missing.conf"),
+                user_conf.clone(),
+            ],
+            [("NIX_CONFIG", "cores = 6\nmax-jobs = 3")],
+            &[
+                "--no-keep-outputs",
+                "--option",
+                "max-jobs",
+                "8",
+                "--fallback",
+            ],
+        )
+        .unwrap();
+
+        let substituters = effective.provenance("substituters").unwrap();
+        assert_eq!(
+            substituters.value(),
+            "https://a.example.com https://b.example.com"
+        );
+        assert_eq!(
+            substituters.origin(),
+            &Origin::File {
+                path: Some(user_conf.clone()),
+                line: 1
+            }
+        );
+        assert_eq!(
+            substituters.overridden(),
+            &[
+                ("https://cache.nixos.org/".into(), Origin::Default),
+                (
+                    "https://a.example.com".into(),
+                    Origin::File {
+                        path: Some(system_conf.clone()),
+                        line: 1
+                    }
+                ),
+            ]
+        );
+
+        let cores = effective.provenance("cores").unwrap();
+        assert_eq!(cores.value(), "6");
+        assert_eq!(
+            cores.origin(),
+            &Origin::Environment {
+                variable: "NIX_CONFIG".into(),
+                line: 1
+            }
+        );
+        assert_eq!(cores.overridden().len(), 3);
+
+        assert_eq!(effective.provenance("max-jobs").unwrap().value(), "8");
+        assert_eq!(
+            effective.provenance("keep-outputs").unwrap().value(),
+            "false"
+        );
+        assert_eq!(effective.provenance("fallback").unwrap().value(), "true");
+        assert!(effective.provenance("require-sigs").unwrap().is_default());
+        assert!(!effective.config().settings().contains_key("binary-caches"));
+        assert_eq!(
+            effective.config().origin("max-jobs"),
+            Some(&Origin::CommandLine)
+        );
+    }
+
+    #[test]
+    fn propagates_parse_errors() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let system_conf = temp_dir.path().join("nix.conf");
+        std::fs::write(&system_conf, "bad config").unwrap();
+
+        match EffectiveConfig::compute(&[system_conf], [("", "")], &[""]) {
+            Err(ParseError::MissingEquals(_, _)) => (),
+            _ => panic!("bad config should have returned ParseError::MissingEquals"),
+        }
+    }
+
+    #[test]
+    fn reports_resolution_as_json() {
+        let effective = EffectiveConfig::compute(
+            &[] as &[&str],
+            [("NIX_CONFIG", "cores = 2\nmy-plugin-setting = \"quoted\"")],
+            &["--cores", "4"],
+        )
+        .unwrap();
+
+        let report = effective.to_report_json();
+        assert!(report.starts_with(r#"{"settings":[{"name":"#));
+        assert!(report.ends_with("]}]}"));
+        assert!(report.contains(concat!(
+            r#"{"name":"cores","value":"4","default":"0","origin":{"kind":"command-line"},"#,
+            r#""is-default":false,"overridden":true,"history":["#,
+            r#"{"value":"0","origin":{"kind":"default"}},"#,
+            r#"{"value":"2","origin":{"kind":"environment","variable":"NIX_CONFIG","line":1}},"#,
+            r#"{"value":"4","origin":{"kind":"command-line"}}]}"#,
+        )));
+        assert!(report.contains(concat!(
+            r#"{"name":"my-plugin-setting","value":"\"quoted\"","default":null,"#,
+            r#""origin":{"kind":"environment","variable":"NIX_CONFIG","line":2},"#,
+            r#""is-default":false,"overridden":false,"#,
+        )));
+    }
+}
@@ -0,0 +1,96 @@
+use std::borrow::Cow;
+
+use crate::InvalidUtf8;
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Decode `bytes` as UTF-8, or as UTF-16 if they start with its byte order
+/// mark, see [`NixConfig::parse_bytes`](crate::NixConfig::parse_bytes). On
+/// failure, returns the offset of the first invalid byte.
+pub(crate) fn decode(bytes: &[u8], invalid: InvalidUtf8) -> Result<Cow<'_, str>, usize> {
+    match bytes {
+        [0xff, 0xfe, rest @ ..] => decode_utf16(rest, u16::from_le_bytes, invalid),
+        [0xfe, 0xff, rest @ ..] => decode_utf16(rest, u16::from_be_bytes, invalid),
+        _ => match bytes.strip_prefix(UTF8_BOM) {
+            Some(rest) => decode_utf8(rest, invalid).map_err(|offset| offset + UTF8_BOM.len()),
+            None => decode_utf8(bytes, invalid),
+        },
+    }
+}
+
+fn decode_utf8(bytes: &[u8], invalid: InvalidUtf8) -> Result<Cow<'_, str>, usize> {
+    match (std::str::from_utf8(bytes), invalid) {
+        (Ok(contents), _) => Ok(Cow::Borrowed(contents)),
+        (Err(_), InvalidUtf8::Replace) => Ok(String::from_utf8_lossy(bytes)),
+        (Err(e), InvalidUtf8::Error) => Err(e.valid_up_to()),
+    }
+}
+
+/// Decode the UTF-16 after the byte order mark.
+fn decode_utf16(
+    bytes: &[u8],
+    from_bytes: fn([u8; 2]) -> u16,
+    invalid: InvalidUtf8,
+) -> Result<Cow<'_, str>, usize> {
+    const BOM_LEN: usize = 2;
+
+    let mut units = bytes.chunks_exact(2);
+    let mut contents = String::with_capacity(bytes.len() / 2);
+    let mut offset = BOM_LEN;
+
+    for decoded in char::decode_utf16(units.by_ref().map(|unit| from_bytes([unit[0], unit[1]]))) {
+        // An unpaired surrogate is a single unit
+        let len = decoded.as_ref().map_or(1, |c| c.len_utf16()) * 2;
+        match (decoded, invalid) {
+            (Ok(c), _) => contents.push(c),
+            (Err(_), InvalidUtf8::Replace) => contents.push(char::REPLACEMENT_CHARACTER),
+            (Err(_), InvalidUtf8::Error) => return Err(offset),
+        }
+        offset += len;
+    }
+
+    if !units.remainder().is_empty() {
+        match invalid {
+            InvalidUtf8::Replace => contents.push(char::REPLACEMENT_CHARACTER),
+            InvalidUtf8::Error => return Err(BOM_LEN + bytes.len() - 1),
+        }
+    }
+
+    Ok(Cow::Owned(contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_utf16() {
+        let utf16 = |units: &[u16], to_bytes: fn(u16) -> [u8; 2]| {
+            units
+                .iter()
+                .flat_map(|unit| to_bytes(*unit))
+                .collect::<Vec<_>>()
+        };
+        let text = "cores = 🦀 4".encode_utf16().collect::<Vec<_>>();
+
+        for to_bytes in [u16::to_le_bytes as fn(u16) -> [u8; 2], u16::to_be_bytes] {
+            let mut units = vec![0xfeff];
+            units.extend(&text);
+            let bytes = utf16(&units, to_bytes);
+            assert_eq!(decode(&bytes, InvalidUtf8::Error).unwrap(), "cores = 🦀 4");
+            assert_eq!(
+                decode(&bytes[..bytes.len() - 1], InvalidUtf8::Error),
+                Err(bytes.len() - 2)
+            );
+
+            // An unpaired surrogate after `cores`
+            units.insert(6, 0xd800);
+            let bytes = utf16(&units, to_bytes);
+            assert_eq!(decode(&bytes, InvalidUtf8::Error), Err(12));
+            assert_eq!(
+                decode(&bytes, InvalidUtf8::Replace).unwrap(),
+                "cores\u{fffd} = 🦀 4"
+            );
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::env::VarError;
+
+use crate::{NixConfig, SerializeError};
+
+/// What a [placeholder](crate::Placeholder) referring to an environment
+/// variable starts with.
+const ENV_PREFIX: &str = "@env:";
+
+impl NixConfig {
+    /// A copy of the config with the references to environment variables in
+    /// its values replaced by their values. A reference is a
+    /// [placeholder](crate::Placeholder) `@env:VARNAME`, as a token or as the
+    /// part after the `=` of a token such as `github.com=@env:GITHUB_TOKEN`.
+    ///
+    /// This keeps secrets out of templates that are checked in: they are only
+    /// filled in when the final `nix.conf` (or `NIX_CONFIG`) is rendered, see
+    /// [`SerializeOptions::resolve_env`](crate::SerializeOptions::resolve_env).
+    /// Parsing doesn't resolve anything, and Nix itself doesn't understand
+    /// these references.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// std::env::set_var("EXAMPLE_CACHE_KEY", "cache.example.com-1:AbC=");
+    /// let template = NixConfig::parse_string(
+    ///     "trusted-public-keys = cache.nixos.org-1:6NCH= @env:EXAMPLE_CACHE_KEY".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     template.resolve_env()?.to_shell_export()?,
+    ///     "export NIX_CONFIG='trusted-public-keys = cache.nixos.org-1:6NCH= cache.example.com-1:AbC=\n'\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_env(&self) -> Result<NixConfig, SerializeError> {
+        self.interpolate(|placeholder| {
+            if !placeholder.text().starts_with(ENV_PREFIX) {
+                return Ok(None);
+            }
+            std::env::var(placeholder.argument()).map(Some)
+        })
+        .map_err(|e| {
+            let (setting, variable) = (
+                e.setting().to_owned(),
+                e.placeholder()[ENV_PREFIX.len()..].to_owned(),
+            );
+            match e.into_reason() {
+                VarError::NotPresent => {
+                    SerializeError::MissingEnvironmentVariable(setting, variable)
+                }
+                VarError::NotUnicode(_) => {
+                    SerializeError::InvalidEnvironmentVariable(setting, variable)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SerializeOptions;
+
+    #[test]
+    fn resolves_references_when_rendering() {
+        std::env::set_var("NIX_CONFIG_PARSER_TEST_ENV_USERS", "alice bob");
+        std::env::set_var("NIX_CONFIG_PARSER_TEST_ENV_TOKEN", "ghp_secret");
+        let template = NixConfig::parse_string(
+            "trusted-users = root @env:NIX_CONFIG_PARSER_TEST_ENV_USERS\naccess-tokens = github.com=@env:NIX_CONFIG_PARSER_TEST_ENV_TOKEN\nbuild-hook-user = me@env:HOME".into(),
+            None,
+        )
+        .unwrap();
+
+        // Parsing and plain rendering leave the references alone.
+        assert_eq!(
+            template.settings().get("trusted-users"),
+            Some(&"root @env:NIX_CONFIG_PARSER_TEST_ENV_USERS".into())
+        );
+        assert_eq!(
+            template
+                .to_string_with_options(&SerializeOptions::new().resolve_env(true))
+                .unwrap(),
+            "trusted-users = root alice bob\naccess-tokens = github.com=ghp_secret\nbuild-hook-user = me@env:HOME\n"
+        );
+
+        let template =
+            NixConfig::parse_string("cores = @env:NIX_CONFIG_PARSER_TEST_ENV_UNSET".into(), None)
+                .unwrap();
+        assert!(matches!(
+            template.resolve_env(),
+            Err(SerializeError::MissingEnvironmentVariable(name, variable))
+                if name == "cores" && variable == "NIX_CONFIG_PARSER_TEST_ENV_UNSET"
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::{NixConfig, Origin, SettingInfo, SettingType};
+
+/// Settings that hold paths inside the build sandbox, which don't need to
+/// exist outside of it.
+pub(crate) const SANDBOX_PATHS: &[&str] = &["sandbox-build-dir", "sandbox-paths"];
+
+/// Settings whose paths may also be directories.
+const DIRECTORIES_ALLOWED: &[&str] = &["plugin-files"];
+
+/// A path referenced by a setting that Nix won't be able to use, see
+/// [`NixConfig::check_paths_exist`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathFinding {
+    name: String,
+    path: PathBuf,
+    issue: PathIssue,
+    origin: Option<Origin>,
+}
+
+/// What is wrong with the path of a [`PathFinding`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "kebab-case")
+)]
+pub enum PathIssue {
+    /// Nothing exists at the path.
+    Missing,
+    /// The path is a directory, but the setting expects a file.
+    IsADirectory,
+    /// The path exists, but can't be read, for example for lack of
+    /// permissions.
+    Unreadable { reason: String },
+}
+
+impl PathFinding {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn issue(&self) -> &PathIssue {
+        &self.issue
+    }
+
+    /// Where the setting was set, if known.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+}
+
+impl fmt::Display for PathFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{origin}: ")?;
+        }
+        write!(f, "'{}' of '{}' ", self.path.display(), self.name)?;
+        match &self.issue {
+            PathIssue::Missing => f.write_str("does not exist"),
+            PathIssue::IsADirectory => f.write_str("is a directory, not a file"),
+            PathIssue::Unreadable { reason } => write!(f, "can't be read: {reason}"),
+        }
+    }
+}
+
+impl NixConfig {
+    /// Check that the files referenced by path-valued settings such as
+    /// `netrc-file`, `ssl-cert-file`, `secret-key-files` and `plugin-files`
+    /// exist and can be read by the current user, returning every problem
+    /// found, in the order of the settings.
+    ///
+    /// Paths inside the build sandbox, such as those in `sandbox-paths`, and
+    /// relative paths (which [`NixConfig::validate_values`] reports) are not
+    /// checked. The result depends on who runs the check: the Nix daemon may
+    /// be able to read files the current user can't.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, PathIssue};
+    ///
+    /// let temp_dir = tempfile::TempDir::new()?;
+    /// let key = temp_dir.path().join("cache-key.sec");
+    /// std::fs::write(&key, "cache-1:...")?;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     format!(
+    ///         "secret-key-files = {} /does/not/exist.sec\nnetrc-file = {}",
+    ///         key.display(),
+    ///         temp_dir.path().display()
+    ///     ),
+    ///     None,
+    /// )?;
+    ///
+    /// let findings = nix_conf.check_paths_exist();
+    /// assert_eq!(findings.len(), 2);
+    /// assert_eq!(findings[0].path(), std::path::Path::new("/does/not/exist.sec"));
+    /// assert_eq!(findings[0].issue(), &PathIssue::Missing);
+    /// assert_eq!(findings[1].name(), "netrc-file");
+    /// assert_eq!(findings[1].issue(), &PathIssue::IsADirectory);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_paths_exist(&self) -> Vec<PathFinding> {
+        let mut findings = Vec::new();
+
+        for (name, value) in self.settings() {
+            let Some(info) = SettingInfo::lookup(name) else {
+                continue;
+            };
+            if SANDBOX_PATHS.contains(&info.name()) {
+                continue;
+            }
+
+            let paths = match info.setting_type() {
+                SettingType::Path => vec![value.as_str()],
+                SettingType::PathList => value.as_list(),
+                _ => continue,
+            };
+
+            for path in paths.into_iter().filter(|path| path.starts_with('/')) {
+                let directory_allowed = DIRECTORIES_ALLOWED.contains(&info.name());
+                if let Some(issue) = check_path(Path::new(path), directory_allowed) {
+                    findings.push(PathFinding {
+                        name: name.clone(),
+                        path: PathBuf::from(path),
+                        issue,
+                        origin: self.origin(name).cloned(),
+                    });
+                }
+            }
+        }
+
+        findings
+    }
+}
+
+fn check_path(path: &Path, directory_allowed: bool) -> Option<PathIssue> {
+    let unreadable = |e: std::io::Error| match e.kind() {
+        ErrorKind::NotFound => PathIssue::Missing,
+        _ => PathIssue::Unreadable {
+            reason: e.to_string(),
+        },
+    };
+
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => return Some(unreadable(e)),
+    };
+
+    let readable = if metadata.is_dir() {
+        if !directory_allowed {
+            return Some(PathIssue::IsADirectory);
+        }
+        std::fs::read_dir(path).map(drop)
+    } else {
+        std::fs::File::open(path).map(drop)
+    };
+
+    readable.err().map(unreadable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_sandbox_and_relative_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let nix_conf = NixConfig::parse_string(
+            format!(
+                "sandbox-paths = /does/not/exist\nsandbox-build-dir = /build\nplugin-files = {}\nnetrc-file = .netrc\nssl-cert-file = /does/not/exist.pem\n",
+                temp_dir.path().display()
+            ),
+            None,
+        )
+        .unwrap();
+
+        let findings = nix_conf.check_paths_exist();
+        assert_eq!(
+            findings
+                .iter()
+                .map(PathFinding::to_string)
+                .collect::<Vec<_>>(),
+            ["<unknown>:5: '/does/not/exist.pem' of 'ssl-cert-file' does not exist"]
+        );
+    }
+}
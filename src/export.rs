@@ -0,0 +1,85 @@
+use crate::{NixConfig, SerializeError, SerializeOptions};
+
+impl NixConfig {
+    /// Render the settings as a `NIX_CONFIG` assignment for the file at
+    /// `$GITHUB_ENV` in GitHub Actions, using its multiline syntax.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let nix_conf = nix_config_parser::NixConfig::parse_string(
+    ///     "experimental-features = nix-command flakes\nmax-jobs = auto".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.to_github_env()?,
+    ///     "NIX_CONFIG<<EOF\nexperimental-features = nix-command flakes\nmax-jobs = auto\nEOF\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_github_env(&self) -> Result<String, SerializeError> {
+        let rendered = self.to_string_with_options(&SerializeOptions::default())?;
+
+        // Every rendered line is a setting, an include or a comment, so it
+        // can't be mistaken for the delimiter
+        Ok(format!("NIX_CONFIG<<EOF\n{rendered}EOF\n"))
+    }
+
+    /// Render the settings as a POSIX shell command exporting `NIX_CONFIG`,
+    /// quoted so that the shell doesn't interpret anything in the config.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let nix_conf = nix_config_parser::NixConfig::parse_string(
+    ///     "cores = 4\nbuild-hook-user = o'brien".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.to_shell_export()?,
+    ///     "export NIX_CONFIG='cores = 4\nbuild-hook-user = o'\\''brien\n'\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_shell_export(&self) -> Result<String, SerializeError> {
+        let rendered = self.to_string_with_options(&SerializeOptions::default())?;
+
+        Ok(format!(
+            "export NIX_CONFIG='{}'\n",
+            rendered.replace('\'', "'\\''")
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_export_round_trips() {
+        let mut nix_conf = NixConfig::new();
+        nix_conf.insert("cores".try_into().unwrap(), "4");
+        nix_conf.insert(
+            "post-build-hook".try_into().unwrap(),
+            "/etc/nix/upload 'to' \"$CACHE\" `now` \\n",
+        );
+        let export = nix_conf.to_shell_export().unwrap();
+
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{export}printf %s \"$NIX_CONFIG\""))
+            .output()
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            nix_conf.to_string_checked().unwrap()
+        );
+    }
+}
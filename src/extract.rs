@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use crate::{parse_contents_for_keys, read_file, NixConfig, ParseError, ParseOptions};
+
+impl NixConfig {
+    /// Parse the `nix.conf` at `path` like [`NixConfig::parse_file`], but only
+    /// keep the settings named in `keys` (and their `extra-` variants, such as
+    /// `extra-experimental-features`). Includes are still followed.
+    ///
+    /// Lines setting anything else are skipped without being parsed, so this is
+    /// cheaper on large configs and isn't affected by malformed lines for
+    /// unrelated settings.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// std::fs::write(
+    ///     "extract.conf",
+    ///     b"experimental-features = flakes nix-command\ncores = 4\nnot a setting\n",
+    /// )?;
+    ///
+    /// let nix_conf = nix_config_parser::NixConfig::extract(
+    ///     std::path::Path::new("extract.conf"),
+    ///     &["experimental-features"],
+    /// )?;
+    ///
+    /// assert_eq!(nix_conf.settings().len(), 1);
+    /// assert_eq!(
+    ///     nix_conf.settings().get("experimental-features").unwrap(),
+    ///     "flakes nix-command"
+    /// );
+    ///
+    /// std::fs::remove_file("extract.conf")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract(path: &Path, keys: &[&str]) -> Result<Self, ParseError> {
+        Self::extract_with_options(path, keys, &ParseOptions::default())
+    }
+
+    /// Like [`NixConfig::extract`], using the provided [`ParseOptions`].
+    pub fn extract_with_options(
+        path: &Path,
+        keys: &[&str],
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let contents = read_file(path)?;
+        let parsed = parse_contents_for_keys(&contents, Some(path), options, Some(keys))?;
+
+        Ok(Self::from_parsed(parsed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_keys_through_includes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let included_conf = temp_dir.path().join("included.conf");
+
+        std::fs::write(
+            &included_conf,
+            "extra-experimental-features = flakes\nsubstituters = https://example.com\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &main_conf,
+            format!(
+                "experimental-features = nix-command # comment\ncores = \"4\n\ninclude {}\n",
+                included_conf.display()
+            ),
+        )
+        .unwrap();
+
+        let nix_conf = NixConfig::extract_with_options(
+            &main_conf,
+            &["experimental-features"],
+            &ParseOptions::new().quoting(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            nix_conf.settings().keys().collect::<Vec<_>>(),
+            ["experimental-features", "extra-experimental-features"]
+        );
+        assert!(
+            NixConfig::parse_file_with_options(&main_conf, &ParseOptions::new().quoting(true))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn plain_contents_are_filtered() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let conf = temp_dir.path().join("nix.conf");
+        std::fs::write(&conf, "cores = 4\nmax-jobs = 2\n").unwrap();
+
+        let nix_conf = NixConfig::extract(&conf, &["max-jobs"]).unwrap();
+
+        assert_eq!(nix_conf.settings().keys().collect::<Vec<_>>(), ["max-jobs"]);
+        assert_eq!(
+            nix_conf.origin("max-jobs"),
+            Some(&crate::Origin::File {
+                path: Some(conf),
+                line: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::Write;
+
+use crate::NixConfig;
+
+// 128-bit FNV-1a, which is simple and stable across platforms and releases,
+// unlike `std::hash`.
+const FNV_OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+struct Fnv1a(u128);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u128::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Write `field` followed by a separator that can't be part of a field,
+    /// so that different splits of the same bytes hash differently.
+    fn field(&mut self, field: &str) {
+        self.write(field.as_bytes());
+        self.write(&[0]);
+    }
+}
+
+impl NixConfig {
+    /// A fingerprint of the configuration Nix would see, as 32 hex digits, for
+    /// use as a cache key: if it didn't change, neither did the config.
+    ///
+    /// The fingerprint covers the settings from every file that contributed
+    /// to the config, including included ones, in canonical form: comments,
+    /// blank lines, whitespace within values, the order of assignments and
+    /// which file a setting came from don't change it. `include` directives
+    /// kept with [`IncludeMode::Keep`](crate::IncludeMode::Keep) and optional
+    /// includes that were skipped do, as the config changes when those files
+    /// appear.
+    ///
+    /// The fingerprint is stable across platforms and releases of this crate.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org\ncores = 4".into(),
+    ///     None,
+    /// )?;
+    /// let reformatted = NixConfig::parse_string(
+    ///     "# Managed by CI\ncores   = 4\nsubstituters =  https://cache.nixos.org  ".into(),
+    ///     None,
+    /// )?;
+    /// let changed = NixConfig::parse_string("cores = 8".into(), None)?;
+    ///
+    /// assert_eq!(nix_conf.hashable_fingerprint().len(), 32);
+    /// assert_eq!(nix_conf.hashable_fingerprint(), reformatted.hashable_fingerprint());
+    /// assert_ne!(nix_conf.hashable_fingerprint(), changed.hashable_fingerprint());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn hashable_fingerprint(&self) -> String {
+        let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+
+        hasher.field("settings");
+        for (name, value) in self.sorted_settings() {
+            hasher.field(name);
+            for item in value.as_list() {
+                hasher.field(item);
+            }
+            hasher.write(b"\n");
+        }
+
+        hasher.field("includes");
+        for include in self.includes() {
+            hasher.field(if include.is_optional() {
+                "!include"
+            } else {
+                "include"
+            });
+            hasher.field(&include.path().to_string_lossy());
+        }
+
+        hasher.field("skipped-includes");
+        for skipped in self.skipped_includes() {
+            hasher.field(&skipped.path().to_string_lossy());
+        }
+
+        let mut fingerprint = String::with_capacity(32);
+        write!(fingerprint, "{:032x}", hasher.0).expect("writing to a String can't fail");
+        fingerprint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_included_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("nix.conf");
+        let extra = temp_dir.path().join("extra.conf");
+        std::fs::write(&path, format!("cores = 4\ninclude {}\n", extra.display())).unwrap();
+        std::fs::write(&extra, "max-jobs = 2\n").unwrap();
+
+        let fingerprint = NixConfig::parse_file(&path).unwrap().hashable_fingerprint();
+        assert_eq!(
+            fingerprint,
+            NixConfig::parse_string("max-jobs = 2\ncores = 4".into(), None)
+                .unwrap()
+                .hashable_fingerprint()
+        );
+
+        std::fs::write(&extra, "max-jobs = 3\n").unwrap();
+        assert_ne!(
+            NixConfig::parse_file(&path).unwrap().hashable_fingerprint(),
+            fingerprint
+        );
+
+        // Changing the fingerprint invalidates every cache key built from it.
+        assert_eq!(fingerprint, "1f7dde9c56a14ca04c058cfe26cd8908");
+
+        // A value of two items is not the same as the two items in one.
+        assert_ne!(
+            NixConfig::parse_string("trusted-users = root alice".into(), None)
+                .unwrap()
+                .hashable_fingerprint(),
+            NixConfig::parse_string("trusted-users = rootalice".into(), None)
+                .unwrap()
+                .hashable_fingerprint()
+        );
+    }
+}
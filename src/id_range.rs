@@ -0,0 +1,163 @@
+use std::fmt;
+
+use crate::{NixConfig, SettingInfo};
+
+/// The largest valid user or group ID; `(uid_t) -1` means "no ID".
+const MAX_ID: u64 = u32::MAX as u64 - 1;
+
+/// A range of user and group IDs, `count` IDs starting at `start`, such as
+/// the one Nix allocates build users from with `auto-allocate-uids`, see
+/// [`NixConfig::id_range`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IdRange {
+    start: u32,
+    count: u32,
+}
+
+/// Why `start-id` and `id-count` don't make a usable [`IdRange`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum IdRangeError {
+    #[error("'{0}' must be a non-negative integer, not '{1}'")]
+    InvalidValue(&'static str, String),
+    #[error("'id-count' must not be 0")]
+    Empty,
+    #[error("the {1} IDs starting at {0} extend past the largest ID, {MAX_ID}")]
+    OutOfRange(u64, u64),
+}
+
+impl IdRange {
+    /// The ranges of IDs that are commonly in use on Linux systems, following
+    /// systemd's conventions, by name.
+    pub const SYSTEM: &'static [(&'static str, IdRange)] = &[
+        ("root", IdRange::new(0, 1)),
+        ("system users", IdRange::new(1, 999)),
+        ("regular users", IdRange::new(1000, 59001)),
+        ("systemd-homed users", IdRange::new(60001, 513)),
+        ("systemd dynamic users", IdRange::new(61184, 4336)),
+        ("nobody", IdRange::new(65534, 2)),
+    ];
+
+    /// `count` IDs starting at `start`, which must fit in 32 bits.
+    pub const fn new(start: u32, count: u32) -> Self {
+        Self { start, count }
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// The ID after the last one in the range.
+    pub fn end(&self) -> u64 {
+        u64::from(self.start) + u64::from(self.count)
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        (u64::from(self.start)..self.end()).contains(&u64::from(id))
+    }
+
+    /// Whether the two ranges have any ID in common.
+    pub fn overlaps(&self, other: &IdRange) -> bool {
+        u64::from(self.start) < other.end() && u64::from(other.start) < self.end()
+    }
+
+    /// The [system ranges](IdRange::SYSTEM) this range overlaps with, by name.
+    pub fn system_overlaps(&self) -> Vec<&'static str> {
+        Self::SYSTEM
+            .iter()
+            .filter(|(_, range)| self.overlaps(range))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl fmt::Display for IdRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.count {
+            0 => write!(f, "{}..{} (empty)", self.start, self.start),
+            _ => write!(f, "{}..={}", self.start, self.end() - 1),
+        }
+    }
+}
+
+impl NixConfig {
+    /// The range of IDs Nix allocates build users from when
+    /// `auto-allocate-uids` is enabled: `id-count` IDs starting at
+    /// `start-id`, using Nix's defaults for unset settings.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{IdRange, IdRangeError, NixConfig};
+    ///
+    /// let nix_conf = NixConfig::parse_string("start-id = 30000\nid-count = 65536".into(), None)?;
+    /// let range = nix_conf.id_range()?;
+    /// assert_eq!(range, IdRange::new(30000, 65536));
+    /// assert_eq!(range.system_overlaps(), ["regular users", "systemd-homed users", "systemd dynamic users", "nobody"]);
+    ///
+    /// assert!(NixConfig::new().id_range()?.system_overlaps().is_empty());
+    ///
+    /// let nix_conf = NixConfig::parse_string("start-id = 4294967000".into(), None)?;
+    /// assert_eq!(nix_conf.id_range(), Err(IdRangeError::OutOfRange(4294967000, 8388608)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn id_range(&self) -> Result<IdRange, IdRangeError> {
+        let id = |name: &'static str| {
+            let value = match self.settings().get(name) {
+                Some(value) => value.as_str(),
+                None => SettingInfo::lookup(name)
+                    .and_then(SettingInfo::default_value)
+                    .expect("the ID range settings have defaults"),
+            };
+            value
+                .parse::<u64>()
+                .map_err(|_| IdRangeError::InvalidValue(name, value.to_owned()))
+        };
+
+        let (start, count) = (id("start-id")?, id("id-count")?);
+        if count == 0 {
+            return Err(IdRangeError::Empty);
+        }
+        if start + count - 1 > MAX_ID {
+            return Err(IdRangeError::OutOfRange(start, count));
+        }
+
+        Ok(IdRange::new(start as u32, count as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_ranges() {
+        let range = |contents: &str| {
+            NixConfig::parse_string(contents.into(), None)
+                .unwrap()
+                .id_range()
+        };
+
+        assert_eq!(
+            range("start-id = -1"),
+            Err(IdRangeError::InvalidValue("start-id", "-1".into()))
+        );
+        assert_eq!(range("id-count = 0"), Err(IdRangeError::Empty));
+        assert_eq!(
+            range("start-id = 4294967294\nid-count = 1"),
+            Ok(IdRange::new(4294967294, 1))
+        );
+        assert!(range("start-id = 4294967294\nid-count = 2").is_err());
+
+        let low = range("start-id = 0\nid-count = 1000").unwrap();
+        assert_eq!(low.to_string(), "0..=999");
+        assert_eq!(low.system_overlaps(), ["root", "system users"]);
+        assert!(low.contains(999) && !low.contains(1000));
+    }
+}
@@ -0,0 +1,508 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::{NixConfig, Origin, ParseError, ParseOptions};
+
+/// An `!include` directive whose file could not be read, and was skipped, or
+/// an `include` skipped because of [`ParseOptions::on_include_error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SkippedInclude {
+    pub(crate) path: PathBuf,
+    pub(crate) origin: Origin,
+}
+
+impl SkippedInclude {
+    /// The file that was to be included.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where the `!include` directive is.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+}
+
+/// An `include` or `!include` directive followed by more than a path, whose
+/// extra tokens were ignored, see [`ParseOptions::lenient_includes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TruncatedInclude {
+    pub(crate) path: PathBuf,
+    pub(crate) ignored: Vec<String>,
+    pub(crate) origin: Origin,
+}
+
+impl TruncatedInclude {
+    /// The file that was included.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The tokens after the path, which were ignored.
+    pub fn ignored(&self) -> &[String] {
+        &self.ignored
+    }
+
+    /// Where the directive is.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+}
+
+impl fmt::Display for TruncatedInclude {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: ignored '{}' after the included path '{}'",
+            self.origin,
+            self.ignored.join(" "),
+            self.path.display()
+        )
+    }
+}
+
+/// An `include` or `!include` directive that was kept instead of being
+/// followed, see [`IncludeMode::Keep`](crate::IncludeMode::Keep).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IncludeDirective {
+    pub(crate) path: PathBuf,
+    pub(crate) optional: bool,
+    pub(crate) origin: Origin,
+}
+
+impl IncludeDirective {
+    /// The file to include.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether this is an `!include`, which is skipped if the file can't be
+    /// read.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Where the directive is.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+}
+
+/// The file an `include` of `path` in the file at `origin` refers to: like in
+/// Nix, a relative path is relative to the directory of the including file.
+/// Whether `path` is absolute, and how it is joined, follows the host's rules
+/// (such as drive letters and `\` on Windows), unless
+/// [`ParseOptions::unix_paths`] is enabled.
+pub(crate) fn resolve_include(
+    path: &str,
+    origin: Option<&Path>,
+    options: &ParseOptions,
+) -> PathBuf {
+    let Some(origin) = origin else {
+        return PathBuf::from(path);
+    };
+
+    if options.unix_paths {
+        if path.starts_with('/') {
+            return PathBuf::from(path);
+        }
+        return match origin.to_string_lossy().rsplit_once('/') {
+            Some((dir, _)) => PathBuf::from(format!("{dir}/{path}")),
+            None => PathBuf::from(path),
+        };
+    }
+
+    match origin.parent() {
+        Some(dir) if Path::new(path).is_relative() => dir.join(path),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The error for the `include` of `path` in the file at `origin` that failed
+/// with `error`: a missing file is told apart from one that can't be read,
+/// and from errors inside the file.
+pub(crate) fn include_failed(
+    path: PathBuf,
+    origin: Option<&Path>,
+    error: ParseError,
+) -> ParseError {
+    let origin = origin.map(ToOwned::to_owned);
+    match error {
+        ParseError::FileNotFound(_) => ParseError::IncludedFileNotFound(path, origin),
+        error @ (ParseError::FailedToReadFile(..) | ParseError::IsADirectory(_)) => {
+            ParseError::IncludedFileUnreadable(path, origin, Box::new(error))
+        }
+        error => ParseError::InIncludedFile(path, origin, Box::new(error)),
+    }
+}
+
+/// Whether `error` is about reading a file rather than its contents, which
+/// is all an `!include` ignores.
+pub(crate) fn is_unreadable(error: &ParseError) -> bool {
+    matches!(
+        error,
+        ParseError::FileNotFound(_)
+            | ParseError::FailedToReadFile(..)
+            | ParseError::IsADirectory(_)
+    )
+}
+
+impl ParseError {
+    /// The files the error is inside of, from the outermost include to the
+    /// innermost, for errors inside included files.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, ParseError};
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("nix.conf"), "include a.conf")?;
+    /// std::fs::write(dir.path().join("a.conf"), "include b.conf")?;
+    /// std::fs::write(dir.path().join("b.conf"), "bad line")?;
+    ///
+    /// let error = NixConfig::parse_file(&dir.path().join("nix.conf")).unwrap_err();
+    /// assert_eq!(
+    ///     error.include_chain(),
+    ///     [dir.path().join("a.conf"), dir.path().join("b.conf")]
+    /// );
+    /// assert!(matches!(error.innermost(), ParseError::MissingEquals(..)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn include_chain(&self) -> Vec<&Path> {
+        let mut chain = Vec::new();
+        let mut error = self;
+        while let ParseError::InIncludedFile(path, _, inner) = error {
+            chain.push(path.as_path());
+            error = inner;
+        }
+        chain
+    }
+
+    /// The error inside the innermost included file, see
+    /// [`ParseError::include_chain`], or else the error itself.
+    pub fn innermost(&self) -> &ParseError {
+        match self {
+            ParseError::InIncludedFile(_, _, inner) => inner.innermost(),
+            error => error,
+        }
+    }
+}
+
+impl NixConfig {
+    /// The include directives kept while parsing with
+    /// [`IncludeMode::Keep`](crate::IncludeMode::Keep), in order. These are
+    /// written back out by [`NixConfig::to_string_with_options`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{IncludeMode, NixConfig, ParseOptions};
+    ///
+    /// let nix_conf = NixConfig::parse_string_with_options(
+    ///     "cores = 4\n!include /etc/nix/extra.conf".into(),
+    ///     None,
+    ///     &ParseOptions::new().includes(IncludeMode::Keep),
+    /// )?;
+    ///
+    /// assert!(nix_conf.includes()[0].is_optional());
+    /// assert_eq!(
+    ///     nix_conf.to_string_checked()?,
+    ///     "cores = 4\n!include /etc/nix/extra.conf\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn includes(&self) -> &[IncludeDirective] {
+        &self.includes
+    }
+
+    /// The optional includes (`!include`) that were skipped while parsing,
+    /// because their file could not be read. Like Nix, the parser ignores
+    /// these, but tools may want to point them out. Includes skipped because
+    /// of [`ParseOptions::on_include_error`] are here too.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, Origin};
+    ///
+    /// let nix_conf = NixConfig::parse_string("!include /does/not/exist.conf".into(), None)?;
+    ///
+    /// let skipped = &nix_conf.skipped_includes()[0];
+    /// assert_eq!(skipped.path(), std::path::Path::new("/does/not/exist.conf"));
+    /// assert_eq!(skipped.origin(), &Origin::File { path: None, line: 1 });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn skipped_includes(&self) -> &[SkippedInclude] {
+        &self.skipped_includes
+    }
+
+    /// The includes whose extra tokens were ignored while parsing with
+    /// [`ParseOptions::lenient_includes`], in order.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, ParseOptions};
+    ///
+    /// let contents = "!include /etc/nix/a.conf /etc/nix/b.conf";
+    /// assert!(NixConfig::parse_string(contents.into(), None).is_err());
+    ///
+    /// let nix_conf = NixConfig::parse_string_with_options(
+    ///     contents.into(),
+    ///     None,
+    ///     &ParseOptions::new().lenient_includes(true),
+    /// )?;
+    /// assert_eq!(
+    ///     nix_conf.truncated_includes()[0].to_string(),
+    ///     "<unknown>:1: ignored '/etc/nix/b.conf' after the included path '/etc/nix/a.conf'"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn truncated_includes(&self) -> &[TruncatedInclude] {
+        &self.truncated_includes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IncludeMode;
+
+    #[test]
+    fn reports_skipped_includes_from_nested_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let included_conf = temp_dir.path().join("included.conf");
+        let missing_conf = temp_dir.path().join("missing.conf");
+
+        std::fs::write(
+            &included_conf,
+            format!("cores = 4\n!include {}\n", missing_conf.display()),
+        )
+        .unwrap();
+        std::fs::write(&main_conf, format!("include {}\n", included_conf.display())).unwrap();
+
+        let nix_conf = NixConfig::parse_file(&main_conf).unwrap();
+
+        assert_eq!(
+            nix_conf.skipped_includes(),
+            &[SkippedInclude {
+                path: missing_conf,
+                origin: Origin::File {
+                    path: Some(included_conf),
+                    line: 2
+                },
+            }]
+        );
+        assert_eq!(nix_conf.settings().get("cores"), Some(&"4".into()));
+    }
+
+    #[test]
+    fn includes_first_path_of_truncated_includes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let included_conf = temp_dir.path().join("included.conf");
+
+        std::fs::write(&included_conf, "cores = 4\n").unwrap();
+        std::fs::write(&main_conf, "include included.conf with trailing junk\n").unwrap();
+
+        assert!(matches!(
+            NixConfig::parse_file(&main_conf),
+            Err(ParseError::MalformedInclude(..))
+        ));
+
+        let nix_conf = NixConfig::parse_file_with_options(
+            &main_conf,
+            &ParseOptions::new().lenient_includes(true),
+        )
+        .unwrap();
+        assert_eq!(nix_conf.settings().get("cores"), Some(&"4".into()));
+        assert_eq!(
+            nix_conf.truncated_includes(),
+            &[TruncatedInclude {
+                path: "included.conf".into(),
+                ignored: vec!["with".into(), "trailing".into(), "junk".into()],
+                origin: Origin::File {
+                    path: Some(main_conf),
+                    line: 1
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_unreadable_includes_on_request() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::OnIncludeError;
+
+        // A directory exists but can't be read as a file, even by root.
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let unreadable = temp_dir.path().join("unreadable.conf");
+        std::fs::create_dir(&unreadable).unwrap();
+        std::fs::write(&main_conf, "include unreadable.conf\ncores = 4\n").unwrap();
+
+        match NixConfig::parse_file(&main_conf) {
+            Err(ParseError::IncludedFileUnreadable(path, Some(origin), source)) => {
+                assert_eq!(path, unreadable);
+                assert_eq!(origin, main_conf);
+                assert!(matches!(*source, ParseError::IsADirectory(_)));
+            }
+            _ => panic!("should have returned ParseError::IncludedFileUnreadable"),
+        }
+
+        let nix_conf = NixConfig::parse_file_with_options(
+            &main_conf,
+            &ParseOptions::new().on_include_error(OnIncludeError::Skip),
+        )
+        .unwrap();
+        assert_eq!(nix_conf.settings().get("cores"), Some(&"4".into()));
+        assert_eq!(nix_conf.skipped_includes()[0].path(), unreadable);
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let options = ParseOptions::new().on_include_error(OnIncludeError::callback({
+            let seen = Arc::clone(&seen);
+            move |path, error| {
+                seen.lock().unwrap().push(path.to_owned());
+                !matches!(error, ParseError::IsADirectory(..))
+            }
+        }));
+        assert!(NixConfig::parse_file_with_options(&main_conf, &options).is_err());
+        assert_eq!(*seen.lock().unwrap(), [unreadable]);
+    }
+
+    #[test]
+    fn reports_errors_inside_included_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let broken_conf = temp_dir.path().join("broken.conf");
+        std::fs::write(&broken_conf, "cores = 4\nbad line\n").unwrap();
+
+        // Even an `!include` only ignores files that can't be read.
+        for directive in ["include", "!include"] {
+            std::fs::write(&main_conf, format!("{directive} broken.conf\n")).unwrap();
+            let error = NixConfig::parse_file(&main_conf).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "in file '{}' included from '{}': missing ' = ' between the name and the value in line 'bad line' in '{}'",
+                    broken_conf.display(),
+                    main_conf.display(),
+                    broken_conf.display()
+                )
+            );
+            assert_eq!(error.include_chain(), [broken_conf.as_path()]);
+        }
+    }
+
+    #[test]
+    fn resolves_relative_includes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("nix.conf.d")).unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        std::fs::write(&main_conf, "include nix.conf.d/extra.conf\n").unwrap();
+        std::fs::write(
+            temp_dir.path().join("nix.conf.d/extra.conf"),
+            "cores = 4\n!include ../missing.conf\n",
+        )
+        .unwrap();
+
+        let nix_conf = NixConfig::parse_file(&main_conf).unwrap();
+        assert_eq!(nix_conf.settings().get("cores"), Some(&"4".into()));
+        assert_eq!(
+            nix_conf.skipped_includes()[0].path(),
+            temp_dir.path().join("nix.conf.d/../missing.conf")
+        );
+
+        let unix = ParseOptions::new().unix_paths(true);
+        let resolve = |path, origin: &str| resolve_include(path, Some(Path::new(origin)), &unix);
+        assert_eq!(
+            resolve("extra.conf", "/etc/nix/nix.conf"),
+            Path::new("/etc/nix/extra.conf")
+        );
+        assert_eq!(
+            resolve("/etc/nix/extra.conf", "/home/alice/nix.conf"),
+            Path::new("/etc/nix/extra.conf")
+        );
+        // Drive letters and backslashes have no special meaning
+        assert_eq!(
+            resolve("C:\\extra.conf", "/etc/nix/nix.conf"),
+            Path::new("/etc/nix/C:\\extra.conf")
+        );
+        assert_eq!(
+            resolve_include("extra.conf", None, &unix),
+            Path::new("extra.conf")
+        );
+    }
+
+    #[test]
+    fn makes_origins_absolute_on_request() {
+        let cwd = std::env::current_dir().unwrap();
+        let nix_conf = NixConfig::parse_string_with_options(
+            "cores = 4\n!include extra.conf\n".into(),
+            Some(Path::new("does-not-exist/nix.conf")),
+            &ParseOptions::new().absolute_origins(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            nix_conf.origin("cores"),
+            Some(&Origin::File {
+                path: Some(cwd.join("does-not-exist/nix.conf")),
+                line: 1
+            })
+        );
+        assert_eq!(
+            nix_conf.skipped_includes()[0].path(),
+            cwd.join("does-not-exist/extra.conf")
+        );
+    }
+
+    #[test]
+    fn include_modes() {
+        let contents = "cores = 4\ninclude /does/not/exist.conf\n";
+
+        let kept = NixConfig::parse_string_with_options(
+            contents.into(),
+            None,
+            &ParseOptions::new().includes(IncludeMode::Keep),
+        )
+        .unwrap();
+        assert_eq!(
+            kept.includes(),
+            &[IncludeDirective {
+                path: "/does/not/exist.conf".into(),
+                optional: false,
+                origin: Origin::File {
+                    path: None,
+                    line: 2
+                },
+            }]
+        );
+        assert_eq!(kept.to_string_checked().unwrap(), contents);
+
+        match NixConfig::parse_string_with_options(
+            contents.into(),
+            None,
+            &ParseOptions::new().includes(IncludeMode::Error),
+        ) {
+            Err(ParseError::IncludeNotAllowed(path, None)) => {
+                assert_eq!(path, Path::new("/does/not/exist.conf"))
+            }
+            _ => panic!("includes should have returned ParseError::IncludeNotAllowed"),
+        }
+    }
+}
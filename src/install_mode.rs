@@ -0,0 +1,249 @@
+use std::fmt;
+
+use crate::{NixConfig, Origin, SettingInfo, SettingValue, Severity};
+
+/// How Nix is installed, which decides which settings take effect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum InstallMode {
+    /// Builds are performed by the Nix daemon, running as root, on behalf of
+    /// the users, as unprivileged build users.
+    MultiUser,
+    /// Nix runs as the user that invokes it and builds as that user, without
+    /// a daemon.
+    SingleUser,
+}
+
+impl fmt::Display for InstallMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InstallMode::MultiUser => "multi-user",
+            InstallMode::SingleUser => "single-user",
+        })
+    }
+}
+
+/// A setting that is meaningless or harmful in an [`InstallMode`], see
+/// [`NixConfig::check_install_mode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InstallModeFinding {
+    name: String,
+    mode: InstallMode,
+    severity: Severity,
+    message: &'static str,
+    origin: Option<Origin>,
+}
+
+impl InstallModeFinding {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn mode(&self) -> InstallMode {
+        self.mode
+    }
+
+    /// [`Severity::Warning`] if the setting breaks builds or weakens
+    /// security, [`Severity::Info`] if it is merely ignored.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Why the setting doesn't fit the install mode.
+    pub fn message(&self) -> &str {
+        self.message
+    }
+
+    /// Where the setting was set, if known.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+}
+
+impl fmt::Display for InstallModeFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{origin}: ")?;
+        }
+        write!(
+            f,
+            "'{}' in a {} install: {}",
+            self.name, self.mode, self.message
+        )
+    }
+}
+
+impl NixConfig {
+    /// Guess the install mode from the settings, or [`None`] if they don't
+    /// tell. In order, the signs are:
+    ///
+    /// - `store = daemon` (or a `unix://` store) for a multi-user install, or
+    ///   `store = local` for a single-user install
+    /// - `build-users-group`, which is empty in single-user installs
+    /// - `auto-allocate-uids = true`, which needs the daemon to run as root
+    /// - `trusted-users` or `allowed-users`, which only the daemon reads
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{InstallMode, NixConfig};
+    ///
+    /// let nix_conf = NixConfig::parse_string("build-users-group = nixbld".into(), None)?;
+    /// assert_eq!(nix_conf.install_mode(), Some(InstallMode::MultiUser));
+    ///
+    /// let nix_conf = NixConfig::parse_string("build-users-group =".into(), None)?;
+    /// assert_eq!(nix_conf.install_mode(), Some(InstallMode::SingleUser));
+    ///
+    /// let nix_conf = NixConfig::parse_string("cores = 4".into(), None)?;
+    /// assert_eq!(nix_conf.install_mode(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn install_mode(&self) -> Option<InstallMode> {
+        let setting = |name: &str| self.settings().get(name);
+
+        match setting("store").map(SettingValue::as_str) {
+            Some(store) if is_daemon_store(store) => return Some(InstallMode::MultiUser),
+            Some("local") => return Some(InstallMode::SingleUser),
+            _ => {}
+        }
+
+        if let Some(group) = setting("build-users-group") {
+            return Some(if group.is_empty() {
+                InstallMode::SingleUser
+            } else {
+                InstallMode::MultiUser
+            });
+        }
+
+        if setting("auto-allocate-uids").and_then(SettingValue::as_bool) == Some(true)
+            || setting("trusted-users").is_some()
+            || setting("allowed-users").is_some()
+        {
+            return Some(InstallMode::MultiUser);
+        }
+
+        None
+    }
+
+    /// Find settings that are meaningless or harmful in an install of
+    /// `mode`, such as `trusted-users` without a daemon to read it, or an
+    /// empty `build-users-group` that makes the daemon build as root.
+    /// Settings that have their built-in default value are not reported.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{InstallMode, NixConfig, Severity};
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "store = daemon\ntrusted-users = root alice".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let findings = nix_conf.check_install_mode(InstallMode::SingleUser);
+    /// assert_eq!(
+    ///     findings.iter().map(|finding| (finding.name(), finding.severity())).collect::<Vec<_>>(),
+    ///     [("store", Severity::Warning), ("trusted-users", Severity::Info)]
+    /// );
+    /// assert!(nix_conf.check_install_mode(InstallMode::MultiUser).is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_install_mode(&self, mode: InstallMode) -> Vec<InstallModeFinding> {
+        let mut findings = Vec::new();
+
+        for (name, value) in self.settings() {
+            let is_default = SettingInfo::lookup(name)
+                .and_then(SettingInfo::default_value)
+                .is_some_and(|default| default == value.as_str());
+            if is_default {
+                continue;
+            }
+
+            let base = name.strip_prefix("extra-").unwrap_or(name);
+            let problem = match (mode, base) {
+                (InstallMode::SingleUser, "store") if is_daemon_store(value) => Some((
+                    Severity::Warning,
+                    "there is no daemon to connect to, so Nix commands will fail",
+                )),
+                (InstallMode::SingleUser, "build-users-group") if !value.is_empty() => Some((
+                    Severity::Info,
+                    "build users are only used when Nix runs as root",
+                )),
+                (InstallMode::SingleUser, "auto-allocate-uids")
+                    if value.as_bool() == Some(true) =>
+                {
+                    Some((
+                        Severity::Info,
+                        "user IDs are only allocated when Nix runs as root",
+                    ))
+                }
+                (InstallMode::SingleUser, "trusted-users" | "allowed-users") => {
+                    Some((Severity::Info, "it is only read by the Nix daemon"))
+                }
+                (InstallMode::MultiUser, "build-users-group") if value.is_empty() => Some((
+                    Severity::Warning,
+                    "without build users, the daemon runs builds as root",
+                )),
+                _ => None,
+            };
+
+            if let Some((severity, message)) = problem {
+                findings.push(InstallModeFinding {
+                    name: name.clone(),
+                    mode,
+                    severity,
+                    message,
+                    origin: self.origin(name).cloned(),
+                });
+            }
+        }
+
+        findings
+    }
+}
+
+fn is_daemon_store(store: &str) -> bool {
+    store == "daemon" || store.starts_with("unix://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_build_users_group_is_harmful_with_a_daemon() {
+        let nix_conf = NixConfig::parse_string(
+            "auto-allocate-uids = true\nbuild-users-group =".into(),
+            None,
+        )
+        .unwrap();
+
+        // `build-users-group` decides over `auto-allocate-uids`
+        assert_eq!(nix_conf.install_mode(), Some(InstallMode::SingleUser));
+        assert_eq!(
+            nix_conf
+                .check_install_mode(InstallMode::SingleUser)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["<unknown>:1: 'auto-allocate-uids' in a single-user install: user IDs are only allocated when Nix runs as root"]
+        );
+        assert_eq!(
+            nix_conf
+                .check_install_mode(InstallMode::MultiUser)
+                .iter()
+                .map(InstallModeFinding::name)
+                .collect::<Vec<_>>(),
+            ["build-users-group"]
+        );
+    }
+}
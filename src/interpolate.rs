@@ -0,0 +1,203 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::{NixConfig, SettingValue};
+
+/// A placeholder in a value, to be filled in by
+/// [`NixConfig::interpolate`]: a token `@kind:argument`, or the part after the
+/// `=` of a token such as `github.com=@env:GITHUB_TOKEN`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placeholder<'a> {
+    setting: &'a str,
+    text: &'a str,
+    kind: &'a str,
+    argument: &'a str,
+}
+
+impl<'a> Placeholder<'a> {
+    /// The name of the setting whose value the placeholder is in.
+    pub fn setting(&self) -> &'a str {
+        self.setting
+    }
+
+    /// The placeholder as written, such as `@env:GITHUB_TOKEN`.
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// What kind of placeholder this is, such as `env`.
+    pub fn kind(&self) -> &'a str {
+        self.kind
+    }
+
+    /// What the placeholder refers to, such as `GITHUB_TOKEN`.
+    pub fn argument(&self) -> &'a str {
+        self.argument
+    }
+
+    fn parse(setting: &'a str, text: &'a str) -> Option<Self> {
+        let (kind, argument) = text.strip_prefix('@')?.split_once(':')?;
+        let is_kind = !kind.is_empty()
+            && kind
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        is_kind.then_some(Self {
+            setting,
+            text,
+            kind,
+            argument,
+        })
+    }
+}
+
+impl fmt::Display for Placeholder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+/// A placeholder the resolver passed to [`NixConfig::interpolate`] failed to
+/// resolve, with the resolver's error.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("failed to resolve '{placeholder}' in setting '{setting}': {reason}")]
+pub struct InterpolationError<E: fmt::Display> {
+    setting: String,
+    placeholder: String,
+    reason: E,
+}
+
+impl<E: fmt::Display> InterpolationError<E> {
+    /// The name of the setting whose value the placeholder is in.
+    pub fn setting(&self) -> &str {
+        &self.setting
+    }
+
+    /// The placeholder as written.
+    pub fn placeholder(&self) -> &str {
+        &self.placeholder
+    }
+
+    /// The error the resolver returned.
+    pub fn reason(&self) -> &E {
+        &self.reason
+    }
+
+    pub fn into_reason(self) -> E {
+        self.reason
+    }
+}
+
+impl NixConfig {
+    /// A copy of the config with the [placeholders](Placeholder) in its values
+    /// replaced by what `resolver` returns for them. The resolver can leave a
+    /// placeholder as it is by returning `Ok(None)`, for example if it doesn't
+    /// know its kind. A resolved value of several words becomes several
+    /// items of a list, and an empty one is dropped unless it is the part
+    /// after a `=`.
+    ///
+    /// This is the mechanism behind [`NixConfig::resolve_env`], and can be
+    /// used for templating as well:
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let template = NixConfig::parse_string(
+    ///     "cores = @var:cores\ntrusted-users = root @var:admins\nsubstituters = @env:CACHE".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let resolved = template.interpolate(|placeholder| match placeholder.kind() {
+    ///     "var" => match placeholder.argument() {
+    ///         "cores" => Ok(Some("4".to_owned())),
+    ///         "admins" => Ok(Some("alice bob".to_owned())),
+    ///         _ => Err("unknown variable"),
+    ///     },
+    ///     _ => Ok(None),
+    /// })?;
+    ///
+    /// assert_eq!(
+    ///     resolved.to_string_checked()?,
+    ///     "cores = 4\ntrusted-users = root alice bob\nsubstituters = @env:CACHE\n"
+    /// );
+    ///
+    /// let error = template
+    ///     .interpolate(|_| Err::<Option<String>, _>("unknown variable"))
+    ///     .unwrap_err();
+    /// assert_eq!(error.setting(), "cores");
+    /// assert_eq!(error.placeholder(), "@var:cores");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn interpolate<F, E>(&self, mut resolver: F) -> Result<NixConfig, InterpolationError<E>>
+    where
+        F: FnMut(&Placeholder<'_>) -> Result<Option<String>, E>,
+        E: fmt::Display,
+    {
+        let mut config = self.clone();
+
+        for (name, value) in config.settings_mut() {
+            if !value.contains('@') {
+                continue;
+            }
+
+            let mut resolved = SettingValue::default();
+            for token in value.as_list() {
+                let (key, text) = match token.split_once('=') {
+                    Some((key, text)) => (Some(key), text),
+                    None => (None, token),
+                };
+                let Some(placeholder) = Placeholder::parse(name, text) else {
+                    resolved.push_item(token);
+                    continue;
+                };
+
+                let replacement = match resolver(&placeholder) {
+                    Ok(Some(replacement)) => replacement,
+                    Ok(None) => {
+                        resolved.push_item(token);
+                        continue;
+                    }
+                    Err(reason) => {
+                        return Err(InterpolationError {
+                            setting: name.clone(),
+                            placeholder: text.to_owned(),
+                            reason,
+                        })
+                    }
+                };
+                match key {
+                    Some(key) => resolved.push_item(&format!("{key}={replacement}")),
+                    None if replacement.trim().is_empty() => {}
+                    None => resolved.push_item(&replacement),
+                }
+            }
+            *value = resolved;
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_placeholders() {
+        let parse = |text| Placeholder::parse("cores", text).map(|p| (p.kind(), p.argument()));
+
+        assert_eq!(parse("@env:CORES"), Some(("env", "CORES")));
+        assert_eq!(parse("@my-vault:a/b:c"), Some(("my-vault", "a/b:c")));
+        assert_eq!(parse("@env:"), Some(("env", "")));
+        // Groups in `trusted-users`, for example, aren't placeholders.
+        assert_eq!(parse("@wheel"), None);
+        assert_eq!(parse("@:CORES"), None);
+        assert_eq!(parse("me@env:HOME"), None);
+        assert_eq!(parse("@a b:c"), None);
+    }
+}
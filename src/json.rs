@@ -0,0 +1,64 @@
+//! A minimal JSON writer for the reports this crate renders, so they don't
+//! depend on `serde`.
+
+use std::fmt::Write;
+
+use crate::Origin;
+
+/// Append `value` to `out` as a JSON string.
+pub(crate) fn string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Append `value` to `out` as a JSON string, or `null`.
+pub(crate) fn optional_string(out: &mut String, value: Option<&str>) {
+    match value {
+        Some(value) => string(out, value),
+        None => out.push_str("null"),
+    }
+}
+
+/// Append `origin` to `out` as a JSON object with a `kind` field.
+pub(crate) fn origin(out: &mut String, origin: &Origin) {
+    match origin {
+        Origin::Default => out.push_str(r#"{"kind":"default"}"#),
+        Origin::File { path, line } => {
+            out.push_str(r#"{"kind":"file","path":"#);
+            let path = path.as_ref().map(|path| path.display().to_string());
+            optional_string(out, path.as_deref());
+            let _ = write!(out, r#","line":{line}}}"#);
+        }
+        Origin::Environment { variable, line } => {
+            out.push_str(r#"{"kind":"environment","variable":"#);
+            string(out, variable);
+            let _ = write!(out, r#","line":{line}}}"#);
+        }
+        Origin::CommandLine => out.push_str(r#"{"kind":"command-line"}"#),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        let mut out = String::new();
+        string(&mut out, "a \"b\"\\\n\u{1}");
+        assert_eq!(out, r#""a \"b\"\\\n\u0001""#);
+    }
+}
@@ -2,35 +2,454 @@
 //!
 //! A simple parser for the Nix configuration file format.
 use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-/// A newtype wrapper around a [`HashMap`], where the key is the name of the Nix
+// Lets the code generated by `#[derive(NixSettings)]` refer to this crate by
+// name in its own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as nix_config_parser;
+
+mod apply;
+mod baseline;
+#[cfg(feature = "support")]
+pub mod bundle;
+mod cache;
+mod ci;
+#[cfg(feature = "compat")]
+pub mod compat;
+mod conflicts;
+pub mod conformance;
+mod dialect;
+mod diff;
+mod directive;
+mod display;
+mod document;
+mod drift;
+mod dropin;
+mod effective;
+mod encoding;
+mod env;
+mod existence;
+mod export;
+mod extract;
+mod fingerprint;
+mod id_range;
+mod include;
+mod install_mode;
+mod interpolate;
+mod json;
+mod lint;
+mod lint_config;
+mod macros;
+mod managed;
+mod merge;
+mod name;
+pub mod netrc;
+mod options;
+mod origin;
+mod overlay;
+pub mod paths;
+mod pattern;
+mod platforms;
+mod pragma;
+mod presets;
+mod registry;
+mod relocate;
+#[cfg(feature = "http")]
+mod remote;
+mod render;
+mod rule;
+#[cfg(feature = "s3")]
+mod s3;
+mod secret;
+mod serialize;
+mod settings;
+mod shadowed;
+#[cfg(feature = "signatures")]
+mod signature;
+mod substituters;
+mod suggest;
+#[cfg(feature = "support")]
+pub mod support;
+#[cfg(feature = "keyring")]
+pub mod tokens;
+mod tree;
+mod validate;
+mod value;
+mod wipe;
+
+pub use baseline::{BaselineError, LintBaseline, LINT_BASELINE_FILE_NAME};
+pub use cache::NixConfigCache;
+pub use conflicts::Conflict;
+pub use dialect::Dialect;
+pub use diff::SettingDiff;
+pub use directive::CustomDirective;
+pub use document::NixConfigDocument;
+pub use drift::{DriftFinding, DriftReport};
+pub use dropin::{DropIn, DropInLayout};
+pub use effective::{EffectiveConfig, SettingProvenance};
+pub use existence::{PathFinding, PathIssue};
+pub use id_range::{IdRange, IdRangeError};
+pub use include::{IncludeDirective, SkippedInclude, TruncatedInclude};
+pub use install_mode::{InstallMode, InstallModeFinding};
+pub use interpolate::{InterpolationError, Placeholder};
+pub use lint::{Diagnostic, Fix, Severity};
+#[cfg(feature = "toml")]
+pub use lint_config::LintConfigError;
+pub use lint_config::{
+    AllowedSubstituters, LintConfig, RuleConfig, RuleParam, LINT_CONFIG_FILE_NAME,
+};
+#[doc(hidden)]
+pub use macros::__private;
+pub use managed::ConfigManager;
+pub use merge::{MergeStrategies, MergeStrategy};
+pub use name::{InvalidSettingName, SettingName};
+#[cfg(feature = "derive")]
+pub use nix_config_parser_derive::NixSettings;
+pub use options::{IncludeMode, InvalidUtf8, OnIncludeError, ParseOptions};
+pub use origin::Origin;
+pub use overlay::ConfigOverlay;
+pub use pattern::SettingPattern;
+pub use platforms::{is_system_type, KNOWN_SYSTEM_FEATURES};
+pub use pragma::Pragma;
+pub use registry::{SettingInfo, SettingType};
+#[cfg(feature = "http")]
+pub use remote::RemoteIncludes;
+pub use rule::{RuleRegistry, ValidationRule};
+#[cfg(feature = "s3")]
+pub use s3::S3Object;
+pub use serialize::{SerializeError, SerializeOptions};
+pub use settings::{FromConfigError, NixSettings, SettingField};
+pub use shadowed::ShadowedSetting;
+#[cfg(feature = "signatures")]
+pub use signature::{sign_fragment, SignatureError, TrustedKeys};
+pub use tree::{ConfigTree, FilePatch, WriteError};
+pub use validate::ValueProblem;
+pub use value::SettingValue;
+
+use wipe::WipeOnDrop;
+
+/// A newtype wrapper around an [`IndexMap`], where the key is the name of the Nix
 /// setting, and the value is the value of that setting. If the setting accepts
-/// a list of values, the value will be space delimited.
-#[derive(Clone, Eq, PartialEq, Debug, Default)]
+/// a list of values, the value will be space delimited (see
+/// [`SettingValue::as_list`]).
+///
+/// Configs produced by the parser also remember the [`Origin`] of each setting.
+/// Origins are not considered when comparing configs.
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NixConfig {
-    settings: IndexMap<String, String>,
+    settings: IndexMap<String, SettingValue>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    origins: IndexMap<String, Origin>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    groups: IndexMap<String, String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    shadowed: Vec<ShadowedSetting>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    skipped_includes: Vec<SkippedInclude>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    truncated_includes: Vec<TruncatedInclude>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    includes: Vec<IncludeDirective>,
+}
+
+impl PartialEq for NixConfig {
+    fn eq(&self, other: &Self) -> bool {
+        self.settings == other.settings
+    }
+}
+
+impl Eq for NixConfig {}
+
+impl FromIterator<(String, SettingValue)> for NixConfig {
+    fn from_iter<T: IntoIterator<Item = (String, SettingValue)>>(iter: T) -> Self {
+        Self {
+            settings: iter.into_iter().collect(),
+            ..Self::new()
+        }
+    }
 }
 
 impl NixConfig {
     pub fn new() -> Self {
         Self {
             settings: IndexMap::new(),
+            origins: IndexMap::new(),
+            groups: IndexMap::new(),
+            shadowed: Vec::new(),
+            skipped_includes: Vec::new(),
+            truncated_includes: Vec::new(),
+            includes: Vec::new(),
         }
     }
 
-    pub fn settings(&self) -> &IndexMap<String, String> {
+    pub fn settings(&self) -> &IndexMap<String, SettingValue> {
         &self.settings
     }
 
-    pub fn settings_mut(&mut self) -> &mut IndexMap<String, String> {
+    pub fn settings_mut(&mut self) -> &mut IndexMap<String, SettingValue> {
         &mut self.settings
     }
 
-    pub fn into_settings(self) -> IndexMap<String, String> {
+    pub fn into_settings(self) -> IndexMap<String, SettingValue> {
+        self.settings
+    }
+
+    /// The settings sorted by name, for callers that want [`Ord`]-based
+    /// lookups and iteration instead of insertion order.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string("max-jobs = 2\ncores = 4\nsandbox = true".into(), None)?;
+    ///
+    /// let sorted = nix_conf.sorted_settings();
+    /// assert_eq!(sorted.keys().copied().collect::<Vec<_>>(), ["cores", "max-jobs", "sandbox"]);
+    /// assert_eq!(sorted.range("m".."n").count(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sorted_settings(&self) -> BTreeMap<&str, &SettingValue> {
         self.settings
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect()
+    }
+
+    /// Like [`NixConfig::sorted_settings`], taking ownership of the settings.
+    /// A map can be turned back into a config with [`FromIterator`].
+    pub fn into_sorted_settings(self) -> BTreeMap<String, SettingValue> {
+        self.settings.into_iter().collect()
+    }
+
+    /// The value of the setting `name`, as a string.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string("cores = 4".into(), None)?;
+    ///
+    /// let name = String::from("cores");
+    /// assert_eq!(nix_conf.get_str(&name), Some("4"));
+    /// assert_eq!(nix_conf.get_owned("cores"), Some("4".into()));
+    /// assert_eq!(nix_conf.get_or("max-jobs", "1"), "1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_str(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.settings.get(name.as_ref()).map(SettingValue::as_str)
+    }
+
+    /// A copy of the value of the setting `name`.
+    pub fn get_owned(&self, name: impl AsRef<str>) -> Option<SettingValue> {
+        self.settings.get(name.as_ref()).cloned()
+    }
+
+    /// The value of the setting `name`, or `default` if it isn't set.
+    pub fn get_or<'a>(&'a self, name: impl AsRef<str>, default: &'a str) -> &'a str {
+        self.get_str(name).unwrap_or(default)
+    }
+
+    /// Insert a setting, returning its previous value if it was already set.
+    /// Use [`NixConfig::settings_mut`] to insert names that aren't a valid
+    /// [`SettingName`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, SettingName};
+    ///
+    /// let mut nix_conf = NixConfig::new();
+    /// nix_conf.insert(SettingName::try_from("cores")?, "4");
+    ///
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "4");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn insert(
+        &mut self,
+        name: SettingName,
+        value: impl Into<SettingValue>,
+    ) -> Option<SettingValue> {
+        self.settings.insert(name.into_inner(), value.into())
+    }
+
+    /// Where the current value of the setting `name` came from, if known.
+    /// Settings inserted programmatically have no origin.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, Origin};
+    ///
+    /// let nix_conf = NixConfig::parse_string("# comment\ncores = 4".into(), None)?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.origin("cores"),
+    ///     Some(&Origin::File { path: None, line: 2 })
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn origin(&self, name: impl AsRef<str>) -> Option<&Origin> {
+        let name = name.as_ref();
+        if !self.settings.contains_key(name) {
+            return None;
+        }
+
+        self.origins.get(name)
+    }
+
+    /// Set the origin of the setting `name`, for example after inserting it
+    /// programmatically. Does nothing if the setting isn't set.
+    pub fn set_origin(&mut self, name: &str, origin: Origin) {
+        if self.settings.contains_key(name) {
+            self.origins.insert(name.to_owned(), origin);
+        }
+    }
+
+    /// Attach a group label to the setting `name`. When rendered, grouped
+    /// settings are written under a `# --- <group> ---` header after all
+    /// ungrouped settings. Like origins, groups are not considered when
+    /// comparing configs.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, SerializeOptions};
+    ///
+    /// let mut nix_conf = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org\ncores = 4".into(),
+    ///     None,
+    /// )?;
+    /// nix_conf.set_group("substituters", "caching");
+    ///
+    /// assert_eq!(
+    ///     nix_conf.to_string_with_options(&SerializeOptions::new())?,
+    ///     "cores = 4\n\n# --- caching ---\nsubstituters = https://cache.nixos.org\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_group(&mut self, name: &str, group: impl Into<String>) {
+        self.groups.insert(name.to_owned(), group.into());
+    }
+
+    /// The group label attached to the setting `name`, if any.
+    pub fn group(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.groups.get(name.as_ref()).map(String::as_str)
+    }
+
+    /// Detach the group label from the setting `name`, returning it.
+    pub fn remove_group(&mut self, name: &str) -> Option<String> {
+        self.groups.shift_remove(name)
+    }
+
+    /// A new config with only the settings named in `names`, in the order of
+    /// this config. Origins and groups are preserved.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "cores = 4\nsubstituters = https://cache.nixos.org\ntrusted-public-keys = cache.nixos.org-1:6NCHdD59X431o0gWypbMrAURkbJ16ZPMQFGspcDShjY=".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let managed = nix_conf.subset(["substituters", "trusted-public-keys"]);
+    /// assert_eq!(managed.settings().len(), 2);
+    ///
+    /// let unmanaged = nix_conf.without(["substituters", "trusted-public-keys"]);
+    /// assert_eq!(unmanaged.settings().keys().collect::<Vec<_>>(), vec!["cores"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subset<I, S>(&self, names: I) -> NixConfig
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names = names.into_iter().collect::<Vec<_>>();
+        self.filtered(|name| names.iter().any(|n| n.as_ref() == name))
+    }
+
+    /// A new config without the settings named in `names`. Origins and groups
+    /// are preserved.
+    pub fn without<I, S>(&self, names: I) -> NixConfig
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names = names.into_iter().collect::<Vec<_>>();
+        self.filtered(|name| !names.iter().any(|n| n.as_ref() == name))
+    }
+
+    fn filtered(&self, mut keep: impl FnMut(&str) -> bool) -> NixConfig {
+        let mut filtered = NixConfig::new();
+
+        for (name, value) in self.settings() {
+            if !keep(name) {
+                continue;
+            }
+
+            filtered.settings.insert(name.clone(), value.clone());
+            if let Some(origin) = self.origin(name) {
+                filtered.origins.insert(name.clone(), origin.clone());
+            }
+            if let Some(group) = self.group(name) {
+                filtered.groups.insert(name.clone(), group.to_owned());
+            }
+        }
+
+        filtered
+    }
+
+    fn from_assignments(assignments: Vec<Assignment>) -> Self {
+        let mut settings = NixConfig::new();
+
+        for Assignment {
+            name,
+            value,
+            origin,
+        } in assignments
+        {
+            let Some(value) = value else {
+                settings.settings.shift_remove(&name);
+                settings.origins.shift_remove(&name);
+                settings.groups.shift_remove(&name);
+                continue;
+            };
+
+            let previous_origin = settings.origins.insert(name.clone(), origin.clone());
+            let previous_value = settings.settings.insert(name.clone(), value);
+            if let (Some(value), Some(previous_origin)) = (previous_value, previous_origin) {
+                settings.shadowed.push(ShadowedSetting {
+                    name,
+                    value,
+                    origin: previous_origin,
+                    shadowed_by: origin,
+                });
+            }
+        }
+
+        settings
     }
 
     /// Attempt to parse the `nix.conf` at the provided path.
@@ -57,14 +476,123 @@ impl NixConfig {
     /// # }
     /// ```
     pub fn parse_file(path: &Path) -> Result<Self, ParseError> {
-        if !path.exists() {
-            return Err(ParseError::FileNotFound(path.to_owned()));
+        Self::parse_file_with_options(path, &ParseOptions::default())
+    }
+
+    /// Attempt to parse the `nix.conf` at the provided path, using the provided
+    /// [`ParseOptions`]. The options also apply to any included files.
+    ///
+    /// If `path` is a directory, this fails with [`ParseError::IsADirectory`],
+    /// unless [`ParseOptions::directories`] is enabled.
+    pub fn parse_file_with_options(
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let path = &options.origin(path);
+        match read_file(path) {
+            Ok(contents) => Self::parse_string_with_options(contents, Some(path), options),
+            Err(ParseError::IsADirectory(_)) if options.directories => {
+                Ok(Self::from_parsed(parse_directory(path, options)?))
+            }
+            Err(e) => Err(e),
         }
+    }
+
+    /// Attempt to parse a `nix.conf` read from `reader` until its end, such as a
+    /// pipe or an archive entry. The `origin` parameter is [`Option`]al, and
+    /// only influences potential error messages.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let reader = std::io::Cursor::new(b"cores = 4\n");
+    /// let nix_conf = nix_config_parser::NixConfig::from_reader(reader, None)?;
+    ///
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "4");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_reader<R: Read>(reader: R, origin: Option<&Path>) -> Result<Self, ParseError> {
+        Self::from_reader_with_options(reader, origin, &ParseOptions::default())
+    }
+
+    /// Attempt to parse a `nix.conf` read from `reader` until its end, using
+    /// the provided [`ParseOptions`].
+    pub fn from_reader_with_options<R: Read>(
+        mut reader: R,
+        origin: Option<&Path>,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .map_err(ParseError::FailedToRead)?;
 
-        let contents = std::fs::read_to_string(path)
-            .map_err(|e| ParseError::FailedToReadFile(path.to_owned(), e))?;
+        Self::parse_string_with_options(contents, origin, options)
+    }
 
-        Self::parse_string(contents, Some(path))
+    /// Attempt to parse a `nix.conf` from raw bytes, such as an archive entry or
+    /// a network response. The `origin` parameter is [`Option`]al, and only
+    /// influences potential error messages.
+    ///
+    /// The bytes are decoded as UTF-8, unless they start with a UTF-16 byte
+    /// order mark. A UTF-8 byte order mark is skipped. Invalid bytes fail
+    /// the parse with [`ParseError::InvalidEncoding`], see
+    /// [`ParseOptions::invalid_utf8`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, ParseError};
+    ///
+    /// let nix_conf = NixConfig::parse_bytes(b"\xef\xbb\xbfcores = 4\n", None)?;
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "4");
+    ///
+    /// assert!(matches!(
+    ///     NixConfig::parse_bytes(b"cores = 4\nbuild-hook-user = caf\xe9\n", None),
+    ///     Err(ParseError::InvalidEncoding(offset, None)) if offset == 31
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_bytes(bytes: &[u8], origin: Option<&Path>) -> Result<Self, ParseError> {
+        Self::parse_bytes_with_options(bytes, origin, &ParseOptions::default())
+    }
+
+    /// Attempt to parse a `nix.conf` from raw bytes, using the provided
+    /// [`ParseOptions`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{InvalidUtf8, NixConfig, ParseOptions};
+    ///
+    /// let nix_conf = NixConfig::parse_bytes_with_options(
+    ///     b"build-hook-user = caf\xe9\n",
+    ///     None,
+    ///     &ParseOptions::new().invalid_utf8(InvalidUtf8::Replace),
+    /// )?;
+    /// assert_eq!(nix_conf.settings().get("build-hook-user").unwrap(), "caf\u{fffd}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_bytes_with_options(
+        bytes: &[u8],
+        origin: Option<&Path>,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let origin = origin.map(|origin| options.origin(origin));
+        let origin = origin.as_deref();
+        let contents = encoding::decode(bytes, options.invalid_utf8)
+            .map(WipeOnDrop::from)
+            .map_err(|offset| ParseError::InvalidEncoding(offset, origin.map(ToOwned::to_owned)))?;
+
+        let parsed = parse_contents(&contents, origin, options)?;
+
+        Ok(Self::from_parsed(parsed))
     }
 
     /// Attempt to parse the `nix.conf` out of the provided [`String`]. The `origin`
@@ -84,88 +612,848 @@ impl NixConfig {
     /// # Ok(())
     /// # }
     /// ```
-    // Mostly a carbon copy of AbstractConfig::applyConfig from Nix:
-    // https://github.com/NixOS/nix/blob/0079d2943702a7a7fbdd88c0f9a5ad677c334aa8/src/libutil/config.cc#L80
-    // Some things were adjusted to be more idiomatic, as well as to account for the lack of
-    // `try { ... } catch (SpecificErrorType &) { }`
     pub fn parse_string(contents: String, origin: Option<&Path>) -> Result<Self, ParseError> {
-        let mut settings = NixConfig::new();
+        Self::parse_string_with_options(contents, origin, &ParseOptions::default())
+    }
+
+    /// Attempt to parse the `nix.conf` out of the provided [`String`], using the
+    /// provided [`ParseOptions`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, ParseOptions};
+    ///
+    /// let nix_conf = NixConfig::parse_string_with_options(
+    ///     String::from(r#"netrc-file = "/Users/First  Last/.netrc""#),
+    ///     None,
+    ///     &ParseOptions::new().quoting(true),
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.settings().get("netrc-file").unwrap(),
+    ///     "/Users/First  Last/.netrc"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_string_with_options(
+        contents: String,
+        origin: Option<&Path>,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let contents = WipeOnDrop::from(contents);
+        let parsed = parse_contents(&contents, origin, options)?;
 
-        for line in contents.lines() {
-            let mut line = line;
+        Ok(Self::from_parsed(parsed))
+    }
 
-            // skip comments
-            if let Some(pos) = line.find('#') {
-                line = &line[..pos];
-            }
+    /// Parse a set of named fragments, such as the keys of a Kubernetes
+    /// ConfigMap, like [`ParseOptions::directories`] parses a directory but
+    /// without a file system: in lexicographic order of their names, with
+    /// later fragments taking precedence. The origin of each setting is the
+    /// name of its fragment.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::path::PathBuf;
+    ///
+    /// use nix_config_parser::{NixConfig, Origin};
+    ///
+    /// let nix_conf = NixConfig::parse_named_fragments([
+    ///     ("20-builders.conf".to_owned(), "max-jobs = 16".to_owned()),
+    ///     ("10-base.conf".to_owned(), "max-jobs = 4\ncores = 2".to_owned()),
+    /// ])?;
+    ///
+    /// assert_eq!(nix_conf.settings().get("max-jobs").unwrap(), "16");
+    /// assert_eq!(
+    ///     nix_conf.origin("cores"),
+    ///     Some(&Origin::File {
+    ///         path: Some(PathBuf::from("10-base.conf")),
+    ///         line: 2
+    ///     })
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_named_fragments<I>(fragments: I) -> Result<Self, ParseError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        Self::parse_named_fragments_with_options(fragments, &ParseOptions::default())
+    }
 
-            line = line.trim();
+    /// Like [`NixConfig::parse_named_fragments`], using the provided
+    /// [`ParseOptions`].
+    pub fn parse_named_fragments_with_options<I>(
+        fragments: I,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let fragments = fragments.into_iter().collect::<BTreeMap<_, _>>();
 
-            if line.is_empty() {
-                continue;
-            }
+        let mut parsed = Parsed::default();
+        for (name, contents) in &fragments {
+            let fragment_parsed = parse_contents(contents, Some(Path::new(name)), options)?;
+
+            parsed.assignments.extend(fragment_parsed.assignments);
+            parsed
+                .skipped_includes
+                .extend(fragment_parsed.skipped_includes);
+            parsed
+                .truncated_includes
+                .extend(fragment_parsed.truncated_includes);
+            parsed.includes.extend(fragment_parsed.includes);
+            parsed.included_files.extend(fragment_parsed.included_files);
+        }
+
+        Ok(Self::from_parsed(parsed))
+    }
+
+    pub(crate) fn from_parsed(parsed: Parsed) -> Self {
+        let mut nix_conf = Self::from_assignments(parsed.assignments);
+        nix_conf.skipped_includes = parsed.skipped_includes;
+        nix_conf.truncated_includes = parsed.truncated_includes;
+        nix_conf.includes = parsed.includes;
+        nix_conf
+    }
+}
+
+/// A single `name = value` line, as returned by [`parse_assignments`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assignment {
+    pub(crate) name: String,
+    /// [`None`] for an `!unset name` line (see [`ParseOptions::unset_directives`]).
+    pub(crate) value: Option<SettingValue>,
+    pub(crate) origin: Origin,
+}
+
+impl Assignment {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The assigned value, or [`None`] for an `!unset <name>` line (see
+    /// [`ParseOptions::unset_directives`]).
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// The file and line of the assignment.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// The line (1-based) of the assignment in its file.
+    pub fn line(&self) -> usize {
+        match self.origin {
+            Origin::File { line, .. } | Origin::Environment { line, .. } => line,
+            Origin::Default | Origin::CommandLine => 0,
+        }
+    }
+}
+
+/// Parse `contents` into the assignments it makes, in order, including ones
+/// that are overridden later on and ones from included files. This is the
+/// low-level building block beneath [`NixConfig`], for consumers that don't
+/// want map semantics.
+///
+/// The `origin` parameter is [`Option`]al, and only influences potential error
+/// messages and the recorded [`Origin`]s.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let assignments = nix_config_parser::parse_assignments("cores = 4\n\ncores = 8", None)?;
+///
+/// let triples = assignments
+///     .iter()
+///     .map(|a| (a.name(), a.value().unwrap(), a.line()))
+///     .collect::<Vec<_>>();
+/// assert_eq!(triples, [("cores", "4", 1), ("cores", "8", 3)]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_assignments(
+    contents: &str,
+    origin: Option<&Path>,
+) -> Result<Vec<Assignment>, ParseError> {
+    parse_assignments_with_options(contents, origin, &ParseOptions::default())
+}
+
+/// Like [`parse_assignments`], using the provided [`ParseOptions`].
+pub fn parse_assignments_with_options(
+    contents: &str,
+    origin: Option<&Path>,
+    options: &ParseOptions,
+) -> Result<Vec<Assignment>, ParseError> {
+    Ok(parse_contents(contents, origin, options)?.assignments)
+}
+
+/// Everything read from a `nix.conf` and the files it includes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Parsed {
+    pub(crate) assignments: Vec<Assignment>,
+    pub(crate) skipped_includes: Vec<SkippedInclude>,
+    pub(crate) truncated_includes: Vec<TruncatedInclude>,
+    /// Includes that were kept as directives, see [`IncludeMode::Keep`].
+    pub(crate) includes: Vec<IncludeDirective>,
+    /// Every included file that was read, in order.
+    pub(crate) included_files: Vec<PathBuf>,
+}
+
+impl Parsed {
+    /// Add what was parsed from the file at `path`, which was included.
+    fn absorb(&mut self, path: PathBuf, included: Parsed) {
+        self.assignments.extend(included.assignments);
+        self.skipped_includes.extend(included.skipped_includes);
+        self.truncated_includes.extend(included.truncated_includes);
+        self.included_files.push(path);
+        self.included_files.extend(included.included_files);
+    }
+}
+
+/// Read the file at `path`. It isn't checked for before, since it could change
+/// in between, and so that only a missing file is reported as
+/// [`ParseError::FileNotFound`].
+pub(crate) fn read_file(path: &Path) -> Result<String, ParseError> {
+    std::fs::read_to_string(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => ParseError::FileNotFound(path.to_owned()),
+        // Depending on the platform, opening or reading a directory fails.
+        _ if path.is_dir() => ParseError::IsADirectory(path.to_owned()),
+        _ => ParseError::FailedToReadFile(path.to_owned(), e),
+    })
+}
+
+/// Parse every `*.conf` file in the directory at `path`, in lexicographic order,
+/// see [`ParseOptions::directories`].
+fn parse_directory(path: &Path, options: &ParseOptions) -> Result<Parsed, ParseError> {
+    let failed = |e| ParseError::FailedToReadFile(path.to_owned(), e);
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path).map_err(failed)? {
+        let file = entry.map_err(failed)?.path();
+        if file.extension().is_some_and(|ext| ext == "conf") && file.is_file() {
+            files.push(file);
+        }
+    }
+    files.sort();
+
+    let mut parsed = Parsed::default();
+    for file in files {
+        let contents = WipeOnDrop::from(read_file(&file)?);
+        let file_parsed = parse_contents(&contents, Some(&file), options)?;
+
+        parsed.assignments.extend(file_parsed.assignments);
+        parsed.skipped_includes.extend(file_parsed.skipped_includes);
+        parsed
+            .truncated_includes
+            .extend(file_parsed.truncated_includes);
+        parsed.includes.extend(file_parsed.includes);
+        parsed.included_files.push(file);
+        parsed.included_files.extend(file_parsed.included_files);
+    }
+
+    Ok(parsed)
+}
+
+pub(crate) fn parse_contents(
+    contents: &str,
+    origin: Option<&Path>,
+    options: &ParseOptions,
+) -> Result<Parsed, ParseError> {
+    parse_contents_for_keys(contents, origin, options, None)
+}
 
-            let mut tokens = line.split(&[' ', '\t', '\n', '\r']).collect::<Vec<_>>();
-            tokens.retain(|t| !t.is_empty());
+/// Like [`parse_contents`], but if `keys` is given, only keeps assignments (and
+/// unsets) of those settings and their `extra-` variants. Lines for other
+/// settings are skipped without being tokenized, so they can't fail the parse.
+pub(crate) fn parse_contents_for_keys(
+    contents: &str,
+    origin: Option<&Path>,
+    options: &ParseOptions,
+    keys: Option<&[&str]>,
+) -> Result<Parsed, ParseError> {
+    let origin = origin.map(|origin| options.origin(origin));
+    let origin = origin.as_deref();
+    if is_plain(contents, options) {
+        return parse_plain_assignments(contents, origin, keys);
+    }
+
+    let mut parsed = Parsed::default();
 
-            if tokens.is_empty() {
+    for (line_number, line) in logical_lines(contents, options) {
+        let line = WipeOnDrop::from(line);
+        let line = &*line;
+        if let Some(keys) = keys {
+            let first = line
+                .split([' ', '\t', '\r'])
+                .find(|token| !token.is_empty());
+            if first.is_some_and(|first| {
+                !matches!(first, "include" | "!include" | "!unset")
+                    && options.directives.get(first).is_none()
+                    && !is_wanted(first, keys)
+            }) {
                 continue;
             }
+        }
 
-            if tokens.len() < 2 {
-                return Err(ParseError::IllegalConfiguration(
-                    line.to_owned(),
-                    origin.map(ToOwned::to_owned),
-                ));
-            }
+        let illegal = |line: &str| illegal_line(line, origin);
 
-            let mut include = false;
-            let mut ignore_missing = false;
-            if tokens[0] == "include" {
-                include = true;
-            } else if tokens[0] == "!include" {
-                include = true;
-                ignore_missing = true;
+        match parse_line(line, options).map_err(illegal)?.directive {
+            Directive::None => {}
+            Directive::Custom { word, args } => {
+                let directive = CustomDirective {
+                    word: word.into_owned(),
+                    args: args.into_iter().map(Cow::into_owned).collect(),
+                    origin: Origin::File {
+                        path: origin.map(ToOwned::to_owned),
+                        line: line_number + 1,
+                    },
+                };
+                if let Some(handler) = options.directives.get(&directive.word) {
+                    handler(&directive).map_err(|message| {
+                        ParseError::DirectiveRejected(
+                            directive.word.clone(),
+                            message,
+                            origin.map(ToOwned::to_owned),
+                        )
+                    })?;
+                }
             }
-
-            if include {
-                if tokens.len() != 2 {
-                    return Err(ParseError::IllegalConfiguration(
-                        line.to_owned(),
-                        origin.map(ToOwned::to_owned),
-                    ));
+            Directive::Include {
+                path,
+                optional,
+                ignored,
+            } => {
+                let include_path = PathBuf::from(path.as_ref());
+                let include_origin = Origin::File {
+                    path: origin.map(ToOwned::to_owned),
+                    line: line_number + 1,
+                };
+                if !ignored.is_empty() {
+                    parsed.truncated_includes.push(TruncatedInclude {
+                        path: include_path.clone(),
+                        ignored: ignored.into_iter().map(Cow::into_owned).collect(),
+                        origin: include_origin.clone(),
+                    });
                 }
 
-                let include_path = PathBuf::from(tokens[1]);
-                match Self::parse_file(&include_path) {
-                    Ok(conf) => settings.settings_mut().extend(conf.into_settings()),
-                    Err(_) if ignore_missing => {}
-                    Err(_) if !ignore_missing => {
-                        return Err(ParseError::IncludedFileNotFound(
+                match options.includes {
+                    IncludeMode::Inline => {}
+                    IncludeMode::Keep => {
+                        parsed.includes.push(IncludeDirective {
+                            path: include_path,
+                            optional,
+                            origin: include_origin,
+                        });
+                        continue;
+                    }
+                    IncludeMode::Error => {
+                        return Err(ParseError::IncludeNotAllowed(
                             include_path,
                             origin.map(ToOwned::to_owned),
                         ));
                     }
-                    _ => unreachable!(),
                 }
 
+                #[cfg(feature = "http")]
+                if let Some(remote) = options
+                    .remote_includes
+                    .as_ref()
+                    .filter(|remote| remote.handles(&path))
+                {
+                    match remote.fetch(&path) {
+                        Ok(contents) => {
+                            let contents = WipeOnDrop::from(contents);
+                            #[cfg(feature = "signatures")]
+                            if let Some(keys) = &options.trusted_keys {
+                                remote
+                                    .fetch_signatures(&path)
+                                    .map_or(Err(SignatureError::Unsigned), |signatures| {
+                                        keys.verify(contents.as_bytes(), &signatures)
+                                    })
+                                    .map_err(|e| {
+                                        ParseError::UnverifiedInclude(include_path.clone(), e)
+                                    })?;
+                            }
+                            match parse_contents_for_keys(
+                                &contents,
+                                Some(&include_path),
+                                options,
+                                keys,
+                            ) {
+                                Ok(included) => parsed.absorb(include_path, included),
+                                Err(e) => {
+                                    return Err(include::include_failed(include_path, origin, e))
+                                }
+                            }
+                        }
+                        Err(e @ ParseError::ChecksumMismatch(..)) => return Err(e),
+                        Err(e) if optional || options.include_errors.skips(&include_path, &e) => {
+                            parsed.skipped_includes.push(SkippedInclude {
+                                path: include_path,
+                                origin: include_origin,
+                            })
+                        }
+                        Err(e) => return Err(e),
+                    }
+                    continue;
+                }
+
+                let include_path = include::resolve_include(&path, origin, options);
+                let included = read_file(&include_path).and_then(|contents| {
+                    let contents = WipeOnDrop::from(contents);
+                    #[cfg(feature = "signatures")]
+                    if let Some(keys) = &options.trusted_keys {
+                        keys.verify_file(&include_path, contents.as_bytes())
+                            .map_err(|e| ParseError::UnverifiedInclude(include_path.clone(), e))?;
+                    }
+                    parse_contents_for_keys(&contents, Some(&include_path), options, keys)
+                });
+                match included {
+                    Ok(included) => parsed.absorb(include_path, included),
+                    #[cfg(feature = "signatures")]
+                    Err(e @ ParseError::UnverifiedInclude(..)) => return Err(e),
+                    Err(e)
+                        if (optional && include::is_unreadable(&e))
+                            || options.include_errors.skips(&include_path, &e) =>
+                    {
+                        parsed.skipped_includes.push(SkippedInclude {
+                            path: include_path,
+                            origin: include_origin,
+                        })
+                    }
+                    Err(e) => return Err(include::include_failed(include_path, origin, e)),
+                }
+            }
+            Directive::Assignment { name, value } => {
+                let assignment = Assignment {
+                    name: name.into_owned(),
+                    value: Some(value),
+                    origin: Origin::File {
+                        path: origin.map(ToOwned::to_owned),
+                        line: line_number + 1,
+                    },
+                };
+                if options.strict_settings {
+                    check_setting(&assignment, options)?;
+                }
+                parsed.assignments.push(assignment);
+            }
+            Directive::Unset { name } if keys.is_some_and(|keys| !is_wanted(&name, keys)) => {}
+            Directive::Unset { name } => parsed.assignments.push(Assignment {
+                name: name.into_owned(),
+                value: None,
+                origin: Origin::File {
+                    path: origin.map(ToOwned::to_owned),
+                    line: line_number + 1,
+                },
+            }),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// The lines of `contents` with their (zero-based) numbers, with lines ending
+/// in a `\` joined with the next one if enabled, see
+/// [`ParseOptions::line_continuations`].
+fn logical_lines<'a>(
+    contents: &'a str,
+    options: &'a ParseOptions,
+) -> impl Iterator<Item = (usize, Cow<'a, str>)> + 'a {
+    let mut lines = contents.lines().enumerate();
+
+    std::iter::from_fn(move || {
+        let (line_number, line) = lines.next()?;
+        let mut line = Cow::Borrowed(line);
+
+        while let Some(mut joined) = continued_line(&line, options).map(str::to_owned) {
+            let Some((_, next)) = lines.next() else {
+                line = Cow::Owned(joined);
+                break;
+            };
+            joined.push(' ');
+            joined.push_str(next);
+            line = Cow::Owned(joined);
+        }
+
+        Some((line_number, line))
+    })
+}
+
+/// If `line` is continued on the next line, the part before the `\`.
+pub(crate) fn continued_line<'a>(line: &'a str, options: &ParseOptions) -> Option<&'a str> {
+    if !options.line_continuations {
+        return None;
+    }
+
+    let line = line.trim_end();
+    line.strip_suffix('\\')
+        .filter(|_| find_comment(line, options).is_none())
+}
+
+/// Check a setting against the registry, see [`ParseOptions::strict_settings`].
+fn check_setting(assignment: &Assignment, options: &ParseOptions) -> Result<(), ParseError> {
+    let Some(value) = &assignment.value else {
+        return Ok(());
+    };
+
+    let Some(info) = SettingInfo::lookup_in(&assignment.name, options.dialect) else {
+        return Err(ParseError::UnknownSetting(
+            assignment.name.clone(),
+            assignment.origin.clone(),
+        ));
+    };
+
+    let value = value.clone();
+    validate::check_value(info, &value).map_err(|message| {
+        ParseError::InvalidValue(ValueProblem {
+            name: assignment.name.clone(),
+            value,
+            message,
+            origin: Some(assignment.origin.clone()),
+        })
+    })
+}
+
+/// Whether the setting `name` is one of `keys`, or the `extra-` variant of one.
+fn is_wanted(name: &str, keys: &[&str]) -> bool {
+    let base = name.strip_prefix("extra-").unwrap_or(name);
+    keys.iter().any(|key| *key == name || *key == base)
+}
+
+/// Whether `contents` has nothing but `name = value` lines (and blank lines), as
+/// is typical for machine-generated configs, which can be parsed much faster.
+fn is_plain(contents: &str, options: &ParseOptions) -> bool {
+    let bytes = contents.as_bytes();
+
+    memchr::memchr(b'#', bytes).is_none()
+        && memchr::memmem::find(bytes, b"include").is_none()
+        && !(options.quoting && memchr::memchr(b'"', bytes).is_some())
+        && !(options.unset_directives && memchr::memmem::find(bytes, b"!unset").is_some())
+        && options.directives.is_empty()
+        && !(options.line_continuations && memchr::memchr(b'\\', bytes).is_some())
+        && !options.compact_assignments
+        && !options.strict_settings
+}
+
+/// The fast path of [`parse_contents`] for contents that are
+/// [plain](is_plain), producing exactly the same result in a single pass
+/// without tokenizing every line.
+fn parse_plain_assignments(
+    contents: &str,
+    origin: Option<&Path>,
+    keys: Option<&[&str]>,
+) -> Result<Parsed, ParseError> {
+    const SEPARATORS: [char; 3] = [' ', '\t', '\r'];
+
+    let mut parsed = Parsed::default();
+
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut tokens = line.split(SEPARATORS).filter(|token| !token.is_empty());
+        if let (Some(keys), Some(name)) = (keys, tokens.clone().next()) {
+            if !is_wanted(name, keys) {
                 continue;
             }
+        }
 
-            if tokens[1] != "=" {
-                return Err(ParseError::IllegalConfiguration(
-                    line.to_owned(),
-                    origin.map(ToOwned::to_owned),
-                ));
+        let (Some(name), Some("=")) = (tokens.next(), tokens.next()) else {
+            return Err(illegal_line(line, origin));
+        };
+
+        let mut value = String::new();
+        for token in tokens {
+            if !value.is_empty() {
+                value.push(' ');
             }
+            value.push_str(token);
+        }
+
+        parsed.assignments.push(Assignment {
+            name: name.to_owned(),
+            value: Some(value.into()),
+            origin: Origin::File {
+                path: origin.map(ToOwned::to_owned),
+                line: line_number + 1,
+            },
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// A single line of a `nix.conf`, split into what it does and its comment.
+pub(crate) struct Line<'a> {
+    pub(crate) directive: Directive<'a>,
+    /// The comment at the end of the line (or making up the entire line),
+    /// starting with the `#`.
+    pub(crate) comment: Option<&'a str>,
+}
+
+pub(crate) enum Directive<'a> {
+    /// A blank or comment-only line.
+    None,
+    Include {
+        path: Cow<'a, str>,
+        optional: bool,
+        /// Tokens after the path, see [`ParseOptions::lenient_includes`].
+        ignored: Vec<Cow<'a, str>>,
+    },
+    Assignment {
+        name: Cow<'a, str>,
+        value: SettingValue,
+    },
+    /// `!unset name`, see [`ParseOptions::unset_directives`].
+    Unset { name: Cow<'a, str> },
+    /// A line claimed by a handler, see [`ParseOptions::on_directive`].
+    Custom {
+        word: Cow<'a, str>,
+        args: Vec<Cow<'a, str>>,
+    },
+}
+
+// Mostly a carbon copy of AbstractConfig::applyConfig from Nix:
+// https://github.com/NixOS/nix/blob/0079d2943702a7a7fbdd88c0f9a5ad677c334aa8/src/libutil/config.cc#L80
+// Some things were adjusted to be more idiomatic, as well as to account for the lack of
+// `try { ... } catch (SpecificErrorType &) { }`
+/// Parse a single line. On failure, returns the offending (comment-free and
+/// trimmed) part of the line.
+pub(crate) fn parse_line<'a>(line: &'a str, options: &ParseOptions) -> Result<Line<'a>, &'a str> {
+    let mut line = line;
+    let mut comment = None;
+
+    // skip comments
+    if let Some(pos) = find_comment(line, options) {
+        comment = Some(&line[pos..]);
+        line = &line[..pos];
+    }
+
+    line = line.trim();
+
+    let directive = |directive| Ok(Line { directive, comment });
+
+    if line.is_empty() {
+        return directive(Directive::None);
+    }
+
+    let mut tokens = tokenize(line, options).ok_or(line)?;
+    if options.compact_assignments {
+        split_compact_assignment(&mut tokens);
+    }
+
+    if tokens.is_empty() {
+        return directive(Directive::None);
+    }
+
+    // Names (and the `=` separator, see below) are never allowed to be quoted
+    if tokens[0].quoted {
+        return Err(line);
+    }
+    let separator_quoted = tokens.get(1).is_some_and(|t| t.quoted);
+
+    let mut tokens = tokens
+        .into_iter()
+        .map(|t| match t.text {
+            text if options.hash_escapes && text.contains("\\#") => {
+                WipeOnDrop::from(text.replace("\\#", "#"))
+            }
+            text => text,
+        })
+        .collect::<Vec<_>>();
+
+    // Directives may take no arguments, so they come before the check for a
+    // name and a value. `include`, `!include` and `!unset` can't be claimed.
+    let reserved = matches!(&*tokens[0], "include" | "!include")
+        || (options.unset_directives && &*tokens[0] == "!unset");
+    if !reserved && options.directives.get(&tokens[0]).is_some() {
+        let word = tokens.remove(0).into_inner();
+        let args = tokens.into_iter().map(WipeOnDrop::into_inner).collect();
+        return directive(Directive::Custom { word, args });
+    }
 
-            let name = tokens[0];
-            let value = tokens[2..].join(" ");
-            settings.settings_mut().insert(name.into(), value);
+    if tokens.len() < 2 {
+        return Err(line);
+    }
+
+    let mut include = false;
+    let mut ignore_missing = false;
+    if &*tokens[0] == "include" {
+        include = true;
+    } else if &*tokens[0] == "!include" {
+        include = true;
+        ignore_missing = true;
+    }
+
+    if options.unset_directives && &*tokens[0] == "!unset" {
+        if tokens.len() != 2 || tokens[1].is_empty() {
+            return Err(line);
+        }
+
+        return directive(Directive::Unset {
+            name: tokens.swap_remove(1).into_inner(),
+        });
+    }
+
+    if include {
+        if tokens.len() != 2 && !(options.lenient_includes && tokens.len() > 2) {
+            return Err(line);
         }
 
-        Ok(settings)
+        let ignored = tokens
+            .split_off(2)
+            .into_iter()
+            .map(WipeOnDrop::into_inner)
+            .collect();
+        return directive(Directive::Include {
+            path: tokens.swap_remove(1).into_inner(),
+            optional: ignore_missing,
+            ignored,
+        });
     }
+
+    if &*tokens[1] != "=" || separator_quoted {
+        return Err(line);
+    }
+
+    // The tokens of the value are wiped when they are dropped.
+    let value = SettingValue::from(tokens[2..].join(" "));
+    directive(Directive::Assignment {
+        name: tokens.swap_remove(0).into_inner(),
+        value,
+    })
+}
+
+/// Split `name=value`, `name= value` and `name =value` into separate tokens,
+/// see [`ParseOptions::compact_assignments`].
+fn split_compact_assignment<'a>(tokens: &mut Vec<Token<'a>>) {
+    let unquoted = |token: Option<&Token<'a>>| -> Option<&'a str> {
+        token.filter(|token| !token.quoted)?.text.borrowed()
+    };
+
+    if unquoted(tokens.get(1)) == Some("=") {
+        return;
+    }
+
+    let borrowed = |text: &'a str| Token {
+        text: text.into(),
+        quoted: false,
+    };
+
+    if let Some((name, value)) = unquoted(tokens.first())
+        .and_then(|first| first.split_once('='))
+        .filter(|(name, _)| !name.is_empty())
+    {
+        let mut split = vec![borrowed(name), borrowed("=")];
+        if !value.is_empty() {
+            split.push(borrowed(value));
+        }
+        tokens.splice(..1, split);
+    } else if let Some(value) = unquoted(tokens.get(1)).and_then(|second| second.strip_prefix('='))
+    {
+        tokens.splice(1..2, [borrowed("="), borrowed(value)]);
+    }
+}
+
+/// Find the start of the comment on this line, if any, skipping over `\#`
+/// escapes if enabled.
+fn find_comment(line: &str, options: &ParseOptions) -> Option<usize> {
+    if !options.hash_escapes {
+        return line.find('#');
+    }
+
+    line.match_indices('#')
+        .map(|(pos, _)| pos)
+        .find(|&pos| !line[..pos].ends_with('\\'))
+}
+
+struct Token<'a> {
+    text: WipeOnDrop<'a>,
+    quoted: bool,
+}
+
+/// Split a (comment-free, trimmed) line into whitespace-separated tokens,
+/// honoring `"..."` quoting if enabled. Returns `None` if the line contains
+/// malformed quoting.
+fn tokenize<'a>(line: &'a str, options: &ParseOptions) -> Option<Vec<Token<'a>>> {
+    const SEPARATORS: [char; 4] = [' ', '\t', '\n', '\r'];
+
+    let mut tokens = Vec::new();
+    let mut rest = line;
+
+    loop {
+        rest = rest.trim_start_matches(SEPARATORS);
+        if rest.is_empty() {
+            return Some(tokens);
+        }
+
+        if options.quoting && rest.starts_with('"') {
+            // Sized up front, as growing it would free parts of it unwiped.
+            let mut text = WipeOnDrop::from(String::with_capacity(rest.len()));
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (i, '"') => break i + 2,
+                    (_, '\\') => match chars.next()? {
+                        (_, c @ ('"' | '\\')) => text.to_mut().push(c),
+                        (_, c) => {
+                            text.to_mut().push('\\');
+                            text.to_mut().push(c);
+                        }
+                    },
+                    (_, c) => text.to_mut().push(c),
+                }
+            };
+
+            rest = &rest[end..];
+            if !rest.is_empty() && !rest.starts_with(SEPARATORS) {
+                return None;
+            }
+
+            tokens.push(Token { text, quoted: true });
+        } else {
+            let end = rest.find(SEPARATORS).unwrap_or(rest.len());
+            tokens.push(Token {
+                text: rest[..end].into(),
+                quoted: false,
+            });
+            rest = &rest[end..];
+        }
+    }
+}
+
+/// The error for the illegal `line` (without its comment), telling the
+/// common mistakes apart.
+pub(crate) fn illegal_line(line: &str, origin: Option<&Path>) -> ParseError {
+    let line = line.trim();
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let error = match tokens.as_slice() {
+        // Quoted names and `!unset` without the extension are illegal as a
+        // whole.
+        _ if line.starts_with('"') => ParseError::IllegalConfiguration,
+        ["!unset", ..] => ParseError::IllegalConfiguration,
+        ["include" | "!include", ..] => ParseError::MalformedInclude,
+        [first, ..] if first.starts_with('=') => ParseError::EmptyKey,
+        [name] if name.contains('=') => ParseError::MissingEquals,
+        [_] => ParseError::MissingValue,
+        [_, second, ..] if second.trim_matches('"') != "=" => ParseError::MissingEquals,
+        _ => ParseError::IllegalConfiguration,
+    };
+
+    error(line.to_owned(), origin.map(ToOwned::to_owned))
 }
 
 /// An error that occurred while attempting to parse a `nix.conf` [`Path`] or
@@ -176,10 +1464,48 @@ pub enum ParseError {
     FileNotFound(PathBuf),
     #[error("file '{0}' included from '{}' not found", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
     IncludedFileNotFound(PathBuf, Option<PathBuf>),
+    #[error("file '{0}' included from '{}' could not be read: {2}", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    IncludedFileUnreadable(PathBuf, Option<PathBuf>, #[source] Box<ParseError>),
+    #[error("in file '{0}' included from '{}': {2}", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    InIncludedFile(PathBuf, Option<PathBuf>, #[source] Box<ParseError>),
     #[error("illegal configuration line '{0}' in '{}'", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
     IllegalConfiguration(String, Option<PathBuf>),
+    #[error("missing ' = ' between the name and the value in line '{0}' in '{}'", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    MissingEquals(String, Option<PathBuf>),
+    #[error("setting '{0}' in '{}' has no ' = ' or value", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    MissingValue(String, Option<PathBuf>),
+    #[error("malformed include line '{0}' in '{}': expected a single path", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    MalformedInclude(String, Option<PathBuf>),
+    #[error("missing setting name in line '{0}' in '{}'", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    EmptyKey(String, Option<PathBuf>),
     #[error("failed to read contents of '{0}': {1}")]
     FailedToReadFile(PathBuf, #[source] std::io::Error),
+    #[error("failed to read config: {0}")]
+    FailedToRead(#[source] std::io::Error),
+    #[error("include of '{0}' in '{}' is not allowed", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    IncludeNotAllowed(PathBuf, Option<PathBuf>),
+    #[error("'{0}' is a directory, not a file")]
+    IsADirectory(PathBuf),
+    #[error("'{0}' directive in '{}' was rejected: {1}", .2.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    DirectiveRejected(String, String, Option<PathBuf>),
+    #[cfg(feature = "http")]
+    #[error("failed to fetch remote include '{0}': {1}")]
+    RemoteIncludeFailed(String, String),
+    #[cfg(feature = "http")]
+    #[error("remote include '{0}' has SHA-256 {2}, but {1} is pinned")]
+    ChecksumMismatch(String, String, String),
+    #[cfg(feature = "s3")]
+    #[error("failed to fetch '{0}': {1}")]
+    ObjectFetchFailed(String, String),
+    #[cfg(feature = "signatures")]
+    #[error("refusing to include '{0}': {1}")]
+    UnverifiedInclude(PathBuf, #[source] SignatureError),
+    #[error("invalid text encoding at byte {0} of '{}'", .1.as_ref().map(|path| path.display().to_string()).unwrap_or(String::from("<unknown>")))]
+    InvalidEncoding(usize, Option<PathBuf>),
+    #[error("{1}: unknown setting '{0}'")]
+    UnknownSetting(String, Origin),
+    #[error("{0}")]
+    InvalidValue(ValueProblem),
 }
 
 #[cfg(test)]
@@ -231,41 +1557,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_directories() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("20-cores.conf"), "cores = 8\n").unwrap();
+        std::fs::write(
+            temp_dir.path().join("10-base.conf"),
+            "cores = 4\nmax-jobs = 2\n",
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("README"), "not a config\n").unwrap();
+
+        let nix_conf = NixConfig::parse_file_with_options(
+            temp_dir.path(),
+            &ParseOptions::new().directories(true),
+        )
+        .unwrap();
+        assert_eq!(nix_conf.settings().get("cores"), Some(&"8".into()));
+        assert_eq!(nix_conf.settings().get("max-jobs"), Some(&"2".into()));
+    }
+
+    #[test]
+    fn parses_config_from_reader() {
+        let res = NixConfig::from_reader(
+            "cores = 4242\nexperimental-features = flakes nix-command".as_bytes(),
+            None,
+        );
+
+        assert_eq!(res.unwrap().settings().get("cores"), Some(&"4242".into()));
+
+        match NixConfig::from_reader(&[0xff, 0xfe][..], None) {
+            Err(ParseError::FailedToRead(_)) => (),
+            _ => panic!("invalid UTF-8 should have returned ParseError::FailedToRead"),
+        }
+    }
+
     #[test]
     fn errors_on_invalid_config() {
         let temp_dir = tempfile::TempDir::new().unwrap();
         let test_file = temp_dir.path().join("does-not-exist");
 
         match NixConfig::parse_string("bad config".into(), None) {
-            Err(ParseError::IllegalConfiguration(_, _)) => (),
-            _ => assert!(
-                false,
-                "bad config should have returned ParseError::IllegalConfiguration"
+            Err(ParseError::MissingEquals(_, _)) => (),
+            _ => panic!("bad config should have returned ParseError::MissingEquals"),
+        }
+
+        for (bad, expected) in [
+            ("cores", "setting 'cores' in '<unknown>' has no ' = ' or value"),
+            ("= 4", "missing setting name in line '= 4' in '<unknown>'"),
+            (
+                "include a.conf b.conf",
+                "malformed include line 'include a.conf b.conf' in '<unknown>': expected a single path",
             ),
+        ] {
+            let error = NixConfig::parse_string(bad.into(), None).unwrap_err();
+            assert_eq!(error.to_string(), expected);
         }
 
         match NixConfig::parse_file(&test_file) {
             Err(ParseError::FileNotFound(path)) => assert_eq!(path, test_file),
-            _ => assert!(
-                false,
-                "nonexistent path should have returned ParseError::FileNotFound"
-            ),
+            _ => panic!("nonexistent path should have returned ParseError::FileNotFound"),
         }
 
         match NixConfig::parse_string(format!("include {}", test_file.display()), None) {
             Err(ParseError::IncludedFileNotFound(path, _)) => assert_eq!(path, test_file),
-            _ => assert!(
-                false,
+            _ => panic!(
                 "nonexistent include path should have returned ParseError::IncludedFileNotFound"
             ),
         }
 
         match NixConfig::parse_file(temp_dir.path()) {
-            Err(ParseError::FailedToReadFile(path, _)) => assert_eq!(path, temp_dir.path()),
-            _ => assert!(
-                false,
-                "trying to read a dir to a string should have returned ParseError::FailedToReadFile"
-            ),
+            Err(ParseError::IsADirectory(path)) => assert_eq!(path, temp_dir.path()),
+            _ => panic!("trying to read a dir should have returned ParseError::IsADirectory"),
+        }
+
+        // A file can't have children, but it exists.
+        #[cfg(unix)]
+        {
+            let file = temp_dir.path().join("nix.conf");
+            std::fs::write(&file, "").unwrap();
+            match NixConfig::parse_file(&file.join("extra.conf")) {
+                Err(ParseError::FailedToReadFile(_, e)) => {
+                    assert_eq!(e.kind(), std::io::ErrorKind::NotADirectory)
+                }
+                _ => panic!(
+                    "a path through a file should have returned ParseError::FailedToReadFile"
+                ),
+            }
         }
     }
 
@@ -286,6 +1664,184 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_quoted_values_when_enabled() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let include_dir = temp_dir.path().join("First Last");
+        std::fs::create_dir(&include_dir).unwrap();
+        let include_file = include_dir.join("nix.conf");
+        std::fs::write(&include_file, "cores = 4242").unwrap();
+
+        let options = ParseOptions::new().quoting(true);
+        let res = NixConfig::parse_string_with_options(
+            format!(
+                "include \"{}\"\nnetrc-file = \"/Users/First  Last/.netrc\"\nescapes = \"a \\\"b\\\" \\\\ \\n\" c",
+                include_file.display()
+            ),
+            None,
+            &options,
+        );
+
+        assert!(res.is_ok());
+
+        let map = res.unwrap();
+
+        assert_eq!(map.settings().get("cores"), Some(&"4242".into()));
+        assert_eq!(
+            map.settings().get("netrc-file"),
+            Some(&"/Users/First  Last/.netrc".into())
+        );
+        assert_eq!(
+            map.settings().get("escapes"),
+            Some(&"a \"b\" \\ \\n c".into())
+        );
+
+        for bad in [
+            "netrc-file = \"unterminated",
+            "netrc-file = \"trailing\"junk",
+            "\"quoted name\" = value",
+            "name \"=\" value",
+        ] {
+            match NixConfig::parse_string_with_options(bad.into(), None, &options) {
+                Err(ParseError::IllegalConfiguration(_, _)) => (),
+                _ => panic!("'{bad}' should have returned ParseError::IllegalConfiguration"),
+            }
+        }
+
+        // Quotes are not special without the extension, just like in Nix
+        let res = NixConfig::parse_string("netrc-file = \"a  b\"".into(), None).unwrap();
+        assert_eq!(res.settings().get("netrc-file"), Some(&"\"a b\"".into()));
+    }
+
+    #[test]
+    fn parses_unset_directives_when_enabled() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let include_file = temp_dir.path().join("machine.conf");
+        std::fs::write(&include_file, "!unset cores\n").unwrap();
+
+        let contents = format!(
+            "cores = 4\nmax-jobs = 2\ninclude {}\n!unset max-jobs\nmax-jobs = 8",
+            include_file.display()
+        );
+        let options = ParseOptions::new().unset_directives(true);
+
+        let map = NixConfig::parse_string_with_options(contents, None, &options).unwrap();
+
+        assert_eq!(map.settings().get("cores"), None);
+        assert_eq!(map.settings().get("max-jobs"), Some(&"8".into()));
+        assert_eq!(map.origin("cores"), None);
+
+        match NixConfig::parse_string("!unset max-jobs".into(), None) {
+            Err(ParseError::IllegalConfiguration(_, _)) => (),
+            _ => panic!("unset directives should have returned ParseError::IllegalConfiguration"),
+        }
+    }
+
+    #[test]
+    fn parses_lenient_syntax_when_enabled() {
+        let contents = "cores=4\nmax-jobs =2\n# not continued \\\nsubstituters = \\\n  https://a.example.com \\\n  https://b.example.com\nsandbox= false";
+        let options = ParseOptions::lenient();
+
+        let map = NixConfig::parse_string_with_options(contents.into(), None, &options).unwrap();
+        assert_eq!(map.settings().get("cores"), Some(&"4".into()));
+        assert_eq!(map.settings().get("max-jobs"), Some(&"2".into()));
+        assert_eq!(map.settings().get("sandbox"), Some(&"false".into()));
+        assert_eq!(
+            map.settings().get("substituters"),
+            Some(&"https://a.example.com https://b.example.com".into())
+        );
+        assert_eq!(
+            map.origin("substituters"),
+            Some(&Origin::File {
+                path: None,
+                line: 4
+            })
+        );
+
+        assert!(NixConfig::parse_string("cores=4".into(), None).is_err());
+        for bad in ["=4", "cores= \"4\"x"] {
+            assert!(
+                NixConfig::parse_string_with_options(bad.into(), None, &options).is_err(),
+                "'{bad}' should have been rejected"
+            );
+        }
+        assert_eq!(
+            NixConfig::parse_string_with_options("cores = 4 \\".into(), None, &options)
+                .unwrap()
+                .settings()
+                .get("cores"),
+            Some(&"4".into())
+        );
+    }
+
+    #[test]
+    fn plain_fast_path_matches_general_path() {
+        let plain = " cores = 4\r\n\nsubstituters =\thttps://a.example.com   https://b.example.com\nempty =\n";
+        assert!(is_plain(plain, &ParseOptions::default()));
+
+        let fast = NixConfig::parse_string(plain.into(), None).unwrap();
+        let general = NixConfig::parse_string(format!("{plain}# comment"), None).unwrap();
+        assert_eq!(fast, general);
+        assert_eq!(fast.origin("empty"), general.origin("empty"));
+
+        for bad in ["cores=4", "cores", "cores 4"] {
+            let fast = NixConfig::parse_string(bad.into(), None).unwrap_err();
+            let general = NixConfig::parse_string(format!("{bad} # comment"), None).unwrap_err();
+            assert_eq!(fast.to_string(), general.to_string());
+        }
+    }
+
+    #[test]
+    fn parses_hash_escapes_when_enabled() {
+        let contents = "substituters = https://cache.example.com/\\#frag # comment";
+
+        let res = NixConfig::parse_string_with_options(
+            contents.into(),
+            None,
+            &ParseOptions::new().hash_escapes(true),
+        )
+        .unwrap();
+        assert_eq!(
+            res.settings().get("substituters"),
+            Some(&"https://cache.example.com/#frag".into())
+        );
+
+        let res = NixConfig::parse_string(contents.into(), None).unwrap();
+        assert_eq!(
+            res.settings().get("substituters"),
+            Some(&"https://cache.example.com/\\".into())
+        );
+    }
+
+    #[test]
+    fn projections_preserve_origins() {
+        let map = NixConfig::parse_string(
+            "cores = 4\nmax-jobs = 2\nwarn-dirty = false".into(),
+            Some(Path::new("/etc/nix/nix.conf")),
+        )
+        .unwrap();
+
+        let subset = map.subset(["warn-dirty", "cores", "not-set"]);
+        assert_eq!(
+            subset.settings().keys().collect::<Vec<_>>(),
+            vec!["cores", "warn-dirty"]
+        );
+        assert_eq!(
+            subset.origin("warn-dirty"),
+            Some(&Origin::File {
+                path: Some("/etc/nix/nix.conf".into()),
+                line: 3
+            })
+        );
+
+        let without = map.without(vec![String::from("cores")]);
+        assert_eq!(
+            without.settings().keys().collect::<Vec<_>>(),
+            vec!["max-jobs", "warn-dirty"]
+        );
+        assert_eq!(without.origin("max-jobs"), map.origin("max-jobs"));
+    }
+
     #[test]
     fn returns_the_same_order() {
         let res = NixConfig::parse_string(
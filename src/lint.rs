@@ -0,0 +1,654 @@
+use std::fmt;
+
+use crate::{
+    Conflict, IncludeMode, NixConfig, NixConfigDocument, Origin, SerializeError, SettingInfo,
+};
+
+/// A problem found by [`NixConfig::lint`]: something Nix accepts, but that is
+/// likely a mistake or just noise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub(crate) code: String,
+    pub(crate) severity: Severity,
+    pub(crate) name: String,
+    pub(crate) message: String,
+    pub(crate) origin: Option<Origin>,
+    pub(crate) fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    /// The short, stable identifier of the check that produced this
+    /// diagnostic, such as `identical-override`.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// How serious the problem is. Every check reports
+    /// [`Severity::Warning`]s, unless configured otherwise with a
+    /// [`LintConfig`](crate::LintConfig).
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// The setting the diagnostic is about, or `include` for diagnostics
+    /// about `include` directives.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where the offending assignment is, if known.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    /// The edit that resolves this diagnostic, if it can be fixed
+    /// automatically. Apply it to the document at the diagnostic's origin
+    /// using [`NixConfigDocument::apply_fixes`](crate::NixConfigDocument::apply_fixes).
+    pub fn fix(&self) -> Option<&Fix> {
+        self.fix.as_ref()
+    }
+}
+
+/// How serious the problem reported by a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Severity {
+    Error,
+    #[default]
+    Warning,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        })
+    }
+}
+
+/// An edit to a [`NixConfigDocument`](crate::NixConfigDocument) that resolves a
+/// [`Diagnostic`]. Line numbers are 1-based, like in [`Origin::File`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "kebab-case")
+)]
+#[non_exhaustive]
+pub enum Fix {
+    /// Remove the assignment of `name` on `line`, keeping a comment at the end
+    /// of it on a line of its own.
+    RemoveAssignment { name: String, line: usize },
+    /// Rename the setting assigned on `line` from `from` to `to`, keeping its
+    /// value.
+    Rename {
+        from: String,
+        to: String,
+        line: usize,
+    },
+    /// Add `items` to the list setting `name`: to its last assignment in the
+    /// document if there is one, otherwise to `extra-<name>`.
+    AppendItems { name: String, items: Vec<String> },
+    /// Sort the `include` directives on lines `start` through `end` by path.
+    SortIncludes { start: usize, end: usize },
+}
+
+impl Fix {
+    /// Whether applying the fix leaves the configuration Nix sees unchanged
+    /// (or only adds what is needed for it to take effect). Reordering
+    /// includes changes which file wins when they set the same setting, so
+    /// [`Fix::SortIncludes`] is not safe.
+    pub fn is_safe(&self) -> bool {
+        !matches!(self, Fix::SortIncludes { .. })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{origin}: ")?;
+        }
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+impl NixConfig {
+    /// Check the assignments that made up this config for likely mistakes and
+    /// noise, in the order they were found. The checks are:
+    ///
+    /// - `identical-override`: a setting is assigned the value it already had,
+    ///   for example because an installer appended the same lines twice. See
+    ///   [`NixConfigDocument::dedupe`](crate::NixConfigDocument::dedupe).
+    /// - `shadowed-setting`: a setting is overridden with a different value
+    ///   from another file, typically an `include`d one, so the assignment
+    ///   has no effect.
+    /// - `deprecated-alias`: a setting is set through a legacy alias, such as
+    ///   `binary-caches` instead of `substituters`.
+    /// - `missing-experimental-feature`: a setting needs an experimental
+    ///   feature that isn't enabled, see
+    ///   [`NixConfig::missing_experimental_features`].
+    ///
+    /// Diagnostics about assignments in a file come with a [`Fix`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, Origin};
+    ///
+    /// let nix_conf = NixConfig::parse_string("cores = 4\nmax-jobs = 2\ncores = 4".into(), None)?;
+    ///
+    /// let diagnostics = nix_conf.lint();
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].code(), "identical-override");
+    /// assert_eq!(diagnostics[0].origin(), Some(&Origin::File { path: None, line: 3 }));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for shadowed in self.shadowed_settings() {
+            let overriding_value = self
+                .shadowed_settings()
+                .iter()
+                .find(|other| other.name == shadowed.name && other.origin == shadowed.shadowed_by)
+                .map(|other| &other.value)
+                .or_else(|| {
+                    (self.origin(&shadowed.name) == Some(&shadowed.shadowed_by))
+                        .then(|| self.settings().get(&shadowed.name))
+                        .flatten()
+                });
+
+            if overriding_value == Some(&shadowed.value) {
+                diagnostics.push(Diagnostic {
+                    code: "identical-override".into(),
+                    severity: Severity::Warning,
+                    name: shadowed.name.clone(),
+                    message: format!(
+                        "'{}' is set to the value it already has from {}",
+                        shadowed.name, shadowed.origin
+                    ),
+                    origin: Some(shadowed.shadowed_by.clone()),
+                    fix: file_line(&shadowed.shadowed_by).map(|line| Fix::RemoveAssignment {
+                        name: shadowed.name.clone(),
+                        line,
+                    }),
+                });
+            } else if overriding_value.is_some()
+                && in_other_files(&shadowed.origin, &shadowed.shadowed_by)
+            {
+                diagnostics.push(Diagnostic {
+                    code: "shadowed-setting".into(),
+                    severity: Severity::Warning,
+                    name: shadowed.name.clone(),
+                    message: format!(
+                        "'{}' is set here, but overridden by {}",
+                        shadowed.name, shadowed.shadowed_by
+                    ),
+                    origin: Some(shadowed.origin.clone()),
+                    fix: None,
+                });
+            }
+        }
+
+        for name in self.settings().keys() {
+            let (prefix, base) = match name.strip_prefix("extra-") {
+                Some(base) => ("extra-", base),
+                None => ("", name.as_str()),
+            };
+            let Some(info) = SettingInfo::lookup(base) else {
+                continue;
+            };
+            if !info.aliases().contains(&base) {
+                continue;
+            }
+
+            let to = format!("{prefix}{}", info.name());
+            let origin = self.origin(name).cloned();
+            diagnostics.push(Diagnostic {
+                code: "deprecated-alias".into(),
+                severity: Severity::Warning,
+                name: name.clone(),
+                message: format!("'{name}' is a deprecated alias of '{to}'"),
+                fix: origin.as_ref().and_then(file_line).map(|line| Fix::Rename {
+                    from: name.clone(),
+                    to,
+                    line,
+                }),
+                origin,
+            });
+        }
+
+        for conflict in self.missing_experimental_features() {
+            let Conflict::MissingExperimentalFeature { setting, feature } = conflict else {
+                continue;
+            };
+
+            let origin = self.origin(&setting).cloned();
+            diagnostics.push(Diagnostic {
+                code: "missing-experimental-feature".into(),
+                severity: Severity::Warning,
+                message: format!(
+                    "'{setting}' has no effect unless the experimental feature '{feature}' is enabled"
+                ),
+                name: setting,
+                fix: origin
+                    .as_ref()
+                    .and_then(file_line)
+                    .map(|_| Fix::AppendItems {
+                        name: "experimental-features".into(),
+                        items: vec![feature],
+                    }),
+                origin,
+            });
+        }
+
+        diagnostics
+    }
+}
+
+impl NixConfigDocument {
+    /// Check this document for likely mistakes and noise, like
+    /// [`NixConfig::lint`] does for a config. Included files are only read to
+    /// find the settings of this document they override and the experimental
+    /// features they enable, and the `include` directives themselves are
+    /// checked too:
+    ///
+    /// - `unsorted-includes`: a block of consecutive `include` directives
+    ///   isn't sorted by path. Its fix is not [safe](Fix::is_safe), as the
+    ///   order decides which file wins.
+    ///
+    /// A `# nixconf-lint: ignore <code>...` comment silences the listed
+    /// checks for the line it ends, or if it is on a line of its own, for the
+    /// next line:
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let doc = NixConfigDocument::parse_string(
+    ///     [
+    ///         "# nixconf-lint: ignore deprecated-alias",
+    ///         "binary-caches = https://cache.nixos.org",
+    ///         "cores = 4",
+    ///         "cores = 4 # nixconf-lint: ignore identical-override",
+    ///     ]
+    ///     .join("\n"),
+    ///     None,
+    /// )?;
+    ///
+    /// assert!(doc.lint().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        let options = self.options().clone().includes(IncludeMode::Keep);
+        let mut diagnostics = match NixConfig::parse_string_with_options(
+            self.to_string(),
+            self.origin_path(),
+            &options,
+        ) {
+            Ok(nix_conf) => nix_conf.lint(),
+            Err(_) => Vec::new(),
+        };
+
+        let includes = self.includes();
+        // The included files may override settings of this one, and enable
+        // features, so only keep what is missing with them, or nothing if
+        // they can't be read.
+        if !includes.is_empty() {
+            let resolved = NixConfig::parse_string_with_options(
+                self.to_string(),
+                self.origin_path(),
+                &self.options().clone().includes(IncludeMode::Inline),
+            );
+            let missing = resolved
+                .as_ref()
+                .map(NixConfig::missing_experimental_features)
+                .unwrap_or_default();
+
+            diagnostics.retain(|diagnostic| {
+                diagnostic.code != "missing-experimental-feature"
+                    || missing.iter().any(|conflict| {
+                        matches!(
+                            conflict,
+                            Conflict::MissingExperimentalFeature { setting, .. }
+                                if *setting == diagnostic.name
+                        )
+                    })
+            });
+
+            diagnostics.extend(resolved.iter().flat_map(NixConfig::lint).filter(|diagnostic| {
+                diagnostic.code == "shadowed-setting"
+                    && matches!(
+                        &diagnostic.origin,
+                        Some(Origin::File { path, .. }) if path.as_deref() == self.origin_path()
+                    )
+            }));
+        }
+
+        let lines = includes
+            .iter()
+            .map(|include| file_line(include.origin()).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let mut start = 0;
+        while start < includes.len() {
+            let mut end = start + 1;
+            while end < includes.len() && lines[end] == lines[end - 1] + 1 {
+                end += 1;
+            }
+
+            let block = &includes[start..end];
+            if block.windows(2).any(|pair| pair[0].path() > pair[1].path()) {
+                diagnostics.push(Diagnostic {
+                    code: "unsorted-includes".into(),
+                    severity: Severity::Warning,
+                    name: "include".into(),
+                    message: format!(
+                        "the {} include directives starting here are not sorted by path",
+                        block.len()
+                    ),
+                    origin: Some(block[0].origin().clone()),
+                    fix: Some(Fix::SortIncludes {
+                        start: lines[start],
+                        end: lines[end - 1],
+                    }),
+                });
+            }
+
+            start = end;
+        }
+
+        self.without_ignored(diagnostics)
+    }
+
+    /// Drop the diagnostics silenced by `# nixconf-lint: ignore` comments in
+    /// this document.
+    pub(crate) fn without_ignored(&self, mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let ignores = self.lint_ignores();
+        if ignores.is_empty() {
+            return diagnostics;
+        }
+
+        diagnostics.retain(|diagnostic| match &diagnostic.origin {
+            Some(Origin::File { path, line }) if path.as_deref() == self.origin_path() => ignores
+                .get(line)
+                .is_none_or(|codes| !codes.contains(&diagnostic.code)),
+            _ => true,
+        });
+        diagnostics
+    }
+
+    /// Apply the fixes of those `diagnostics` that are about this document,
+    /// returning how many were applied. Fixes whose lines no longer match
+    /// (for example because the document was edited after linting) are
+    /// skipped. Everything else in the document is left as it is.
+    ///
+    /// All fixes are applied, including ones that aren't
+    /// [safe](Fix::is_safe); filter `diagnostics` to leave those out.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let mut doc = NixConfigDocument::parse_string(
+    ///     "binary-caches = https://cache.nixos.org # primary\ncores = 4\ncores = 4\n".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let diagnostics = doc.lint();
+    /// assert_eq!(doc.apply_fixes(&diagnostics)?, 2);
+    /// assert_eq!(
+    ///     doc.to_string(),
+    ///     "substituters = https://cache.nixos.org # primary\ncores = 4\n"
+    /// );
+    /// assert!(doc.lint().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_fixes<'a, I>(&mut self, diagnostics: I) -> Result<usize, SerializeError>
+    where
+        I: IntoIterator<Item = &'a Diagnostic>,
+    {
+        let mut fixes = diagnostics
+            .into_iter()
+            .filter(|diagnostic| {
+                matches!(
+                    &diagnostic.origin,
+                    Some(Origin::File { path, .. }) if path.as_deref() == self.origin_path()
+                )
+            })
+            .filter_map(Diagnostic::fix)
+            .collect::<Vec<_>>();
+        // Fixes that remove lines go from the bottom up, so the line numbers of
+        // the remaining fixes stay valid. Appending goes last for the same
+        // reason.
+        fixes.sort_by_key(|fix| match fix {
+            Fix::RemoveAssignment { line, .. }
+            | Fix::Rename { line, .. }
+            | Fix::SortIncludes { start: line, .. } => std::cmp::Reverse(*line),
+            Fix::AppendItems { .. } => std::cmp::Reverse(0),
+        });
+
+        let mut applied = 0;
+        for fix in fixes {
+            let changed = match fix {
+                Fix::RemoveAssignment { name, line } => self.remove_assignment_at(*line, name),
+                Fix::Rename { from, to, line } => self.rename_at(*line, from, to),
+                Fix::SortIncludes { start, end } => self.sort_includes(*start, *end),
+                Fix::AppendItems { name, items } => self.append_items(name, items)?,
+            };
+            if changed {
+                applied += 1;
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// See [`Fix::AppendItems`].
+    fn append_items(&mut self, name: &str, items: &[String]) -> Result<bool, SerializeError> {
+        let extra_name = format!("extra-{name}");
+        let existing = [name, extra_name.as_str()]
+            .into_iter()
+            .filter_map(|name| self.get(name))
+            .collect::<Vec<_>>();
+        let missing = items
+            .iter()
+            .filter(|item| {
+                !existing
+                    .iter()
+                    .any(|value| value.as_list().contains(&item.as_str()))
+            })
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(false);
+        }
+
+        let target = if self.get(name).is_some() {
+            name
+        } else {
+            &extra_name
+        };
+        let mut value = self.get(target).unwrap_or_default();
+        for item in missing {
+            value.push_item(item);
+        }
+        self.set(target, &value)?;
+
+        Ok(true)
+    }
+}
+
+/// Whether `a` and `b` are in different files.
+fn in_other_files(a: &Origin, b: &Origin) -> bool {
+    matches!(
+        (a, b),
+        (Origin::File { path: a, .. }, Origin::File { path: b, .. }) if a != b
+    )
+}
+
+/// The line of `origin`, if it is a line in a file that can be edited.
+fn file_line(origin: &Origin) -> Option<usize> {
+    match origin {
+        Origin::File { line, .. } => Some(*line),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_overrides_across_includes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let included_conf = temp_dir.path().join("included.conf");
+
+        std::fs::write(&included_conf, "cores = 4\nsandbox = true\n").unwrap();
+        std::fs::write(
+            &main_conf,
+            format!(
+                "cores = 4\nsandbox = false\ninclude {}\nsandbox = true\n",
+                included_conf.display()
+            ),
+        )
+        .unwrap();
+
+        let diagnostics = NixConfig::parse_file(&main_conf).unwrap().lint();
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            [
+                format!(
+                    "{}:1: 'cores' is set to the value it already has from {}:1 [identical-override]",
+                    included_conf.display(),
+                    main_conf.display()
+                ),
+                format!(
+                    "{}:2: 'sandbox' is set here, but overridden by {}:2 [shadowed-setting]",
+                    main_conf.display(),
+                    included_conf.display()
+                ),
+                format!(
+                    "{}:4: 'sandbox' is set to the value it already has from {}:2 [identical-override]",
+                    main_conf.display(),
+                    included_conf.display()
+                ),
+            ]
+        );
+        assert!(diagnostics[1].fix().is_none());
+
+        // Documents only report what is about their own lines.
+        let diagnostics = NixConfigDocument::parse_file(&main_conf).unwrap().lint();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), "shadowed-setting");
+        assert_eq!(
+            diagnostics[0].origin(),
+            Some(&Origin::File {
+                path: Some(main_conf),
+                line: 2
+            })
+        );
+    }
+
+    #[test]
+    fn fixes_documents_in_place() {
+        let mut doc = NixConfigDocument::parse_string(
+            [
+                "# Managed by hand",
+                "!include /etc/nix/b.conf",
+                "!include /etc/nix/a.conf",
+                "extra-binary-caches = https://example.com",
+                "accept-flake-config = true",
+                "",
+                "# again",
+                "accept-flake-config = true # oops",
+                "",
+            ]
+            .join("\n"),
+            None,
+        )
+        .unwrap();
+
+        let diagnostics = doc.lint();
+        assert_eq!(
+            diagnostics.iter().map(Diagnostic::code).collect::<Vec<_>>(),
+            [
+                "identical-override",
+                "deprecated-alias",
+                "missing-experimental-feature",
+                "unsorted-includes",
+            ]
+        );
+        assert!(!diagnostics[3].fix().unwrap().is_safe());
+
+        let safe = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.fix().is_some_and(Fix::is_safe));
+        assert_eq!(doc.apply_fixes(safe).unwrap(), 3);
+        assert_eq!(
+            doc.to_string(),
+            [
+                "# Managed by hand",
+                "!include /etc/nix/b.conf",
+                "!include /etc/nix/a.conf",
+                "extra-substituters = https://example.com",
+                "accept-flake-config = true",
+                "",
+                "# again",
+                "# oops",
+                "extra-experimental-features = flakes",
+                "",
+            ]
+            .join("\n")
+        );
+
+        assert_eq!(doc.apply_fixes(&doc.lint()).unwrap(), 1);
+        assert!(doc.to_string().starts_with(
+            "# Managed by hand\n!include /etc/nix/a.conf\n!include /etc/nix/b.conf\n"
+        ));
+        assert!(doc.lint().is_empty());
+    }
+
+    #[test]
+    fn resolves_experimental_features_from_includes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let features_conf = temp_dir.path().join("features.conf");
+
+        std::fs::write(&features_conf, "experimental-features = flakes\n").unwrap();
+        let doc = NixConfigDocument::parse_string(
+            "include features.conf\naccept-flake-config = true\n".into(),
+            Some(&main_conf),
+        )
+        .unwrap();
+        assert!(doc.lint().is_empty());
+
+        // Without the included file, it isn't known whether the feature is
+        // enabled.
+        std::fs::remove_file(&features_conf).unwrap();
+        assert!(doc.lint().is_empty());
+    }
+}
@@ -0,0 +1,423 @@
+#[cfg(feature = "toml")]
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+
+use crate::{
+    Diagnostic, NixConfig, NixConfigDocument, RuleRegistry, SettingValue, Severity, ValidationRule,
+};
+
+/// The name of the file [`LintConfig::find`] looks for.
+pub const LINT_CONFIG_FILE_NAME: &str = ".nixconf-lint.toml";
+
+/// Which lint rules run, how serious their diagnostics are, and the parameters
+/// of rules that take some, by rule [code](Diagnostic::code).
+///
+/// With the `toml` feature, it can be read from a `.nixconf-lint.toml`:
+///
+/// ```toml
+/// [rules.identical-override]
+/// enabled = false
+///
+/// [rules.deprecated-alias]
+/// severity = "error"
+///
+/// [rules.allowed-substituters]
+/// hosts = ["cache.nixos.org", "cache.example.com"]
+/// ```
+///
+/// Every key of a rule's table other than `enabled` and `severity` is a
+/// parameter. Built-in rules that take parameters, such as
+/// [`AllowedSubstituters`], only run when they are configured.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::{LintConfig, NixConfig, RuleRegistry, Severity};
+///
+/// let config = LintConfig::new()
+///     .disable("identical-override")
+///     .severity("deprecated-alias", Severity::Error)
+///     .param("allowed-substituters", "hosts", vec!["cache.nixos.org".to_owned()]);
+///
+/// let nix_conf = NixConfig::parse_string(
+///     "binary-caches = https://cache.example.com\ncores = 4\ncores = 4".into(),
+///     None,
+/// )?;
+/// let diagnostics = nix_conf.lint_with_config(&config, &RuleRegistry::new());
+///
+/// assert_eq!(
+///     diagnostics
+///         .iter()
+///         .map(|diagnostic| (diagnostic.code(), diagnostic.severity()))
+///         .collect::<Vec<_>>(),
+///     [
+///         ("deprecated-alias", Severity::Error),
+///         ("allowed-substituters", Severity::Warning),
+///     ]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, rename_all = "kebab-case")
+)]
+pub struct LintConfig {
+    pub(crate) rules: IndexMap<String, RuleConfig>,
+}
+
+/// The configuration of a single rule in a [`LintConfig`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, rename_all = "kebab-case")
+)]
+pub struct RuleConfig {
+    pub(crate) enabled: bool,
+    pub(crate) severity: Option<Severity>,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub(crate) params: IndexMap<String, RuleParam>,
+}
+
+impl Default for RuleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            severity: None,
+            params: IndexMap::new(),
+        }
+    }
+}
+
+impl RuleConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The severity the rule's diagnostics get, if it is overridden.
+    pub fn severity(&self) -> Option<Severity> {
+        self.severity
+    }
+
+    pub fn param(&self, key: &str) -> Option<&RuleParam> {
+        self.params.get(key)
+    }
+
+    pub fn params(&self) -> &IndexMap<String, RuleParam> {
+        &self.params
+    }
+}
+
+/// The value of a rule parameter in a [`LintConfig`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum RuleParam {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    List(Vec<String>),
+}
+
+impl RuleParam {
+    /// The value as a list: a single string is a list of one item.
+    pub fn as_list(&self) -> Option<Vec<&str>> {
+        match self {
+            RuleParam::String(value) => Some(vec![value.as_str()]),
+            RuleParam::List(values) => Some(values.iter().map(String::as_str).collect()),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for RuleParam {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for RuleParam {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<&str> for RuleParam {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for RuleParam {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<Vec<String>> for RuleParam {
+    fn from(values: Vec<String>) -> Self {
+        Self::List(values)
+    }
+}
+
+/// An error reading a [`LintConfig`].
+#[cfg(feature = "toml")]
+#[derive(thiserror::Error, Debug)]
+pub enum LintConfigError {
+    #[error("failed to read lint configuration '{0}': {1}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("invalid lint configuration{}: {1}", .0.as_ref().map(|path| format!(" '{}'", path.display())).unwrap_or_default())]
+    Invalid(Option<PathBuf>, #[source] toml::de::Error),
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a `.nixconf-lint.toml`.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(contents: &str) -> Result<Self, LintConfigError> {
+        toml::from_str(contents).map_err(|e| LintConfigError::Invalid(None, e))
+    }
+
+    /// Read the `.nixconf-lint.toml` at `path`.
+    #[cfg(feature = "toml")]
+    pub fn load(path: &Path) -> Result<Self, LintConfigError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| LintConfigError::Read(path.to_owned(), e))?;
+        toml::from_str(&contents).map_err(|e| LintConfigError::Invalid(Some(path.to_owned()), e))
+    }
+
+    /// Look for a [`LINT_CONFIG_FILE_NAME`] in `dir` and its ancestors, like
+    /// other linters do, and read the first one found. Returns [`None`] if
+    /// there is none.
+    #[cfg(feature = "toml")]
+    pub fn find(dir: &Path) -> Result<Option<Self>, LintConfigError> {
+        for dir in dir.ancestors() {
+            let path = dir.join(LINT_CONFIG_FILE_NAME);
+            if path.is_file() {
+                return Self::load(&path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Enable the rule `code`, which all rules are unless disabled.
+    pub fn enable(mut self, code: &str) -> Self {
+        self.rule_mut(code).enabled = true;
+        self
+    }
+
+    /// Don't report diagnostics of the rule `code`.
+    pub fn disable(mut self, code: &str) -> Self {
+        self.rule_mut(code).enabled = false;
+        self
+    }
+
+    /// Report diagnostics of the rule `code` with `severity`.
+    pub fn severity(mut self, code: &str, severity: Severity) -> Self {
+        self.rule_mut(code).severity = Some(severity);
+        self
+    }
+
+    /// Set the parameter `key` of the rule `code`.
+    pub fn param(mut self, code: &str, key: &str, value: impl Into<RuleParam>) -> Self {
+        self.rule_mut(code)
+            .params
+            .insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// The configuration of the rule `code`, if it has any.
+    pub fn rule(&self, code: &str) -> Option<&RuleConfig> {
+        self.rules.get(code)
+    }
+
+    pub fn is_enabled(&self, code: &str) -> bool {
+        self.rule(code).is_none_or(RuleConfig::is_enabled)
+    }
+
+    /// Drop the diagnostics of disabled rules, and change the severity of
+    /// those whose rule has one configured.
+    pub fn apply(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| self.is_enabled(&diagnostic.code))
+            .map(|mut diagnostic| {
+                if let Some(severity) = self.rule(&diagnostic.code).and_then(|rule| rule.severity) {
+                    diagnostic.severity = severity;
+                }
+                diagnostic
+            })
+            .collect()
+    }
+
+    /// `rules`, plus the built-in rules that take parameters and are
+    /// configured here.
+    fn rules_with_builtins(&self, rules: &RuleRegistry) -> RuleRegistry {
+        let mut rules = rules.clone();
+        if let Some(hosts) = self
+            .rule(AllowedSubstituters::NAME)
+            .and_then(|rule| rule.param("hosts"))
+            .and_then(RuleParam::as_list)
+        {
+            rules.register(AllowedSubstituters::new(hosts));
+        }
+        rules
+    }
+
+    fn rule_mut(&mut self, code: &str) -> &mut RuleConfig {
+        self.rules.entry(code.to_owned()).or_default()
+    }
+}
+
+impl NixConfig {
+    /// Like [`NixConfig::lint_with`], configured by `config`: disabled rules
+    /// don't report anything, severities are overridden, and the built-in
+    /// rules that take parameters run if configured.
+    pub fn lint_with_config(&self, config: &LintConfig, rules: &RuleRegistry) -> Vec<Diagnostic> {
+        config.apply(self.lint_with(&config.rules_with_builtins(rules)))
+    }
+}
+
+impl NixConfigDocument {
+    /// Like [`NixConfigDocument::lint_with`], configured by `config`, see
+    /// [`NixConfig::lint_with_config`].
+    pub fn lint_with_config(&self, config: &LintConfig, rules: &RuleRegistry) -> Vec<Diagnostic> {
+        config.apply(self.lint_with(&config.rules_with_builtins(rules)))
+    }
+}
+
+/// The `allowed-substituters` rule: every substituter must be hosted on one of
+/// the allowed hosts. Local stores, which have no host, are always allowed.
+///
+/// Configure it in a [`LintConfig`] with the `hosts` parameter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllowedSubstituters {
+    hosts: Vec<String>,
+}
+
+impl AllowedSubstituters {
+    pub const NAME: &'static str = "allowed-substituters";
+
+    pub fn new<I, S>(hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            hosts: hosts.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl ValidationRule for AllowedSubstituters {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn applies_to(&self, name: &str) -> bool {
+        matches!(
+            name,
+            "substituters" | "extra-substituters" | "binary-caches" | "extra-binary-caches"
+        )
+    }
+
+    fn check(&self, name: &str, value: &SettingValue, _: &NixConfig) -> Result<(), String> {
+        let disallowed = value
+            .as_list()
+            .into_iter()
+            .filter(|url| {
+                host(url).is_some_and(|host| !self.hosts.iter().any(|allowed| allowed == host))
+            })
+            .collect::<Vec<_>>();
+
+        if disallowed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{name}' uses substituters on hosts that are not allowed: {}",
+                disallowed.join(" ")
+            ))
+        }
+    }
+}
+
+/// The host of a store URL such as `https://user@cache.example.com:8080/path`,
+/// or [`None`] if it has none.
+fn host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_hosts() {
+        assert_eq!(host("https://cache.nixos.org/"), Some("cache.nixos.org"));
+        assert_eq!(
+            host("ssh-ng://builder@build.example.com:2222"),
+            Some("build.example.com")
+        );
+        assert_eq!(host("s3://bucket?region=eu-west-1"), Some("bucket"));
+        assert_eq!(host("file:///var/cache/nix"), None);
+        assert_eq!(host("daemon"), None);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn reads_toml() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let nested = temp_dir.path().join("hosts/builder");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            temp_dir.path().join(LINT_CONFIG_FILE_NAME),
+            [
+                "[rules.identical-override]",
+                "enabled = false",
+                "",
+                "[rules.allowed-substituters]",
+                "severity = \"error\"",
+                "hosts = [\"cache.nixos.org\"]",
+                "max = 3",
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let config = LintConfig::find(&nested).unwrap().unwrap();
+        assert_eq!(
+            config,
+            LintConfig::new()
+                .disable("identical-override")
+                .severity("allowed-substituters", Severity::Error)
+                .param(
+                    "allowed-substituters",
+                    "hosts",
+                    vec!["cache.nixos.org".to_owned()]
+                )
+                .param("allowed-substituters", "max", 3_i64)
+        );
+
+        assert!(matches!(
+            LintConfig::from_toml_str("[rules.cores]\nseverity = \"fatal\""),
+            Err(LintConfigError::Invalid(None, _))
+        ));
+    }
+}
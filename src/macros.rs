@@ -0,0 +1,113 @@
+/// Build a [`NixConfig`](crate::NixConfig) from `"name" = "value"` pairs of
+/// string literals, for embedding known-good defaults in a binary.
+///
+/// The pairs are checked at compile time: names must be valid
+/// [`SettingName`](crate::SettingName)s, and values can't contain a newline or a `#`, which `nix.conf` can't represent.
+/// Whitespace in values is normalized the way the parser would.
+///
+/// ```rust
+/// use nix_config_parser::nix_config;
+///
+/// let defaults = nix_config! {
+///     "experimental-features" = "flakes nix-command",
+///     "max-jobs" = "auto",
+/// };
+///
+/// assert_eq!(
+///     defaults.settings().get("experimental-features").unwrap(),
+///     "flakes nix-command"
+/// );
+/// assert_eq!(defaults.settings().len(), 2);
+/// ```
+///
+/// Malformed pairs fail to compile:
+///
+/// ```compile_fail
+/// let defaults = nix_config_parser::nix_config! {
+///     "trusted users" = "root",
+/// };
+/// ```
+///
+/// ```compile_fail
+/// let defaults = nix_config_parser::nix_config! {
+///     "cores=4" = "",
+/// };
+/// ```
+#[macro_export]
+macro_rules! nix_config {
+    ($($name:literal = $value:literal),* $(,)?) => {{
+        $(
+            const _: () = ::core::assert!(
+                $crate::__private::is_valid_name($name),
+                ::core::concat!("invalid setting name: ", $name),
+            );
+            const _: () = ::core::assert!(
+                $crate::__private::is_valid_value($value),
+                ::core::concat!("the value of ", $name, " can't contain a newline or a #"),
+            );
+        )*
+
+        #[allow(unused_mut)]
+        let mut nix_conf = $crate::NixConfig::new();
+        $(
+            nix_conf.settings_mut().insert(
+                ::std::string::String::from($name),
+                $crate::__private::value($value),
+            );
+        )*
+        nix_conf
+    }};
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::name::is_valid_name;
+    pub use crate::settings::{missing, read, write};
+    use crate::SettingValue;
+
+    pub const fn is_valid_value(value: &str) -> bool {
+        let bytes = value.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'\n' || bytes[i] == b'#' {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// `value` with its tokens joined by single spaces, as the parser would
+    /// produce.
+    pub fn value(value: &str) -> SettingValue {
+        value
+            .split([' ', '\t', '\r'])
+            .filter(|token| !token.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::NixConfig;
+
+    #[test]
+    fn matches_the_parser() {
+        let from_macro = nix_config! {
+            "substituters" = "  https://a.example.com\thttps://b.example.com ",
+            "warn-dirty" = "false",
+            "post-build-hook" = "",
+        };
+        let parsed = NixConfig::parse_string(
+            "substituters =   https://a.example.com\thttps://b.example.com \nwarn-dirty = false\npost-build-hook ="
+                .into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(from_macro, parsed);
+        assert!(nix_config! {}.settings().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::serialize::render_value;
+use crate::{NixConfig, NixConfigDocument, SerializeError, SerializeOptions};
+
+/// A tool that generates `nix.conf` files, see [`NixConfig::managed_by`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ConfigManager {
+    /// The [Determinate Nix Installer](https://github.com/DeterminateSystems/nix-installer).
+    NixInstaller,
+    /// The `nix.*` options of a NixOS configuration.
+    NixOS,
+    /// The `nix.*` options of a nix-darwin configuration.
+    NixDarwin,
+}
+
+impl fmt::Display for ConfigManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigManager::NixInstaller => "nix-installer",
+            ConfigManager::NixOS => "NixOS",
+            ConfigManager::NixDarwin => "nix-darwin",
+        })
+    }
+}
+
+/// The settings the Determinate Nix Installer writes into `/etc/nix/nix.conf`
+/// itself. Anything else was added later.
+const INSTALLER_SETTINGS: &[&str] = &[
+    "auto-optimise-store",
+    "bash-prompt-prefix",
+    "build-users-group",
+    "experimental-features",
+    "extra-experimental-features",
+    "extra-nix-path",
+    "extra-trusted-substituters",
+    "extra-trusted-public-keys",
+    "max-jobs",
+    "upgrade-nix-store-path-url",
+    "always-allow-substitutes",
+    "netrc-file",
+    "ssl-cert-file",
+];
+
+impl NixConfig {
+    /// Guess which tool generated this config, from the settings only that
+    /// tool writes. Only configs written by the Determinate Nix Installer can
+    /// be recognized this way; [`NixConfigDocument::managed_by`] also looks at
+    /// the header comment that each of the tools writes.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{ConfigManager, NixConfig};
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     r"build-users-group = nixbld
+    ///     bash-prompt-prefix = (nix:$name)\040
+    ///     extra-nix-path = nixpkgs=flake:nixpkgs"
+    ///         .into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(nix_conf.managed_by(), Some(ConfigManager::NixInstaller));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn managed_by(&self) -> Option<ConfigManager> {
+        let settings = self.settings();
+        let installer_prompt = settings
+            .get("bash-prompt-prefix")
+            .is_some_and(|prefix| prefix == r"(nix:$name)\040");
+        let installer_nix_path = settings
+            .get("extra-nix-path")
+            .is_some_and(|path| path.as_list().contains(&"nixpkgs=flake:nixpkgs"));
+
+        (installer_prompt && installer_nix_path).then_some(ConfigManager::NixInstaller)
+    }
+}
+
+impl NixConfigDocument {
+    /// Guess which tool generated this document from its header (see
+    /// [`NixConfigDocument::leading_comments`]), falling back to
+    /// [`NixConfig::managed_by`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{ConfigManager, NixConfigDocument};
+    ///
+    /// let contents = [
+    ///     "# WARNING: this file is generated from the nix.* options in",
+    ///     "# your NixOS configuration, typically",
+    ///     "# /etc/nixos/configuration.nix.  Do not edit it!",
+    ///     "build-users-group = nixbld",
+    /// ];
+    /// let doc = NixConfigDocument::parse_string(contents.join("\n"), None)?;
+    ///
+    /// assert_eq!(doc.managed_by(), Some(ConfigManager::NixOS));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn managed_by(&self) -> Option<ConfigManager> {
+        let header = self.leading_comments().join("\n");
+
+        if header.contains("nix-installer") {
+            Some(ConfigManager::NixInstaller)
+        } else if header.contains("NixOS configuration") {
+            Some(ConfigManager::NixOS)
+        } else if header.contains("nix-darwin configuration") {
+            Some(ConfigManager::NixDarwin)
+        } else {
+            self.to_config().managed_by()
+        }
+    }
+
+    /// Upgrade a `nix.conf` written by an older Determinate Nix Installer to
+    /// the current layout, where the installer owns `nix.conf` and local
+    /// changes live in a file it includes: every setting the installer didn't
+    /// write is moved, with its comment, into the returned document, and an
+    /// `!include` of `custom_path` is added to this one.
+    ///
+    /// `custom_path` is written as given, so a relative path is resolved
+    /// against the directory of this document. If this document already
+    /// includes it, no second include is added.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfigDocument;
+    ///
+    /// let mut doc = NixConfigDocument::parse_string(
+    ///     [
+    ///         "# Generated by https://github.com/DeterminateSystems/nix-installer.",
+    ///         "build-users-group = nixbld",
+    ///         "# Our cache",
+    ///         "extra-substituters = https://example.cachix.org",
+    ///     ]
+    ///     .join("\n"),
+    ///     None,
+    /// )?;
+    ///
+    /// let custom = doc.migrate_installer_config("nix.custom.conf")?;
+    ///
+    /// assert_eq!(
+    ///     doc.to_string(),
+    ///     [
+    ///         "# Generated by https://github.com/DeterminateSystems/nix-installer.",
+    ///         "build-users-group = nixbld",
+    ///         "!include nix.custom.conf\n",
+    ///     ]
+    ///     .join("\n")
+    /// );
+    /// assert_eq!(
+    ///     custom.to_string(),
+    ///     "# Our cache\nextra-substituters = https://example.cachix.org\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn migrate_installer_config(
+        &mut self,
+        custom_path: &str,
+    ) -> Result<NixConfigDocument, SerializeError> {
+        let mut custom =
+            NixConfigDocument::parse_string_with_options(String::new(), None, self.options())
+                .expect("an empty document always parses");
+
+        let moved = self
+            .to_config()
+            .into_settings()
+            .into_keys()
+            .filter(|name| !INSTALLER_SETTINGS.contains(&name.as_str()))
+            .collect::<Vec<_>>();
+        for name in moved {
+            let Some((value, comment)) = self.take(&name) else {
+                continue;
+            };
+            match comment {
+                Some(comment) => custom.set_with_comment(&name, &value, &comment)?,
+                None => custom.set(&name, &value)?,
+            }
+        }
+
+        if !self
+            .includes()
+            .iter()
+            .any(|include| include.path() == Path::new(custom_path))
+        {
+            let path = render_value(
+                "!include",
+                custom_path,
+                &SerializeOptions::new()
+                    .quoting(self.options().quoting)
+                    .hash_escapes(self.options().hash_escapes),
+            )?;
+            self.push_line(format!("!include {path}"));
+        }
+
+        Ok(custom)
+    }
+}
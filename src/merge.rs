@@ -0,0 +1,270 @@
+use indexmap::{IndexMap, IndexSet};
+
+use crate::{NixConfig, SettingInfo, SettingValue};
+
+/// How [`NixConfig::merge`] combines a setting that is set in both configs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum MergeStrategy {
+    /// The merged-in value overwrites the existing one.
+    #[default]
+    Replace,
+    /// The items of both values are combined, without duplicates, and
+    /// `<name>` and `extra-<name>` are treated as one list.
+    ListUnion,
+}
+
+/// The [`MergeStrategy`] to use for each setting in [`NixConfig::merge`].
+/// Settings without one are [replaced](MergeStrategy::Replace).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct MergeStrategies {
+    strategies: IndexMap<String, MergeStrategy>,
+}
+
+impl MergeStrategies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Union the items of each of the list settings in `names`, and replace
+    /// everything else.
+    pub fn list_union<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        names.into_iter().fold(Self::new(), |strategies, name| {
+            strategies.strategy(name, MergeStrategy::ListUnion)
+        })
+    }
+
+    /// Use `strategy` for the setting `name` (and `extra-<name>`).
+    pub fn strategy(mut self, name: impl Into<String>, strategy: MergeStrategy) -> Self {
+        self.strategies.insert(name.into(), strategy);
+        self
+    }
+
+    /// The strategy for the setting `name`, if one was given for it or the
+    /// setting it is the `extra-` variant of.
+    pub fn get(&self, name: &str) -> Option<MergeStrategy> {
+        let base = name.strip_prefix("extra-").unwrap_or(name);
+        self.strategies
+            .get(name)
+            .or_else(|| self.strategies.get(base))
+            .copied()
+    }
+}
+
+impl NixConfig {
+    /// Merge the settings of `other` into this config, as if `other` came after
+    /// it, using `strategies` to decide how settings set in both are combined.
+    ///
+    /// For a setting merged with [`MergeStrategy::ListUnion`], if either config
+    /// sets `<name>`, the result is `<name>` with the items of `<name>` and
+    /// `extra-<name>` of both configs, in order and without duplicates.
+    /// Otherwise, the `extra-<name>` values are combined the same way, so that
+    /// Nix's default for `<name>` is kept.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{MergeStrategies, NixConfig};
+    ///
+    /// let mut nix_conf = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org\ncores = 4".into(),
+    ///     None,
+    /// )?;
+    /// let other = NixConfig::parse_string(
+    ///     "substituters = https://example.com\nextra-substituters = https://cache.nixos.org\ncores = 8"
+    ///         .into(),
+    ///     None,
+    /// )?;
+    ///
+    /// nix_conf.merge(
+    ///     &other,
+    ///     &MergeStrategies::list_union(["substituters", "trusted-public-keys"]),
+    /// );
+    ///
+    /// assert_eq!(
+    ///     nix_conf.settings().get("substituters").unwrap(),
+    ///     "https://cache.nixos.org https://example.com"
+    /// );
+    /// assert!(nix_conf.settings().get("extra-substituters").is_none());
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "8");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge(&mut self, other: &NixConfig, strategies: &MergeStrategies) {
+        self.merge_with(other, |name| strategies.get(name).unwrap_or_default());
+    }
+
+    /// Merge the settings of `other` into this config like [`NixConfig::merge`],
+    /// choosing the strategy from the registry: list settings are
+    /// [unioned](MergeStrategy::ListUnion), everything else, including unknown
+    /// settings, is [replaced](MergeStrategy::Replace).
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let mut nix_conf = NixConfig::parse_string(
+    ///     "trusted-users = root\nexperimental-features = nix-command\ncores = 4".into(),
+    ///     None,
+    /// )?;
+    /// let other = NixConfig::parse_string(
+    ///     "trusted-users = @wheel\nextra-experimental-features = flakes\ncores = 8".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// nix_conf.merge_smart(&other);
+    ///
+    /// assert_eq!(nix_conf.settings().get("trusted-users").unwrap(), "root @wheel");
+    /// assert_eq!(
+    ///     nix_conf.settings().get("experimental-features").unwrap(),
+    ///     "nix-command flakes"
+    /// );
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "8");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge_smart(&mut self, other: &NixConfig) {
+        self.merge_with(other, |name| match SettingInfo::lookup(name) {
+            Some(info) if info.setting_type().is_list() => MergeStrategy::ListUnion,
+            _ => MergeStrategy::Replace,
+        });
+    }
+
+    pub(crate) fn merge_with(
+        &mut self,
+        other: &NixConfig,
+        strategy: impl Fn(&str) -> MergeStrategy,
+    ) {
+        for (name, value) in other.settings() {
+            if strategy(name) == MergeStrategy::ListUnion {
+                continue;
+            }
+
+            self.settings_mut().insert(name.clone(), value.clone());
+            match other.origin(name) {
+                Some(origin) => self.set_origin(name, origin.clone()),
+                None => {
+                    self.origins.shift_remove(name);
+                }
+            }
+        }
+
+        let unions = other
+            .settings()
+            .keys()
+            .filter(|name| strategy(name) == MergeStrategy::ListUnion)
+            .map(|name| name.strip_prefix("extra-").unwrap_or(name).to_owned())
+            .collect::<IndexSet<_>>();
+
+        for base in unions {
+            self.union_list(other, &base);
+        }
+    }
+
+    fn union_list(&mut self, other: &NixConfig, base: &str) {
+        let extra = format!("extra-{base}");
+        let has_base = self.settings().contains_key(base) || other.settings().contains_key(base);
+
+        let this = &*self;
+        let sources: &[(&NixConfig, &str)] = if has_base {
+            &[(this, base), (this, &extra), (other, base), (other, &extra)]
+        } else {
+            &[(this, &extra), (other, &extra)]
+        };
+
+        let mut merged = SettingValue::default();
+        let mut origin = None;
+        for (config, name) in sources {
+            if let Some(value) = config.settings().get(*name) {
+                for item in value.as_list() {
+                    merged.push_item(item);
+                }
+                origin = config.origin(name).cloned().or(origin);
+            }
+        }
+        merged.dedup();
+
+        let target = if has_base { base } else { extra.as_str() };
+        self.settings.shift_remove(&extra);
+        self.origins.shift_remove(&extra);
+        self.settings.insert(target.to_owned(), merged);
+        if let Some(origin) = origin {
+            self.origins.insert(target.to_owned(), origin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_merge_unions_lists() {
+        let mut nix_conf =
+            NixConfig::parse_string("trusted-public-keys = a:1".into(), None).unwrap();
+        let other = NixConfig::parse_string(
+            "extra-trusted-public-keys = a:1 b:2\nsandbox = false".into(),
+            None,
+        )
+        .unwrap();
+
+        nix_conf.merge_smart(&other);
+
+        assert_eq!(
+            nix_conf.settings().get("trusted-public-keys").unwrap(),
+            "a:1 b:2"
+        );
+        assert_eq!(nix_conf.settings().get("sandbox").unwrap(), "false");
+        assert_eq!(nix_conf.settings().len(), 2);
+    }
+
+    #[test]
+    fn keeps_extra_lists_without_base() {
+        let mut nix_conf = NixConfig::parse_string(
+            "extra-trusted-public-keys = a:1 b:2\nextra-substituters = https://a.example.com"
+                .into(),
+            None,
+        )
+        .unwrap();
+        let other = NixConfig::parse_string(
+            "extra-trusted-public-keys = b:2 c:3\nextra-substituters = https://b.example.com"
+                .into(),
+            None,
+        )
+        .unwrap();
+
+        nix_conf.merge(
+            &other,
+            &MergeStrategies::list_union(["trusted-public-keys"]),
+        );
+
+        assert_eq!(
+            nix_conf
+                .settings()
+                .get("extra-trusted-public-keys")
+                .unwrap(),
+            "a:1 b:2 c:3"
+        );
+        assert!(nix_conf.settings().get("trusted-public-keys").is_none());
+        assert_eq!(
+            nix_conf.settings().get("extra-substituters").unwrap(),
+            "https://b.example.com"
+        );
+    }
+}
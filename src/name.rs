@@ -0,0 +1,192 @@
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+use thiserror::Error;
+
+/// The name of a Nix setting that is guaranteed to be representable in a
+/// `nix.conf`: it is non-empty, contains no whitespace, `#` or `=`, and is not one
+/// of the `include`/`!include` directives.
+///
+/// ```rust
+/// use nix_config_parser::SettingName;
+///
+/// assert!(SettingName::try_from("experimental-features").is_ok());
+/// assert!(SettingName::try_from("experimental features").is_err());
+/// ```
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct SettingName(String);
+
+impl SettingName {
+    pub fn new(name: impl Into<String>) -> Result<Self, InvalidSettingName> {
+        let name = name.into();
+
+        if Self::is_valid(&name) {
+            Ok(Self(name))
+        } else {
+            Err(InvalidSettingName(name))
+        }
+    }
+
+    /// Wrap `name` without validating it. Prefer [`SettingName::new`]; this is
+    /// an escape hatch for callers that really need to store an arbitrary key.
+    pub fn new_unchecked(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// Whether `name` would be accepted by [`SettingName::new`].
+    pub fn is_valid(name: &str) -> bool {
+        is_valid_name(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+/// See [`SettingName::is_valid`]. This is a `const fn`, so that
+/// [`nix_config!`](crate::nix_config) can check names at compile time.
+pub const fn is_valid_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    if bytes.is_empty()
+        || matches!(
+            bytes,
+            [b'i', b'n', b'c', b'l', b'u', b'd', b'e']
+                | [b'!', b'i', b'n', b'c', b'l', b'u', b'd', b'e']
+        )
+    {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let (c, len) = decode_char(bytes, i);
+        if c == '#' || c == '=' || c.is_whitespace() {
+            return false;
+        }
+        i += len;
+    }
+    true
+}
+
+/// The character starting at `bytes[i]` of valid UTF-8, and its length.
+const fn decode_char(bytes: &[u8], i: usize) -> (char, usize) {
+    let first = bytes[i] as u32;
+    let (mut code, len) = match first {
+        0x00..=0x7f => (first, 1),
+        0x80..=0xdf => (first & 0x1f, 2),
+        0xe0..=0xef => (first & 0x0f, 3),
+        _ => (first & 0x07, 4),
+    };
+
+    let mut j = 1;
+    while j < len {
+        code = (code << 6) | (bytes[i + j] as u32 & 0x3f);
+        j += 1;
+    }
+
+    match char::from_u32(code) {
+        Some(c) => (c, len),
+        None => (char::REPLACEMENT_CHARACTER, len),
+    }
+}
+
+impl Deref for SettingName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SettingName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for SettingName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SettingName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<&str> for SettingName {
+    type Error = InvalidSettingName;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<String> for SettingName {
+    type Error = InvalidSettingName;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl From<SettingName> for String {
+    fn from(name: SettingName) -> Self {
+        name.0
+    }
+}
+
+/// The error returned when a [`SettingName`] would not be representable in a
+/// `nix.conf`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("'{0}' is not a valid setting name")]
+pub struct InvalidSettingName(pub String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_names() {
+        for valid in [
+            "cores",
+            "extra-substituters",
+            "ssl-cert-file",
+            "plugin-café",
+        ] {
+            assert!(SettingName::try_from(valid).is_ok(), "'{valid}' is valid");
+        }
+
+        for invalid in [
+            "",
+            "has space",
+            "tab\there",
+            "has#hash",
+            "no\u{a0}break",
+            "a=b",
+            "=",
+            "include",
+            "!include",
+        ] {
+            assert_eq!(
+                SettingName::try_from(invalid),
+                Err(InvalidSettingName(invalid.into())),
+            );
+        }
+
+        assert_eq!(
+            SettingName::new_unchecked("has space").as_str(),
+            "has space"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! A parser for the netrc files `netrc-file` points at, which Nix (through
+//! curl) reads the credentials for substituters and other HTTP servers from.
+//!
+//! A netrc file is a sequence of whitespace-separated tokens: each
+//! `machine <host>` (or `default`, which matches any host) is followed by its
+//! `login <name>`, `password <password>` and `account <account>`. Values may
+//! be quoted with `"` to contain whitespace, lines starting with `#` are
+//! comments, and `macdef` macros (used by `ftp` only) are skipped.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::{paths, NixConfig};
+
+/// The entries of a netrc file, in order.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::netrc::Netrc;
+///
+/// let netrc = Netrc::parse(
+///     r#"# Credentials for the private cache
+///     machine cache.example.org login alice password "correct horse"
+///     default login anonymous"#,
+/// )?;
+///
+/// let machine = netrc.find("cache.example.org").unwrap();
+/// assert_eq!(machine.login(), Some("alice"));
+/// assert_eq!(machine.password(), Some("correct horse"));
+/// assert_eq!(netrc.find("example.com").unwrap().login(), Some("anonymous"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Netrc {
+    machines: Vec<Machine>,
+}
+
+/// A `machine` or `default` entry of a [`Netrc`].
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Machine {
+    host: Option<String>,
+    login: Option<String>,
+    password: Option<String>,
+    account: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum NetrcError {
+    #[error("failed to read contents of '{0}': {1}")]
+    FailedToReadFile(PathBuf, #[source] std::io::Error),
+    #[error("unexpected '{0}' on line {1}")]
+    UnexpectedToken(String, usize),
+    #[error("'{0}' on line {1} is missing its value")]
+    MissingValue(String, usize),
+    #[error("'{0}' on line {1} does not follow a 'machine' or 'default'")]
+    OutsideMachine(String, usize),
+    #[error("unterminated quote on line {0}")]
+    UnterminatedQuote(usize),
+}
+
+impl Netrc {
+    pub fn parse(contents: &str) -> Result<Self, NetrcError> {
+        let mut tokens = tokenize(contents)?.into_iter();
+        let mut machines = Vec::<Machine>::new();
+
+        while let Some((token, line)) = tokens.next() {
+            let mut value = |keyword: &str| {
+                tokens
+                    .next()
+                    .map(|(value, _)| value)
+                    .ok_or_else(|| NetrcError::MissingValue(keyword.to_owned(), line))
+            };
+
+            match token.as_str() {
+                "machine" => machines.push(Machine {
+                    host: Some(value("machine")?),
+                    ..Machine::default()
+                }),
+                "default" => machines.push(Machine::default()),
+                "macdef" => drop(value("macdef")?),
+                "login" | "password" | "account" => {
+                    let value = Some(value(&token)?);
+                    let Some(machine) = machines.last_mut() else {
+                        return Err(NetrcError::OutsideMachine(token, line));
+                    };
+                    match token.as_str() {
+                        "login" => machine.login = value,
+                        "password" => machine.password = value,
+                        _ => machine.account = value,
+                    }
+                }
+                _ => return Err(NetrcError::UnexpectedToken(token, line)),
+            }
+        }
+
+        Ok(Self { machines })
+    }
+
+    pub fn parse_file(path: &Path) -> Result<Self, NetrcError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| NetrcError::FailedToReadFile(path.to_owned(), e))?;
+
+        Self::parse(&contents)
+    }
+
+    pub fn machines(&self) -> &[Machine] {
+        &self.machines
+    }
+
+    /// The entry curl uses for `host`: the first `machine` entry for it, or
+    /// else the first `default` entry.
+    pub fn find(&self, host: &str) -> Option<&Machine> {
+        self.machines
+            .iter()
+            .find(|machine| machine.host() == Some(host))
+            .or_else(|| self.machines.iter().find(|machine| machine.host.is_none()))
+    }
+}
+
+impl Machine {
+    /// The host of a `machine` entry, or [`None`] for the `default` entry.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    pub fn login(&self) -> Option<&str> {
+        self.login.as_deref()
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+}
+
+impl fmt::Debug for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Machine")
+            .field("host", &self.host)
+            .field("login", &self.login)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("account", &self.account)
+            .finish()
+    }
+}
+
+impl NixConfig {
+    /// Parse the netrc file Nix reads credentials from: `netrc-file`, or
+    /// `netrc` next to the system-wide `nix.conf` if it isn't set.
+    pub fn read_netrc(&self) -> Result<Netrc, NetrcError> {
+        let path = match self.settings().get("netrc-file") {
+            Some(path) => PathBuf::from(path.as_str()),
+            None => paths::system_config_file().with_file_name("netrc"),
+        };
+
+        Netrc::parse_file(&path)
+    }
+}
+
+/// Split `contents` into tokens and the lines they are on, skipping comments
+/// and the bodies of macros, which end at the next empty line.
+fn tokenize(contents: &str) -> Result<Vec<(String, usize)>, NetrcError> {
+    let mut tokens = Vec::new();
+    let mut in_macro = false;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        if in_macro {
+            in_macro = !line.trim().is_empty();
+            continue;
+        }
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let mut chars = line.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let Some(first) = chars.next() else {
+                break;
+            };
+
+            let mut token = String::new();
+            if first == '"' {
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => token.extend(chars.next()),
+                        Some(c) => token.push(c),
+                        None => return Err(NetrcError::UnterminatedQuote(line_number)),
+                    }
+                }
+            } else {
+                token.push(first);
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    token.push(c);
+                }
+                in_macro |= token == "macdef";
+            }
+
+            tokens.push((token, line_number));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_macros_and_reports_lines() {
+        let netrc = Netrc::parse(
+            "machine a.example.org\n  login a\nmacdef init\ncd /pub\nbinary\n\nmachine b.example.org password \"p\\\"w\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            netrc
+                .machines()
+                .iter()
+                .map(|machine| (machine.host(), machine.login(), machine.password()))
+                .collect::<Vec<_>>(),
+            [
+                (Some("a.example.org"), Some("a"), None),
+                (Some("b.example.org"), None, Some("p\"w"))
+            ]
+        );
+        assert!(!format!("{:?}", netrc.machines()[1]).contains("p\\\"w"));
+        assert!(netrc.find("c.example.org").is_none());
+
+        assert_eq!(
+            Netrc::parse("login alice").unwrap_err().to_string(),
+            "'login' on line 1 does not follow a 'machine' or 'default'"
+        );
+        assert_eq!(
+            Netrc::parse("machine a\n\npasword x")
+                .unwrap_err()
+                .to_string(),
+            "unexpected 'pasword' on line 3"
+        );
+    }
+}
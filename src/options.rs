@@ -0,0 +1,520 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::directive::DirectiveHandlers;
+#[cfg(feature = "http")]
+use crate::RemoteIncludes;
+#[cfg(feature = "signatures")]
+use crate::TrustedKeys;
+use crate::{CustomDirective, Dialect, ParseError};
+
+/// Options that influence how a `nix.conf` is parsed.
+///
+/// The default options parse exactly like Nix does. Everything else is an
+/// opt-in extension that Nix itself does not understand, so files relying on
+/// them should only be consumed by tools built on this crate.
+///
+/// With the `serde` feature, the options can be embedded in other configuration
+/// formats, with any missing field taking its default value. Handlers
+/// registered with [`ParseOptions::on_directive`],
+/// [`ParseOptions::on_include_error`], [`ParseOptions::remote_includes`] and
+/// [`ParseOptions::verify_includes`] are not serialized.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, rename_all = "kebab-case")
+)]
+pub struct ParseOptions {
+    pub(crate) quoting: bool,
+    pub(crate) hash_escapes: bool,
+    pub(crate) includes: IncludeMode,
+    pub(crate) dialect: Dialect,
+    pub(crate) unset_directives: bool,
+    pub(crate) directories: bool,
+    pub(crate) line_continuations: bool,
+    pub(crate) compact_assignments: bool,
+    pub(crate) strict_settings: bool,
+    pub(crate) invalid_utf8: InvalidUtf8,
+    pub(crate) unix_paths: bool,
+    pub(crate) absolute_origins: bool,
+    pub(crate) lenient_includes: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) directives: DirectiveHandlers,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) include_errors: OnIncludeError,
+    #[cfg(feature = "http")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) remote_includes: Option<RemoteIncludes>,
+    #[cfg(feature = "signatures")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) trusted_keys: Option<TrustedKeys>,
+}
+
+/// What the parser does with `include` and `!include` directives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum IncludeMode {
+    /// Read the included file and parse its settings in place, like Nix does.
+    #[default]
+    Inline,
+    /// Don't read the included file, but remember the directive (see
+    /// [`NixConfig::includes`](crate::NixConfig::includes)) so it can be
+    /// written back out.
+    Keep,
+    /// Refuse any include with [`ParseError::IncludeNotAllowed`](crate::ParseError::IncludeNotAllowed),
+    /// for example when parsing untrusted input that must not read other files.
+    Error,
+}
+
+type IncludeErrorHandler = dyn Fn(&Path, &ParseError) -> bool + Send + Sync;
+
+/// What the parser does when an `include` can't be followed, for example
+/// because the file isn't readable, see [`ParseOptions::on_include_error`].
+/// `!include`s of files that can't be read are always skipped.
+#[derive(Clone, Default)]
+pub enum OnIncludeError {
+    /// Fail the parse, with
+    /// [`ParseError::IncludedFileNotFound`](crate::ParseError::IncludedFileNotFound),
+    /// [`ParseError::IncludedFileUnreadable`](crate::ParseError::IncludedFileUnreadable)
+    /// or, for errors inside the included file,
+    /// [`ParseError::InIncludedFile`](crate::ParseError::InIncludedFile), like
+    /// Nix does.
+    #[default]
+    Fail,
+    /// Skip the include like an `!include`, recording it in
+    /// [`NixConfig::skipped_includes`](crate::NixConfig::skipped_includes).
+    Skip,
+    /// Ask the handler, which is given the included file and the error, and
+    /// skips the include if it returns `true`. Handlers are compared by
+    /// identity.
+    Callback(Arc<IncludeErrorHandler>),
+}
+
+impl OnIncludeError {
+    /// [`OnIncludeError::Callback`] with `handler`.
+    pub fn callback<F>(handler: F) -> Self
+    where
+        F: Fn(&Path, &ParseError) -> bool + Send + Sync + 'static,
+    {
+        Self::Callback(Arc::new(handler))
+    }
+
+    /// Whether to skip the include of `path` that failed with `error`.
+    pub(crate) fn skips(&self, path: &Path, error: &ParseError) -> bool {
+        match self {
+            OnIncludeError::Fail => false,
+            OnIncludeError::Skip => true,
+            OnIncludeError::Callback(handler) => handler(path, error),
+        }
+    }
+}
+
+impl fmt::Debug for OnIncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnIncludeError::Fail => f.write_str("Fail"),
+            OnIncludeError::Skip => f.write_str("Skip"),
+            OnIncludeError::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+impl PartialEq for OnIncludeError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (OnIncludeError::Fail, OnIncludeError::Fail)
+            | (OnIncludeError::Skip, OnIncludeError::Skip) => true,
+            (OnIncludeError::Callback(f), OnIncludeError::Callback(g)) => Arc::ptr_eq(f, g),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for OnIncludeError {}
+
+/// What [`NixConfig::parse_bytes_with_options`](crate::NixConfig::parse_bytes_with_options)
+/// does with bytes that aren't valid in the encoding of the input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum InvalidUtf8 {
+    /// Fail with [`ParseError::InvalidEncoding`](crate::ParseError::InvalidEncoding),
+    /// which has the offset of the first invalid byte.
+    #[default]
+    Error,
+    /// Replace them with `U+FFFD REPLACEMENT CHARACTER`.
+    Replace,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse exactly like Nix does, accepting the same files and producing
+    /// the same settings. This is the same as [`ParseOptions::default`].
+    pub fn nix_compatible() -> Self {
+        Self::default()
+    }
+
+    /// Like [`ParseOptions::nix_compatible`], but unknown settings, which Nix
+    /// ignores with a warning, and values Nix would reject or misinterpret
+    /// fail the parse, see [`ParseOptions::strict_settings`].
+    ///
+    /// ```rust
+    /// use nix_config_parser::{NixConfig, ParseError, ParseOptions};
+    ///
+    /// let parse = |contents: &str, options| {
+    ///     NixConfig::parse_string_with_options(contents.into(), None, &options)
+    /// };
+    ///
+    /// assert!(parse("build-corez = 4", ParseOptions::nix_compatible()).is_ok());
+    /// assert!(matches!(
+    ///     parse("build-corez = 4", ParseOptions::strict()),
+    ///     Err(ParseError::UnknownSetting(name, _)) if name == "build-corez"
+    /// ));
+    /// assert!(matches!(
+    ///     parse("cores = four", ParseOptions::strict()),
+    ///     Err(ParseError::InvalidValue(problem)) if problem.name() == "cores"
+    /// ));
+    /// ```
+    pub fn strict() -> Self {
+        Self::default().strict_settings(true)
+    }
+
+    /// Accept the syntax people tend to expect from other config formats:
+    /// [quoting](ParseOptions::quoting),
+    /// [line continuations](ParseOptions::line_continuations) and
+    /// [`name=value`](ParseOptions::compact_assignments), and make the best
+    /// of [malformed includes](ParseOptions::lenient_includes). Files relying
+    /// on these can't be read by Nix itself.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, ParseOptions};
+    ///
+    /// let nix_conf = NixConfig::parse_string_with_options(
+    ///     "cores=4\nnetrc-file = \"/Users/First Last/.netrc\"\nsubstituters = \\\n  https://cache.nixos.org \\\n  https://cache.example.com".into(),
+    ///     None,
+    ///     &ParseOptions::lenient(),
+    /// )?;
+    ///
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "4");
+    /// assert_eq!(nix_conf.settings().get("netrc-file").unwrap(), "/Users/First Last/.netrc");
+    /// assert_eq!(
+    ///     nix_conf.settings().get("substituters").unwrap(),
+    ///     "https://cache.nixos.org https://cache.example.com"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lenient() -> Self {
+        Self::default()
+            .quoting(true)
+            .line_continuations(true)
+            .compact_assignments(true)
+            .lenient_includes(true)
+    }
+
+    /// Recognize `"..."` quoting in setting values and `include` paths, so that
+    /// values such as `/Users/First Last/.netrc` survive tokenization.
+    ///
+    /// Inside quotes, `\"` produces a literal `"` and `\\` produces a literal
+    /// `\`; any other backslash is kept as-is. A quoted token must start at a
+    /// token boundary and its closing quote must be followed by whitespace or
+    /// the end of the line. Setting names and the `=` separator can't be
+    /// quoted. Comments are stripped before quotes are considered, so a `#`
+    /// starts a comment even inside quotes, unless escaped (see
+    /// [`ParseOptions::hash_escapes`]).
+    pub fn quoting(mut self, enabled: bool) -> Self {
+        self.quoting = enabled;
+        self
+    }
+
+    /// Treat `\#` as a literal `#` instead of the start of a comment, so that
+    /// values such as substituter URLs with fragments can be expressed.
+    ///
+    /// Only the backslash directly in front of the `#` is consumed, both inside
+    /// and outside of quotes.
+    pub fn hash_escapes(mut self, enabled: bool) -> Self {
+        self.hash_escapes = enabled;
+        self
+    }
+
+    /// How to handle `include` and `!include` directives. Defaults to
+    /// [`IncludeMode::Inline`].
+    pub fn includes(mut self, mode: IncludeMode) -> Self {
+        self.includes = mode;
+        self
+    }
+
+    /// Recognize `!unset <name>` lines, which remove a setting assigned earlier
+    /// (including in an earlier layer, see
+    /// [`NixConfig::apply_string`](crate::NixConfig::apply_string)). This
+    /// allows a machine-specific file to drop a fleet-wide setting instead of
+    /// overriding it.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, ParseOptions};
+    ///
+    /// let options = ParseOptions::new().unset_directives(true);
+    ///
+    /// let mut nix_conf = NixConfig::parse_string("cores = 4\nmax-jobs = 2".into(), None)?;
+    /// nix_conf.apply_string_with_options("!unset max-jobs", None, &options)?;
+    ///
+    /// assert_eq!(nix_conf.settings().len(), 1);
+    /// assert!(nix_conf.settings().get("max-jobs").is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unset_directives(mut self, enabled: bool) -> Self {
+        self.unset_directives = enabled;
+        self
+    }
+
+    /// When [`NixConfig::parse_file_with_options`](crate::NixConfig::parse_file_with_options)
+    /// is given a directory, parse every `*.conf` file in it in lexicographic
+    /// order, like a `conf.d` directory, instead of returning
+    /// [`ParseError::IsADirectory`](crate::ParseError::IsADirectory). Later
+    /// files take precedence.
+    pub fn directories(mut self, enabled: bool) -> Self {
+        self.directories = enabled;
+        self
+    }
+
+    /// Join a line ending with a `\` with the next one, so that long values
+    /// such as lists of substituters can be spread over several lines. The
+    /// `\` is replaced by a space. A `\` at the end of a comment is part of
+    /// the comment and doesn't continue it. Settings spanning several lines
+    /// have the number of their first line as [origin](crate::Origin).
+    pub fn line_continuations(mut self, enabled: bool) -> Self {
+        self.line_continuations = enabled;
+        self
+    }
+
+    /// Accept assignments without whitespace around the `=`, such as
+    /// `cores=4`, which Nix rejects. A [quoted](ParseOptions::quoting) value
+    /// must still be separated from the `=` by whitespace.
+    pub fn compact_assignments(mut self, enabled: bool) -> Self {
+        self.compact_assignments = enabled;
+        self
+    }
+
+    /// Fail the parse on unknown settings (of the
+    /// [dialect](ParseOptions::dialect)) with
+    /// [`ParseError::UnknownSetting`](crate::ParseError::UnknownSetting), and
+    /// values of the wrong type (see
+    /// [`NixConfig::validate_values`](crate::NixConfig::validate_values))
+    /// with [`ParseError::InvalidValue`](crate::ParseError::InvalidValue).
+    /// Every assignment is checked, including those that are overridden
+    /// later.
+    pub fn strict_settings(mut self, enabled: bool) -> Self {
+        self.strict_settings = enabled;
+        self
+    }
+
+    /// Resolve `include` paths with Unix semantics regardless of the host:
+    /// only paths starting with `/` are absolute, and relative paths are
+    /// joined to the directory of the including file with `/`. Use this when
+    /// working with a Linux or macOS `nix.conf` on Windows, where a path like
+    /// `/etc/nix/extra.conf` is otherwise relative to the current drive, and
+    /// `C:` and `\` have special meaning.
+    ///
+    /// Path-valued settings such as `netrc-file` are always checked with Unix
+    /// semantics (see [`NixConfig::validate_values`](crate::NixConfig::validate_values)).
+    pub fn unix_paths(mut self, enabled: bool) -> Self {
+        self.unix_paths = enabled;
+        self
+    }
+
+    /// Make the paths of parsed files absolute, relative to the current
+    /// directory, wherever they show up: in errors, in
+    /// [origins](crate::Origin) and in the paths of included files. This
+    /// avoids confusing logs when the current directory of a daemon isn't
+    /// what one would expect.
+    ///
+    /// Symlinks and `..` aren't resolved, since the path of a symlink is
+    /// usually the more familiar one: on NixOS, `/etc/nix/nix.conf` is a
+    /// symlink into the Nix store.
+    ///
+    /// ```rust
+    /// use nix_config_parser::{NixConfig, ParseOptions};
+    ///
+    /// let error = NixConfig::parse_string_with_options(
+    ///     "bad line".into(),
+    ///     Some("nix.conf".as_ref()),
+    ///     &ParseOptions::new().absolute_origins(true),
+    /// )
+    /// .unwrap_err();
+    ///
+    /// let absolute = std::env::current_dir().unwrap().join("nix.conf");
+    /// assert!(error.to_string().ends_with(&format!("in '{}'", absolute.display())));
+    /// ```
+    pub fn absolute_origins(mut self, enabled: bool) -> Self {
+        self.absolute_origins = enabled;
+        self
+    }
+
+    /// `path` as it should appear in origins, see
+    /// [`ParseOptions::absolute_origins`].
+    pub(crate) fn origin<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if !self.absolute_origins || path.is_absolute() {
+            return Cow::Borrowed(path);
+        }
+
+        std::path::absolute(path).map_or(Cow::Borrowed(path), Cow::Owned)
+    }
+
+    /// Accept `include` and `!include` directives followed by more than one
+    /// token, which Nix rejects, by including the first one and ignoring the
+    /// rest. The ignored tokens are recorded, see
+    /// [`NixConfig::truncated_includes`](crate::NixConfig::truncated_includes).
+    pub fn lenient_includes(mut self, enabled: bool) -> Self {
+        self.lenient_includes = enabled;
+        self
+    }
+
+    /// What to do when an `include` can't be followed, for example because
+    /// the file exists but isn't readable. Defaults to
+    /// [`OnIncludeError::Fail`]; diagnostic tools can use
+    /// [`OnIncludeError::Skip`] to still report on the rest of the config.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, OnIncludeError, ParseOptions};
+    ///
+    /// let contents = "include /does/not/exist.conf\ncores = 4";
+    /// assert!(NixConfig::parse_string(contents.into(), None).is_err());
+    ///
+    /// let nix_conf = NixConfig::parse_string_with_options(
+    ///     contents.into(),
+    ///     None,
+    ///     &ParseOptions::new().on_include_error(OnIncludeError::Skip),
+    /// )?;
+    /// assert_eq!(nix_conf.settings().get("cores").unwrap(), "4");
+    /// assert_eq!(
+    ///     nix_conf.skipped_includes()[0].path(),
+    ///     std::path::Path::new("/does/not/exist.conf")
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_include_error(mut self, mode: OnIncludeError) -> Self {
+        self.include_errors = mode;
+        self
+    }
+
+    /// What to do with invalid bytes when parsing bytes with
+    /// [`NixConfig::parse_bytes_with_options`](crate::NixConfig::parse_bytes_with_options).
+    /// Defaults to [`InvalidUtf8::Error`].
+    pub fn invalid_utf8(mut self, invalid: InvalidUtf8) -> Self {
+        self.invalid_utf8 = invalid;
+        self
+    }
+
+    /// Hand lines starting with `word` to `handler` instead of parsing them as
+    /// settings, for vendor extensions or experimental directives. The line
+    /// is tokenized like a setting's value (see [`CustomDirective::args`]);
+    /// if the handler returns an error, parsing fails with
+    /// [`ParseError::DirectiveRejected`](crate::ParseError::DirectiveRejected).
+    ///
+    /// `include`, `!include` and (if enabled) `!unset` can't be claimed.
+    /// Registering a handler for a `word` again replaces the previous one.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use nix_config_parser::{NixConfig, ParseError, ParseOptions};
+    ///
+    /// let channels = Arc::new(Mutex::new(Vec::new()));
+    /// let options = ParseOptions::new().on_directive("@channel", {
+    ///     let channels = Arc::clone(&channels);
+    ///     move |directive| match directive.args() {
+    ///         [name, url] => {
+    ///             channels.lock().unwrap().push((name.clone(), url.clone()));
+    ///             Ok(())
+    ///         }
+    ///         _ => Err("expected a name and a URL".into()),
+    ///     }
+    /// });
+    ///
+    /// let nix_conf = NixConfig::parse_string_with_options(
+    ///     "@channel nixpkgs https://nixos.org/channels/nixpkgs-unstable\ncores = 4".into(),
+    ///     None,
+    ///     &options,
+    /// )?;
+    ///
+    /// assert_eq!(nix_conf.settings().len(), 1);
+    /// assert_eq!(channels.lock().unwrap()[0].0, "nixpkgs");
+    /// assert!(matches!(
+    ///     NixConfig::parse_string_with_options("@channel".into(), None, &options),
+    ///     Err(ParseError::DirectiveRejected(word, _, None)) if word == "@channel"
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_directive<F>(mut self, word: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&CustomDirective) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.directives.insert(word.into(), Arc::new(handler));
+        self
+    }
+
+    /// Fetch `include https://...` directives (and `!include`s) over the
+    /// network as configured by `remote`, instead of treating the URL as a
+    /// path. An `!include` that can't be fetched is skipped, unless its
+    /// contents don't match their [pinned checksum](RemoteIncludes::pin).
+    ///
+    /// Nix itself doesn't support remote includes, so a config using them
+    /// must be resolved, for example by writing out the parsed
+    /// [`NixConfig`](crate::NixConfig), before Nix reads it.
+    /// [`NixConfigCache`](crate::NixConfigCache) doesn't notice changes to
+    /// remote files.
+    #[cfg(feature = "http")]
+    pub fn remote_includes(mut self, remote: RemoteIncludes) -> Self {
+        self.remote_includes = Some(remote);
+        self
+    }
+
+    /// Only include files signed by one of the `keys`, failing the parse
+    /// with [`ParseError::UnverifiedInclude`](crate::ParseError::UnverifiedInclude)
+    /// otherwise, also for an `!include`. The signatures of a file are read
+    /// from the file next to it with `.sig` appended (fetched the same way for
+    /// [remote includes](ParseOptions::remote_includes)). The top-level file
+    /// itself isn't checked.
+    #[cfg(feature = "signatures")]
+    pub fn verify_includes(mut self, keys: TrustedKeys) -> Self {
+        self.trusted_keys = Some(keys);
+        self
+    }
+
+    /// The implementation of Nix the config is meant for, which determines the
+    /// settings that are known to exist (see
+    /// [`SettingInfo::lookup_in`](crate::SettingInfo::lookup_in)). All dialects
+    /// currently share the same syntax. Defaults to [`Dialect::Nix`].
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+}
@@ -0,0 +1,32 @@
+use std::{fmt, path::PathBuf};
+
+/// Where the value of a setting came from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Origin {
+    /// Nix's built-in default for the setting.
+    Default,
+    /// A line (1-based) in a `nix.conf`. The path is [`None`] if the config was
+    /// parsed from a string without an origin.
+    File { path: Option<PathBuf>, line: usize },
+    /// A line (1-based) in a config passed through an environment variable,
+    /// such as `NIX_CONFIG`.
+    Environment { variable: String, line: usize },
+    /// An option passed on the command line.
+    CommandLine,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => f.write_str("default"),
+            Origin::File {
+                path: Some(path),
+                line,
+            } => write!(f, "{}:{line}", path.display()),
+            Origin::File { path: None, line } => write!(f, "<unknown>:{line}"),
+            Origin::Environment { variable, line } => write!(f, "${variable}:{line}"),
+            Origin::CommandLine => f.write_str("command line"),
+        }
+    }
+}
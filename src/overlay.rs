@@ -0,0 +1,236 @@
+use indexmap::IndexMap;
+
+use crate::{NixConfig, SettingDiff, SettingInfo, SettingValue};
+
+/// A declarative patch to a [`NixConfig`]: settings to remove, settings to
+/// set, and items to append to list settings, applied in that order.
+///
+/// With the `serde` feature, overlays can be shipped by deployment tools
+/// instead of whole files.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::{ConfigOverlay, NixConfig};
+///
+/// let mut nix_conf = NixConfig::parse_string(
+///     "cores = 4\nmax-jobs = 2\ntrusted-users = root".into(),
+///     None,
+/// )?;
+///
+/// ConfigOverlay::new()
+///     .unset("max-jobs")
+///     .set("cores", "8")
+///     .append("trusted-users", "@wheel")
+///     .apply(&mut nix_conf);
+///
+/// assert_eq!(nix_conf.settings().get("cores").unwrap(), "8");
+/// assert_eq!(nix_conf.settings().get("trusted-users").unwrap(), "root @wheel");
+/// assert!(nix_conf.settings().get("max-jobs").is_none());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ConfigOverlay {
+    set: IndexMap<String, SettingValue>,
+    unset: Vec<String>,
+    append: IndexMap<String, SettingValue>,
+}
+
+impl ConfigOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `name` to `value`, replacing any previous value.
+    pub fn set(mut self, name: impl Into<String>, value: impl Into<SettingValue>) -> Self {
+        self.set.insert(name.into(), value.into());
+        self
+    }
+
+    /// Remove `name`.
+    pub fn unset(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !self.unset.contains(&name) {
+            self.unset.push(name);
+        }
+        self
+    }
+
+    /// Append the whitespace-separated items of `items` to the list setting
+    /// `name`, skipping items that are already present.
+    pub fn append(mut self, name: impl Into<String>, items: impl Into<SettingValue>) -> Self {
+        let items = items.into();
+        let appended = self.append.entry(name.into()).or_default();
+        for item in items.as_list() {
+            appended.push_item(item);
+        }
+        self
+    }
+
+    /// The overlay that turns `old` into `new`, for recording changes made to
+    /// one config and replaying them on others. Items added to the end of a
+    /// list setting are recorded as appended, so other items of that setting
+    /// are left alone when replaying.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{ConfigOverlay, NixConfig};
+    ///
+    /// let old = NixConfig::parse_string("trusted-users = root\ncores = 4".into(), None)?;
+    /// let new = NixConfig::parse_string("trusted-users = root alice".into(), None)?;
+    ///
+    /// let overlay = ConfigOverlay::from_diff(&old, &new);
+    /// assert_eq!(overlay, ConfigOverlay::new().unset("cores").append("trusted-users", "alice"));
+    ///
+    /// let mut other = NixConfig::parse_string("trusted-users = bob\ncores = 8".into(), None)?;
+    /// overlay.apply(&mut other);
+    /// assert_eq!(other.settings().get("trusted-users").unwrap(), "bob alice");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_diff(old: &NixConfig, new: &NixConfig) -> Self {
+        let mut overlay = Self::new();
+
+        for diff in old.diff(new) {
+            overlay = match diff {
+                SettingDiff::Added { name, value } => overlay.set(name, value),
+                SettingDiff::Removed { name, .. } => overlay.unset(name),
+                SettingDiff::Changed { name, old, new } => {
+                    match appended_items(&name, &old, &new) {
+                        Some(items) => overlay.append(name, items),
+                        None => overlay.set(name, new),
+                    }
+                }
+            };
+        }
+
+        overlay
+    }
+
+    /// The settings this overlay sets.
+    pub fn sets(&self) -> &IndexMap<String, SettingValue> {
+        &self.set
+    }
+
+    /// The settings this overlay removes.
+    pub fn unsets(&self) -> &[String] {
+        &self.unset
+    }
+
+    /// The items this overlay appends to list settings.
+    pub fn appends(&self) -> &IndexMap<String, SettingValue> {
+        &self.append
+    }
+
+    /// Whether applying this overlay would never change anything.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.unset.is_empty() && self.append.is_empty()
+    }
+
+    /// Apply this overlay to `config`. Applying the same overlay again has no
+    /// further effect.
+    pub fn apply(&self, config: &mut NixConfig) {
+        for name in &self.unset {
+            config.settings_mut().shift_remove(name);
+        }
+
+        for (name, value) in &self.set {
+            config.settings_mut().insert(name.clone(), value.clone());
+        }
+
+        for (name, items) in &self.append {
+            let value = config.settings_mut().entry(name.clone()).or_default();
+            for item in items.as_list() {
+                if !value.as_list().contains(&item) {
+                    value.push_item(item);
+                }
+            }
+        }
+    }
+}
+
+/// The items appended to the end of the list setting `name` going from `old`
+/// to `new`, if that's the only change.
+fn appended_items(name: &str, old: &SettingValue, new: &SettingValue) -> Option<String> {
+    if !SettingInfo::lookup(name)?.setting_type().is_list() {
+        return None;
+    }
+
+    let old = old.as_list();
+    let new = new.as_list();
+    let added = new.strip_prefix(old.as_slice())?;
+
+    (!added.is_empty() && added.iter().all(|item| !old.contains(item))).then(|| added.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_idempotently() {
+        let overlay = ConfigOverlay::new()
+            .append("extra-substituters", "https://a.example.com")
+            .append(
+                "extra-substituters",
+                "https://a.example.com https://b.example.com",
+            )
+            .unset("sandbox")
+            .set("sandbox", "relaxed");
+
+        let mut nix_conf =
+            NixConfig::parse_string("sandbox = true\nwarn-dirty = false".into(), None).unwrap();
+        overlay.apply(&mut nix_conf);
+        overlay.apply(&mut nix_conf);
+
+        assert_eq!(
+            nix_conf.settings().get("extra-substituters"),
+            Some(&"https://a.example.com https://b.example.com".into())
+        );
+        assert_eq!(nix_conf.settings().get("sandbox"), Some(&"relaxed".into()));
+        assert_eq!(nix_conf.settings().len(), 3);
+        assert!(!overlay.is_empty());
+        assert!(ConfigOverlay::new().is_empty());
+    }
+
+    #[test]
+    fn replays_diffs() {
+        let old = NixConfig::parse_string(
+            "substituters = https://a.example.com\ntrusted-users = root alice\ncores = 4\nsandbox = true"
+                .into(),
+            None,
+        )
+        .unwrap();
+        let new = NixConfig::parse_string(
+            "substituters = https://a.example.com https://b.example.com\ntrusted-users = root\ncores = 8\nfallback = true"
+                .into(),
+            None,
+        )
+        .unwrap();
+
+        let overlay = ConfigOverlay::from_diff(&old, &new);
+        assert_eq!(
+            overlay,
+            ConfigOverlay::new()
+                .append("substituters", "https://b.example.com")
+                .set("trusted-users", "root")
+                .set("cores", "8")
+                .unset("sandbox")
+                .set("fallback", "true")
+        );
+
+        let mut replayed = old.clone();
+        overlay.apply(&mut replayed);
+        assert_eq!(replayed, new);
+        assert!(ConfigOverlay::from_diff(&new, &new).is_empty());
+    }
+}
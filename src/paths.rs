@@ -0,0 +1,195 @@
+//! The locations Nix reads its configuration from, following the same lookup
+//! rules as Nix itself. These only depend on environment variables, so they
+//! are the same on Linux and macOS.
+
+use std::path::PathBuf;
+
+/// The system-wide `nix.conf`: `$NIX_CONF_DIR/nix.conf`, where `NIX_CONF_DIR`
+/// defaults to `/etc/nix`.
+///
+/// ```rust
+/// let system_conf = nix_config_parser::paths::system_config_file();
+///
+/// if std::env::var_os("NIX_CONF_DIR").is_none() {
+///     assert_eq!(system_conf, std::path::Path::new("/etc/nix/nix.conf"));
+/// }
+/// ```
+pub fn system_config_file() -> PathBuf {
+    system_config_file_from(env)
+}
+
+/// The user's `nix.conf` files, in the order Nix reads them; settings in later
+/// files take precedence over earlier ones.
+///
+/// If `NIX_USER_CONF_FILES` is set, these are the files it lists (separated by
+/// `:`), with the last one read first. Otherwise, they are `nix/nix.conf` in
+/// each of the directories in `XDG_CONFIG_DIRS` (defaulting to `/etc/xdg`),
+/// followed by `XDG_CONFIG_HOME` (defaulting to `$HOME/.config`).
+pub fn user_config_files() -> Vec<PathBuf> {
+    user_config_files_from(env)
+}
+
+/// The user's `nix.conf` that takes precedence over all others, and which
+/// tools should edit when changing the user's configuration: the first file in
+/// `NIX_USER_CONF_FILES` if set, or `$XDG_CONFIG_HOME/nix/nix.conf`, or
+/// `$HOME/.config/nix/nix.conf`. Returns [`None`] if none of these variables
+/// are set (or `NIX_USER_CONF_FILES` lists no files), rather than one of the
+/// system-wide `XDG_CONFIG_DIRS`.
+///
+/// Nix uses these locations on macOS too, not `~/Library`, so there is
+/// nothing platform-specific here.
+pub fn user_config_file() -> Option<PathBuf> {
+    user_config_file_from(env)
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Like [`system_config_file`], but looking up environment variables with
+/// `env` rather than in the environment of this process, such as a captured
+/// one.
+pub fn system_config_file_from(env: impl Fn(&str) -> Option<String>) -> PathBuf {
+    let conf_dir = env("NIX_CONF_DIR").unwrap_or_else(|| String::from("/etc/nix"));
+    PathBuf::from(conf_dir).join("nix.conf")
+}
+
+fn user_config_file_from(env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    if let Some(files) = env("NIX_USER_CONF_FILES") {
+        return files
+            .split(':')
+            .find(|file| !file.is_empty())
+            .map(PathBuf::from);
+    }
+
+    config_home(&env).map(|dir| dir.join("nix").join("nix.conf"))
+}
+
+/// Like [`user_config_files`], but looking up environment variables with `env`
+/// rather than in the environment of this process, such as a captured one.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::path::PathBuf;
+///
+/// use nix_config_parser::paths;
+///
+/// let captured = HashMap::from([("HOME", "/home/alice")]);
+/// let files = paths::user_config_files_from(|name| captured.get(name).map(|v| v.to_string()));
+///
+/// assert_eq!(
+///     files,
+///     [
+///         PathBuf::from("/etc/xdg/nix/nix.conf"),
+///         PathBuf::from("/home/alice/.config/nix/nix.conf"),
+///     ]
+/// );
+/// ```
+pub fn user_config_files_from(env: impl Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    if let Some(files) = env("NIX_USER_CONF_FILES") {
+        return files
+            .split(':')
+            .filter(|file| !file.is_empty())
+            .rev()
+            .map(PathBuf::from)
+            .collect();
+    }
+
+    let config_home = config_home(&env);
+    let config_dirs = env("XDG_CONFIG_DIRS").unwrap_or_else(|| String::from("/etc/xdg"));
+
+    config_dirs
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .rev()
+        .map(PathBuf::from)
+        .chain(config_home)
+        .map(|dir| dir.join("nix").join("nix.conf"))
+        .collect()
+}
+
+/// `XDG_CONFIG_HOME`, defaulting to `$HOME/.config`.
+fn config_home(env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    env("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn follows_nix_lookup_rules() {
+        assert_eq!(
+            system_config_file_from(fake_env(&[])),
+            PathBuf::from("/etc/nix/nix.conf")
+        );
+        assert_eq!(
+            system_config_file_from(fake_env(&[("NIX_CONF_DIR", "/opt/nix")])),
+            PathBuf::from("/opt/nix/nix.conf")
+        );
+
+        assert_eq!(
+            user_config_files_from(fake_env(&[("HOME", "/home/alice")])),
+            [
+                PathBuf::from("/etc/xdg/nix/nix.conf"),
+                PathBuf::from("/home/alice/.config/nix/nix.conf"),
+            ]
+        );
+        assert_eq!(
+            user_config_files_from(fake_env(&[
+                ("HOME", "/home/alice"),
+                ("XDG_CONFIG_HOME", "/xdg/home"),
+                ("XDG_CONFIG_DIRS", "/xdg/a:/xdg/b"),
+            ])),
+            [
+                PathBuf::from("/xdg/b/nix/nix.conf"),
+                PathBuf::from("/xdg/a/nix/nix.conf"),
+                PathBuf::from("/xdg/home/nix/nix.conf"),
+            ]
+        );
+        assert_eq!(
+            user_config_files_from(fake_env(&[
+                ("HOME", "/home/alice"),
+                ("NIX_USER_CONF_FILES", "/first.conf::/second.conf"),
+            ])),
+            [PathBuf::from("/second.conf"), PathBuf::from("/first.conf")]
+        );
+    }
+
+    #[test]
+    fn finds_the_users_own_file() {
+        assert_eq!(
+            user_config_file_from(fake_env(&[("HOME", "/home/alice")])),
+            Some(PathBuf::from("/home/alice/.config/nix/nix.conf"))
+        );
+        assert_eq!(
+            user_config_file_from(fake_env(&[
+                ("HOME", "/home/alice"),
+                ("XDG_CONFIG_HOME", "/xdg/home"),
+            ])),
+            Some(PathBuf::from("/xdg/home/nix/nix.conf"))
+        );
+        assert_eq!(
+            user_config_file_from(fake_env(&[(
+                "NIX_USER_CONF_FILES",
+                ":/first.conf:/second.conf"
+            )])),
+            Some(PathBuf::from("/first.conf"))
+        );
+        assert_eq!(
+            user_config_file_from(fake_env(&[("XDG_CONFIG_DIRS", "/xdg/a")])),
+            None
+        );
+        assert_eq!(user_config_file_from(fake_env(&[])), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::{NixConfig, SettingValue};
+
+/// A glob pattern over setting names, in which `*` matches any number of
+/// characters and `?` matches a single one, such as `extra-*` or
+/// `trusted-*`. Every other character matches itself.
+///
+/// ```rust
+/// use nix_config_parser::SettingPattern;
+///
+/// let pattern = SettingPattern::new("trusted-*");
+/// assert!(pattern.matches("trusted-users"));
+/// assert!(!pattern.matches("extra-trusted-users"));
+/// assert!(SettingPattern::new("c?res").matches("cores"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct SettingPattern(String);
+
+impl SettingPattern {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the pattern has no wildcards, and so only matches the setting
+    /// of the same name.
+    pub fn is_literal(&self) -> bool {
+        !self.0.contains(['*', '?'])
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let pattern = self.0.chars().collect::<Vec<_>>();
+        let name = name.chars().collect::<Vec<_>>();
+
+        let (mut p, mut n) = (0, 0);
+        // Where to resume after the last `*` if the characters after it stop
+        // matching: the position of the `*`, and how much of the name it
+        // matched so far.
+        let mut backtrack = None;
+        while n < name.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, n));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match backtrack {
+                    Some((star, matched)) => {
+                        p = star + 1;
+                        n = matched + 1;
+                        backtrack = Some((star, matched + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        pattern[p..].iter().all(|&c| c == '*')
+    }
+}
+
+impl fmt::Display for SettingPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for SettingPattern {
+    fn from(pattern: &str) -> Self {
+        Self::new(pattern)
+    }
+}
+
+impl From<String> for SettingPattern {
+    fn from(pattern: String) -> Self {
+        Self(pattern)
+    }
+}
+
+impl NixConfig {
+    /// The settings whose name matches `pattern` (see [`SettingPattern`]), in
+    /// order, for example to manage a whole family of settings at once.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "extra-substituters = https://example.com\ncores = 4\nextra-trusted-users = alice".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let extra = nix_conf
+    ///     .settings_matching("extra-*")
+    ///     .map(|(name, _)| name)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(extra, ["extra-substituters", "extra-trusted-users"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn settings_matching(
+        &self,
+        pattern: impl Into<SettingPattern>,
+    ) -> impl Iterator<Item = (&str, &SettingValue)> {
+        let pattern = pattern.into();
+        self.settings()
+            .iter()
+            .filter(move |(name, _)| pattern.matches(name))
+            .map(|(name, value)| (name.as_str(), value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_globs() {
+        let cases = [
+            ("*", "", true),
+            ("*", "cores", true),
+            ("cores", "cores", true),
+            ("cores", "cores2", false),
+            ("extra-*", "extra-substituters", true),
+            ("extra-*", "substituters", false),
+            ("*-users", "trusted-users", true),
+            ("*-users", "allowed-users-list", false),
+            ("*a*b*c", "xaybzc", true),
+            ("*a*b*c", "xaybzcd", false),
+            ("a*a*a", "aaaa", true),
+            ("?", "", false),
+            ("build-??re?", "build-cores", true),
+        ];
+
+        for (pattern, name, expected) in cases {
+            assert_eq!(
+                SettingPattern::new(pattern).matches(name),
+                expected,
+                "{pattern} ~ {name}"
+            );
+        }
+        assert!(SettingPattern::new("cores").is_literal());
+        assert!(!SettingPattern::new("c?res").is_literal());
+    }
+}
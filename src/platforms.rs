@@ -0,0 +1,169 @@
+use crate::{NixConfig, ValueProblem};
+
+/// The system features with a meaning known to Nix, Nixpkgs or NixOS.
+/// Features starting with `gccarch-` (such as `gccarch-x86-64-v3`) are known
+/// as well.
+pub const KNOWN_SYSTEM_FEATURES: &[&str] = &[
+    "apple-virt",
+    "benchmark",
+    "big-parallel",
+    "ca-derivations",
+    "kvm",
+    "nixos-test",
+    "recursive-nix",
+    "uid-range",
+];
+
+/// The operating systems Nix system types are known to end with.
+const KNOWN_KERNELS: &[&str] = &[
+    "cygwin", "darwin", "freebsd", "genode", "illumos", "linux", "netbsd", "none", "openbsd",
+    "redox", "solaris", "wasi", "windows",
+];
+
+impl NixConfig {
+    /// The items of `system-features` and `extra-system-features`, in order.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "system-features = kvm big-parallel\nextra-system-features = nixos-test".into(),
+    ///     None,
+    /// )?;
+    /// assert_eq!(nix_conf.system_features(), ["kvm", "big-parallel", "nixos-test"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn system_features(&self) -> Vec<&str> {
+        self.list_items("system-features")
+    }
+
+    /// The items of `extra-platforms` (and `extra-extra-platforms`), in order.
+    pub fn extra_platforms(&self) -> Vec<&str> {
+        self.list_items("extra-platforms")
+    }
+
+    /// Check `system-features` and `extra-platforms` (and their `extra-`
+    /// variants): features should be one of the [`KNOWN_SYSTEM_FEATURES`],
+    /// and platforms should look like Nix system types such as
+    /// `aarch64-linux`, not like target triples such as
+    /// `aarch64-unknown-linux-gnu`.
+    ///
+    /// Unknown features aren't necessarily wrong, as derivations can require
+    /// any feature, but are often typos.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "system-features = kvm big-paralel gccarch-armv8-a\nextra-platforms = x86_64-darwin aarch64-apple-darwin"
+    ///         .into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let problems = nix_conf.validate_platforms();
+    /// assert_eq!(
+    ///     problems.iter().map(|problem| problem.message()).collect::<Vec<_>>(),
+    ///     [
+    ///         "unknown system feature 'big-paralel'",
+    ///         "'aarch64-apple-darwin' is not a Nix system type like 'x86_64-linux'",
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_platforms(&self) -> Vec<ValueProblem> {
+        let mut problems = Vec::new();
+
+        for (name, value) in self.settings() {
+            for item in value.as_list() {
+                let message = match name.as_str() {
+                    "system-features" | "extra-system-features"
+                        if !is_known_system_feature(item) =>
+                    {
+                        format!("unknown system feature '{item}'")
+                    }
+                    "extra-platforms" | "extra-extra-platforms" if !is_system_type(item) => {
+                        format!("'{item}' is not a Nix system type like 'x86_64-linux'")
+                    }
+                    _ => continue,
+                };
+                problems.push(ValueProblem {
+                    name: name.clone(),
+                    value: value.clone(),
+                    message,
+                    origin: self.origin(name).cloned(),
+                });
+            }
+        }
+
+        problems
+    }
+
+    /// The items of `name` and `extra-<name>`, in order.
+    fn list_items(&self, name: &str) -> Vec<&str> {
+        [name.to_owned(), format!("extra-{name}")]
+            .iter()
+            .filter_map(|name| self.settings().get(name))
+            .flat_map(|value| value.as_list())
+            .collect()
+    }
+}
+
+fn is_known_system_feature(feature: &str) -> bool {
+    KNOWN_SYSTEM_FEATURES.contains(&feature) || feature.starts_with("gccarch-")
+}
+
+/// Whether `system` is a Nix system type: `<cpu>-<kernel>`, such as
+/// `x86_64-linux` or `aarch64-darwin`.
+pub fn is_system_type(system: &str) -> bool {
+    let Some((cpu, kernel)) = system.split_once('-') else {
+        return false;
+    };
+
+    !cpu.is_empty()
+        && cpu
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && KNOWN_KERNELS.contains(&kernel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_system_types() {
+        for system in [
+            "x86_64-linux",
+            "aarch64-darwin",
+            "armv7l-linux",
+            "wasm32-wasi",
+        ] {
+            assert!(is_system_type(system), "{system}");
+        }
+        for system in [
+            "x86_64",
+            "-linux",
+            "x86_64-unknown-linux-gnu",
+            "X86_64-linux",
+            "x86_64-linuxx",
+        ] {
+            assert!(!is_system_type(system), "{system}");
+        }
+
+        let nix_conf = NixConfig::parse_string(
+            "extra-extra-platforms = x86_64-linux\nextra-platforms = i686-linux".into(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(nix_conf.extra_platforms(), ["i686-linux", "x86_64-linux"]);
+        assert!(nix_conf.validate_platforms().is_empty());
+    }
+}
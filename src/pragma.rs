@@ -0,0 +1,93 @@
+use crate::Origin;
+
+/// A `# key: value` comment in a [`NixConfigDocument`](crate::NixConfigDocument),
+/// which tools can use to keep machine-readable metadata in a `nix.conf`.
+/// Nix ignores it like any other comment.
+///
+/// The key is a single word of letters, digits, `-`, `_`, `.` and `/`,
+/// followed directly by a `:` and then whitespace or the end of the comment,
+/// so URLs in comments are not mistaken for pragmas. Keys are case-sensitive;
+/// pick a prefix unlikely to appear in prose, like `nixconf-lint`.
+///
+/// A pragma at the end of a line applies to that line; one on a line of its
+/// own applies to the next line that isn't blank or a comment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pragma {
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) setting: Option<String>,
+    pub(crate) origin: Origin,
+    pub(crate) target: Option<Origin>,
+}
+
+impl Pragma {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Everything after the `:`, trimmed.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The setting assigned on the line the pragma applies to, if it is an
+    /// assignment.
+    pub fn setting(&self) -> Option<&str> {
+        self.setting.as_deref()
+    }
+
+    /// Where the comment is.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// The line the pragma applies to, or [`None`] if there is none, like
+    /// for a pragma at the end of the document.
+    pub fn target(&self) -> Option<&Origin> {
+        self.target.as_ref()
+    }
+}
+
+/// Split a comment (starting with its `#`) into the key and value of a
+/// pragma, if it is one.
+pub(crate) fn parse_pragma(comment: &str) -> Option<(&str, &str)> {
+    let text = comment.strip_prefix('#')?.trim();
+    let (key, value) = text.split_once(':')?;
+
+    let is_key_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/');
+    if key.is_empty() || !key.chars().all(is_key_char) {
+        return None;
+    }
+    if !value.is_empty() && !value.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some((key, value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pragmas() {
+        assert_eq!(
+            parse_pragma("# owner: platform-team"),
+            Some(("owner", "platform-team"))
+        );
+        assert_eq!(
+            parse_pragma("#ci/expires:2025-01-01"),
+            None,
+            "the value must be separated from the `:`"
+        );
+        assert_eq!(parse_pragma("#managed-by:"), Some(("managed-by", "")));
+        assert_eq!(
+            parse_pragma("# nixconf-lint: ignore deprecated-alias"),
+            Some(("nixconf-lint", "ignore deprecated-alias"))
+        );
+        assert_eq!(parse_pragma("# see https://nixos.org/manual"), None);
+        assert_eq!(parse_pragma("# https://nixos.org/manual"), None);
+        assert_eq!(parse_pragma("# Note that: this is prose"), None);
+    }
+}
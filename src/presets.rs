@@ -0,0 +1,89 @@
+use crate::NixConfig;
+
+const FLAKES: &[(&str, &str)] = &[("experimental-features", "nix-command flakes")];
+
+const PARALLELISM: &[(&str, &str)] = &[("max-jobs", "auto"), ("cores", "0")];
+
+const DEVELOPER: &[(&str, &str)] = &[("keep-outputs", "true"), ("keep-derivations", "true")];
+
+const CI: &[(&str, &str)] = &[
+    ("always-allow-substitutes", "true"),
+    ("builders-use-substitutes", "true"),
+    ("narinfo-cache-negative-ttl", "0"),
+    ("fallback", "true"),
+    ("show-trace", "true"),
+];
+
+/// Opinionated starting points for common kinds of machines. Each preset is a
+/// plain [`NixConfig`], so callers can adjust it before writing it out.
+impl NixConfig {
+    /// Enable flakes and the `nix` command.
+    ///
+    /// ```rust
+    /// let nix_conf = nix_config_parser::NixConfig::preset_flakes();
+    ///
+    /// assert_eq!(
+    ///     nix_conf.settings().get("experimental-features").unwrap(),
+    ///     "nix-command flakes"
+    /// );
+    /// ```
+    pub fn preset_flakes() -> Self {
+        Self::from_preset(&[FLAKES])
+    }
+
+    /// [`NixConfig::preset_flakes`], plus building in parallel on all cores and
+    /// keeping build-time dependencies around across garbage collections, so
+    /// development shells don't need to be re-fetched.
+    pub fn preset_developer() -> Self {
+        Self::from_preset(&[FLAKES, PARALLELISM, DEVELOPER])
+    }
+
+    /// [`NixConfig::preset_flakes`], plus building in parallel on all cores,
+    /// substituting as much as possible (including on remote builders), never
+    /// caching missing paths so freshly pushed paths are picked up, and
+    /// verbose errors for CI logs.
+    pub fn preset_ci() -> Self {
+        Self::from_preset(&[FLAKES, PARALLELISM, CI])
+    }
+
+    fn from_preset(groups: &[&[(&str, &str)]]) -> Self {
+        let mut nix_conf = Self::new();
+
+        for (name, value) in groups.iter().copied().flatten() {
+            nix_conf
+                .settings_mut()
+                .insert(name.to_string(), (*value).into());
+        }
+
+        nix_conf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid_and_conflict_free() {
+        for preset in [
+            NixConfig::preset_flakes(),
+            NixConfig::preset_developer(),
+            NixConfig::preset_ci(),
+        ] {
+            assert!(preset.to_string_checked().is_ok());
+            assert!(preset.detect_conflicts().is_empty());
+
+            for name in preset.settings().keys() {
+                assert!(
+                    crate::SettingInfo::lookup(name).is_some(),
+                    "'{name}' is not a known setting"
+                );
+            }
+        }
+
+        assert_eq!(
+            NixConfig::preset_developer().settings().get("keep-outputs"),
+            Some(&"true".into())
+        );
+    }
+}
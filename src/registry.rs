@@ -0,0 +1,1099 @@
+use std::fmt;
+use std::sync::{PoisonError, RwLock};
+
+use crate::{Dialect, NixConfig, SettingValue};
+
+const DOCUMENTATION_BASE_URL: &str =
+    "https://nixos.org/manual/nix/stable/command-ref/conf-file.html";
+
+/// How Nix interprets the value of a setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingType {
+    Boolean,
+    Integer,
+    /// An integer, or `auto` for the number of available CPUs.
+    IntegerOrAuto,
+    String,
+    Path,
+    StringList,
+    PathList,
+    /// A whitespace-separated list of `key=value` pairs.
+    StringMap,
+    /// One of a fixed set of values.
+    Enum(&'static [&'static str]),
+}
+
+impl SettingType {
+    /// Whether values of this type are whitespace-separated lists, and thus can
+    /// be extended using `extra-<name>`.
+    pub fn is_list(&self) -> bool {
+        matches!(
+            self,
+            SettingType::StringList | SettingType::PathList | SettingType::StringMap
+        )
+    }
+}
+
+impl fmt::Display for SettingType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingType::Boolean => f.write_str("boolean"),
+            SettingType::Integer => f.write_str("integer"),
+            SettingType::IntegerOrAuto => f.write_str("integer or 'auto'"),
+            SettingType::String => f.write_str("string"),
+            SettingType::Path => f.write_str("path"),
+            SettingType::StringList => f.write_str("list of strings"),
+            SettingType::PathList => f.write_str("list of paths"),
+            SettingType::StringMap => f.write_str("list of key=value pairs"),
+            SettingType::Enum(values) => write!(f, "one of '{}'", values.join("', '")),
+        }
+    }
+}
+
+/// Metadata about a setting known to Nix.
+///
+/// ```rust
+/// use nix_config_parser::{SettingInfo, SettingType};
+///
+/// let info = SettingInfo::lookup("binary-caches").unwrap();
+///
+/// assert_eq!(info.name(), "substituters");
+/// assert_eq!(info.setting_type(), SettingType::StringList);
+/// assert_eq!(info.type_name(), "list of strings");
+/// assert_eq!(
+///     info.documentation_url(),
+///     "https://nixos.org/manual/nix/stable/command-ref/conf-file.html#conf-substituters"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettingInfo {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    setting_type: SettingType,
+    default: Option<&'static str>,
+    description: &'static str,
+    dialects: &'static [Dialect],
+}
+
+/// Settings registered by [`SettingInfo::register`].
+static REGISTERED: RwLock<Vec<&'static SettingInfo>> = RwLock::new(Vec::new());
+
+impl SettingInfo {
+    /// Describe a setting that isn't known to this crate, such as one of a
+    /// Nix plugin, to [`register`](SettingInfo::register) it. It has no
+    /// aliases and is supported by every [`Dialect`].
+    pub const fn new(
+        name: &'static str,
+        setting_type: SettingType,
+        default: Option<&'static str>,
+        description: &'static str,
+    ) -> Self {
+        setting(name, setting_type, default, description)
+    }
+
+    /// Set the legacy names that are also accepted for this setting.
+    pub const fn with_aliases(self, aliases: &'static [&'static str]) -> Self {
+        aliased(self, aliases)
+    }
+
+    /// Set the implementations of Nix that support this setting.
+    pub const fn with_dialects(self, dialects: &'static [Dialect]) -> Self {
+        only(self, dialects)
+    }
+
+    /// Add a setting to the registry for the rest of the process, so
+    /// [`SettingInfo::lookup`] finds it and it takes part in checks such as
+    /// [`NixConfig::validate_values`] and [`NixConfig::unknown_settings`], like
+    /// the settings of Nix itself. This lets third-party setting families,
+    /// such as those of Nix plugins, be described once.
+    ///
+    /// Returns `false`, and registers nothing, if the name or one of the
+    /// aliases is already known.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, SettingInfo, SettingType};
+    ///
+    /// static UPLOAD_JOBS: SettingInfo = SettingInfo::new(
+    ///     "example-upload-jobs",
+    ///     SettingType::Integer,
+    ///     Some("4"),
+    ///     "How many paths the example plugin uploads at once.",
+    /// );
+    ///
+    /// assert!(SettingInfo::register(&UPLOAD_JOBS));
+    /// assert!(!SettingInfo::register(&UPLOAD_JOBS));
+    ///
+    /// let nix_conf = NixConfig::parse_string("example-upload-jobs = many".into(), None)?;
+    /// assert_eq!(nix_conf.validate_values()[0].message(), "expected an integer");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register(info: &'static SettingInfo) -> bool {
+        let mut registered = REGISTERED.write().unwrap_or_else(PoisonError::into_inner);
+
+        let known = |name: &str| {
+            SETTINGS
+                .iter()
+                .chain(registered.iter().copied())
+                .any(|other| other.name == name || other.aliases.contains(&name))
+        };
+        if std::iter::once(&info.name)
+            .chain(info.aliases)
+            .any(|name| known(name))
+        {
+            return false;
+        }
+
+        registered.push(info);
+        true
+    }
+
+    /// Look up a setting by its name or one of its legacy aliases. Names of the
+    /// form `extra-<name>` resolve to `<name>` if it is a list setting.
+    /// Settings added with [`SettingInfo::register`] are found too.
+    pub fn lookup(name: &str) -> Option<&'static SettingInfo> {
+        let matches = |info: &&SettingInfo| info.name == name || info.aliases.contains(&name);
+        let found = SETTINGS.iter().find(matches).or_else(|| {
+            REGISTERED
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .copied()
+                .find(matches)
+        });
+
+        found.or_else(|| {
+            let base = name.strip_prefix("extra-")?;
+            Self::lookup(base).filter(|info| info.setting_type.is_list())
+        })
+    }
+
+    /// Like [`SettingInfo::lookup`], but only finds settings supported by
+    /// `dialect`.
+    ///
+    /// ```rust
+    /// use nix_config_parser::{Dialect, SettingInfo};
+    ///
+    /// assert!(SettingInfo::lookup_in("substituters", Dialect::Lix).is_some());
+    /// ```
+    pub fn lookup_in(name: &str, dialect: Dialect) -> Option<&'static SettingInfo> {
+        Self::lookup(name).filter(|info| info.is_supported_by(dialect))
+    }
+
+    /// All settings known to this crate, not including those added with
+    /// [`SettingInfo::register`].
+    pub fn all() -> &'static [SettingInfo] {
+        SETTINGS
+    }
+
+    /// The settings added with [`SettingInfo::register`], in order.
+    pub fn registered() -> Vec<&'static SettingInfo> {
+        REGISTERED
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Legacy names that Nix still accepts for this setting.
+    pub fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    pub fn setting_type(&self) -> SettingType {
+        self.setting_type
+    }
+
+    /// A human-readable name for the type of this setting.
+    pub fn type_name(&self) -> String {
+        self.setting_type.to_string()
+    }
+
+    /// The default value, as it would be written in a `nix.conf`, or [`None`]
+    /// if it depends on the platform or installation.
+    pub fn default_value(&self) -> Option<&'static str> {
+        self.default
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// The implementations of Nix that support this setting.
+    pub fn dialects(&self) -> &'static [Dialect] {
+        self.dialects
+    }
+
+    pub fn is_supported_by(&self, dialect: Dialect) -> bool {
+        self.dialects.contains(&dialect)
+    }
+
+    /// A link to the documentation of this setting in the Nix manual.
+    pub fn documentation_url(&self) -> String {
+        format!("{DOCUMENTATION_BASE_URL}#conf-{}", self.name)
+    }
+}
+
+const fn setting(
+    name: &'static str,
+    setting_type: SettingType,
+    default: Option<&'static str>,
+    description: &'static str,
+) -> SettingInfo {
+    SettingInfo {
+        name,
+        aliases: &[],
+        setting_type,
+        default,
+        description,
+        dialects: Dialect::ALL,
+    }
+}
+
+const fn aliased(info: SettingInfo, aliases: &'static [&'static str]) -> SettingInfo {
+    SettingInfo { aliases, ..info }
+}
+
+const fn only(info: SettingInfo, dialects: &'static [Dialect]) -> SettingInfo {
+    SettingInfo { dialects, ..info }
+}
+
+impl NixConfig {
+    /// The names of the settings in this config that `dialect` doesn't know
+    /// about, and would warn about or ignore.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{Dialect, NixConfig};
+    ///
+    /// let nix_conf = NixConfig::parse_string("cores = 4\nbuild-corez = 4".into(), None)?;
+    ///
+    /// assert_eq!(nix_conf.unknown_settings(Dialect::Nix), ["build-corez"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unknown_settings(&self, dialect: Dialect) -> Vec<&str> {
+        self.settings()
+            .keys()
+            .filter(|name| SettingInfo::lookup_in(name, dialect).is_none())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The settings whose name starts with `prefix`, such as `determinate-`
+    /// for a vendor's settings, in order. Settings extending a list with
+    /// `extra-<name>` are included if `<name>` starts with `prefix`.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "lazy-trees = true\nmy-plugin-cache = /var/cache\nextra-my-plugin-hosts = a b".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let names = nix_conf
+    ///     .settings_in_namespace("my-plugin-")
+    ///     .map(|(name, _)| name)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(names, ["my-plugin-cache", "extra-my-plugin-hosts"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn settings_in_namespace<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a SettingValue)> {
+        self.settings()
+            .iter()
+            .filter(move |(name, _)| {
+                let name = name.strip_prefix("extra-").unwrap_or(name);
+                name.starts_with(prefix)
+            })
+            .map(|(name, value)| (name.as_str(), value))
+    }
+}
+
+use SettingType::{Boolean, Enum, Integer, IntegerOrAuto, Path, PathList, StringList, StringMap};
+
+static SETTINGS: &[SettingInfo] = &[
+    setting(
+        "accept-flake-config",
+        Boolean,
+        Some("false"),
+        "Whether to accept nix configuration from a flake without prompting.",
+    ),
+    setting(
+        "access-tokens",
+        StringMap,
+        Some(""),
+        "Access tokens used to access protected GitHub, GitLab, or other locations requiring token-based authentication.",
+    ),
+    setting(
+        "allow-dirty",
+        Boolean,
+        Some("true"),
+        "Whether to allow dirty Git/Mercurial trees.",
+    ),
+    setting(
+        "allow-import-from-derivation",
+        Boolean,
+        Some("true"),
+        "Whether the Nix evaluator will allow importing the result of a derivation.",
+    ),
+    setting(
+        "allow-new-privileges",
+        Boolean,
+        Some("false"),
+        "Whether builders on Linux are allowed to acquire new privileges, for example via setuid programs.",
+    ),
+    setting(
+        "allow-symlinked-store",
+        Boolean,
+        Some("false"),
+        "Whether to allow the Nix store to be a symlink.",
+    ),
+    setting(
+        "allow-unsafe-native-code-during-evaluation",
+        Boolean,
+        Some("false"),
+        "Whether builtin functions that allow executing native code are enabled.",
+    ),
+    setting(
+        "allowed-impure-host-deps",
+        StringList,
+        Some(""),
+        "Which prefixes to allow derivations to ask for access to on macOS when sandboxing is enabled.",
+    ),
+    setting(
+        "allowed-uris",
+        StringList,
+        Some(""),
+        "URI prefixes that may be fetched by builtin functions in restricted evaluation mode.",
+    ),
+    setting(
+        "allowed-users",
+        StringList,
+        Some("*"),
+        "The users and groups allowed to connect to the Nix daemon.",
+    ),
+    setting(
+        "always-allow-substitutes",
+        Boolean,
+        Some("false"),
+        "Whether to substitute outputs even for derivations that set `allowSubstitutes = false`.",
+    ),
+    setting(
+        "auto-allocate-uids",
+        Boolean,
+        Some("false"),
+        "Whether to select UIDs for builds automatically, instead of using the users in `build-users-group`.",
+    ),
+    setting(
+        "auto-optimise-store",
+        Boolean,
+        Some("false"),
+        "Whether to automatically hard-link identical files in the Nix store.",
+    ),
+    setting(
+        "bash-prompt",
+        SettingType::String,
+        Some(""),
+        "The bash prompt (`PS1`) in `nix develop` shells.",
+    ),
+    setting(
+        "bash-prompt-prefix",
+        SettingType::String,
+        Some(""),
+        "A prefix prepended to the bash prompt (`PS1`) in `nix develop` shells.",
+    ),
+    setting(
+        "bash-prompt-suffix",
+        SettingType::String,
+        Some(""),
+        "A suffix appended to the bash prompt (`PS1`) in `nix develop` shells.",
+    ),
+    setting(
+        "build-poll-interval",
+        Integer,
+        Some("5"),
+        "How often (in seconds) to poll for locks.",
+    ),
+    setting(
+        "build-users-group",
+        SettingType::String,
+        None,
+        "The Unix group containing the build users used to run builds in multi-user installations.",
+    ),
+    setting(
+        "builders",
+        SettingType::String,
+        Some("@/etc/nix/machines"),
+        "A list of machines on which to perform builds.",
+    ),
+    setting(
+        "builders-use-substitutes",
+        Boolean,
+        Some("false"),
+        "Whether remote builders should fetch dependencies from their own substituters.",
+    ),
+    setting(
+        "commit-lockfile-summary",
+        SettingType::String,
+        Some(""),
+        "The commit summary to use when committing changed flake lock files.",
+    ),
+    aliased(
+        setting(
+            "compress-build-log",
+            Boolean,
+            Some("true"),
+            "Whether build logs should be compressed on disk.",
+        ),
+        &["build-compress-log"],
+    ),
+    setting(
+        "connect-timeout",
+        Integer,
+        Some("0"),
+        "The timeout (in seconds) for establishing connections in the binary cache substituter.",
+    ),
+    aliased(
+        setting(
+            "cores",
+            Integer,
+            Some("0"),
+            "The number of CPU cores each build may use, or 0 to use all of them.",
+        ),
+        &["build-cores"],
+    ),
+    only(
+        setting(
+            "deprecated-features",
+            StringList,
+            Some(""),
+            "Deprecated language features to allow again.",
+        ),
+        &[Dialect::Lix],
+    ),
+    setting(
+        "diff-hook",
+        Path,
+        Some(""),
+        "Absolute path to an executable capable of diffing build results.",
+    ),
+    setting(
+        "download-attempts",
+        Integer,
+        Some("5"),
+        "How often Nix will attempt to download a file before giving up.",
+    ),
+    setting(
+        "download-speed",
+        Integer,
+        Some("0"),
+        "The maximum download speed in kilobytes per second, or 0 for no limit.",
+    ),
+    setting(
+        "eval-cache",
+        Boolean,
+        Some("true"),
+        "Whether to use the flake evaluation cache.",
+    ),
+    setting(
+        "experimental-features",
+        StringList,
+        Some(""),
+        "Experimental Nix features to enable.",
+    ),
+    setting(
+        "extra-platforms",
+        StringList,
+        None,
+        "System types of executables that can be run on this machine in addition to `system`.",
+    ),
+    aliased(
+        setting(
+            "fallback",
+            Boolean,
+            Some("false"),
+            "Whether to fall back to building from source if a substitute fails.",
+        ),
+        &["build-fallback"],
+    ),
+    setting(
+        "filter-syscalls",
+        Boolean,
+        Some("true"),
+        "Whether to prevent certain dangerous system calls, such as creation of setuid executables, in builds.",
+    ),
+    setting(
+        "flake-registry",
+        SettingType::String,
+        Some("https://channels.nixos.org/flake-registry.json"),
+        "Path or URI of the global flake registry.",
+    ),
+    setting(
+        "fsync-metadata",
+        Boolean,
+        Some("true"),
+        "Whether SQLite should use fsync() to flush writes to disk.",
+    ),
+    setting(
+        "gc-reserved-space",
+        Integer,
+        Some("8388608"),
+        "Amount of reserved disk space for the garbage collector.",
+    ),
+    setting(
+        "hashed-mirrors",
+        StringList,
+        Some(""),
+        "Content-addressed mirrors to try before fetching a fixed-output derivation from its URL.",
+    ),
+    aliased(
+        setting(
+            "http-connections",
+            Integer,
+            Some("25"),
+            "The maximum number of parallel TCP connections used to fetch files from binary caches.",
+        ),
+        &["binary-caches-parallel-connections"],
+    ),
+    setting(
+        "http2",
+        Boolean,
+        Some("true"),
+        "Whether to enable HTTP/2 support.",
+    ),
+    setting(
+        "id-count",
+        Integer,
+        Some("8388608"),
+        "The number of UIDs/GIDs to use for dynamic ID allocation.",
+    ),
+    setting(
+        "ignored-acls",
+        StringList,
+        Some("security.csm security.selinux system.nfs4_acl"),
+        "Extended attributes to ignore when canonicalizing file metadata.",
+    ),
+    aliased(
+        setting(
+            "impersonate-linux-26",
+            Boolean,
+            Some("false"),
+            "Whether to make Linux builds report a 2.6 kernel version.",
+        ),
+        &["build-impersonate-linux-26"],
+    ),
+    aliased(
+        setting(
+            "keep-build-log",
+            Boolean,
+            Some("true"),
+            "Whether to store build logs.",
+        ),
+        &["build-keep-log"],
+    ),
+    aliased(
+        setting(
+            "keep-derivations",
+            Boolean,
+            Some("true"),
+            "Whether the garbage collector keeps the derivations from which non-garbage store paths were built.",
+        ),
+        &["gc-keep-derivations"],
+    ),
+    aliased(
+        setting(
+            "keep-env-derivations",
+            Boolean,
+            Some("false"),
+            "Whether to add the derivations of installed packages to the user environment.",
+        ),
+        &["env-keep-derivations"],
+    ),
+    setting(
+        "keep-failed",
+        Boolean,
+        Some("false"),
+        "Whether to keep temporary directories of failed builds.",
+    ),
+    setting(
+        "keep-going",
+        Boolean,
+        Some("false"),
+        "Whether to keep building derivations when another build fails.",
+    ),
+    aliased(
+        setting(
+            "keep-outputs",
+            Boolean,
+            Some("false"),
+            "Whether the garbage collector keeps the outputs of non-garbage derivations.",
+        ),
+        &["gc-keep-outputs"],
+    ),
+    only(
+        setting(
+            "lazy-trees",
+            Boolean,
+            Some("false"),
+            "Whether to avoid copying flake sources to the store until they are needed.",
+        ),
+        &[Dialect::Determinate],
+    ),
+    setting(
+        "log-lines",
+        Integer,
+        Some("10"),
+        "The number of lines of the tail of the log to show if a build fails.",
+    ),
+    aliased(
+        setting(
+            "max-build-log-size",
+            Integer,
+            Some("0"),
+            "The maximum number of bytes a builder can write to its log, or 0 for no limit.",
+        ),
+        &["build-max-log-size"],
+    ),
+    setting(
+        "max-free",
+        Integer,
+        Some("9223372036854775807"),
+        "When the garbage collector is triggered by `min-free`, the amount of free space to stop at.",
+    ),
+    aliased(
+        setting(
+            "max-jobs",
+            IntegerOrAuto,
+            Some("1"),
+            "The maximum number of jobs that Nix will try to build locally in parallel.",
+        ),
+        &["build-max-jobs"],
+    ),
+    aliased(
+        setting(
+            "max-silent-time",
+            Integer,
+            Some("0"),
+            "The number of seconds a builder can go without producing output before it is killed.",
+        ),
+        &["build-max-silent-time"],
+    ),
+    aliased(
+        setting(
+            "max-substitution-jobs",
+            Integer,
+            Some("16"),
+            "The maximum number of substitution jobs that Nix will try to run in parallel.",
+        ),
+        &["substitution-max-jobs"],
+    ),
+    setting(
+        "min-free",
+        Integer,
+        Some("0"),
+        "When free disk space drops below this many bytes during a build, the garbage collector is run.",
+    ),
+    setting(
+        "min-free-check-interval",
+        Integer,
+        Some("5"),
+        "Number of seconds between checking free disk space.",
+    ),
+    setting(
+        "nar-buffer-size",
+        Integer,
+        Some("33554432"),
+        "Maximum size of NARs before spilling them to disk.",
+    ),
+    setting(
+        "narinfo-cache-negative-ttl",
+        Integer,
+        Some("3600"),
+        "The TTL in seconds for negative lookups in binary caches.",
+    ),
+    setting(
+        "narinfo-cache-positive-ttl",
+        Integer,
+        Some("2592000"),
+        "The TTL in seconds for positive lookups in binary caches.",
+    ),
+    setting(
+        "netrc-file",
+        Path,
+        Some("/etc/nix/netrc"),
+        "The path of a netrc file used to obtain usernames and passwords for HTTP authentication.",
+    ),
+    setting(
+        "nix-path",
+        StringList,
+        None,
+        "The entries of the Nix search path, used to resolve `<...>` lookups.",
+    ),
+    setting(
+        "plugin-files",
+        PathList,
+        Some(""),
+        "Plugins to dynamically load at Nix initialization time.",
+    ),
+    setting(
+        "post-build-hook",
+        Path,
+        Some(""),
+        "An executable run after each build, for example to upload the results to a binary cache.",
+    ),
+    setting(
+        "pre-build-hook",
+        Path,
+        Some(""),
+        "An executable run before each build to set up additional sandbox paths.",
+    ),
+    setting(
+        "preallocate-contents",
+        Boolean,
+        Some("false"),
+        "Whether to preallocate files when writing objects with known size.",
+    ),
+    setting(
+        "print-missing",
+        Boolean,
+        Some("true"),
+        "Whether to print what paths need to be built or downloaded.",
+    ),
+    setting(
+        "pure-eval",
+        Boolean,
+        Some("false"),
+        "Whether to restrict file system and network access to files specified by cryptographic hash.",
+    ),
+    setting(
+        "require-drop-supplementary-groups",
+        Boolean,
+        Some("false"),
+        "Whether failing to drop supplementary groups before building is a fatal error.",
+    ),
+    only(
+        setting(
+            "repl-overlays",
+            PathList,
+            Some(""),
+            "Nix files that are applied as overlays to the scope of the REPL.",
+        ),
+        &[Dialect::Lix],
+    ),
+    setting(
+        "require-sigs",
+        Boolean,
+        Some("true"),
+        "Whether store paths copied into the store must be signed by a trusted key.",
+    ),
+    setting(
+        "restrict-eval",
+        Boolean,
+        Some("false"),
+        "Whether the evaluator only allows access to files in the search path and `allowed-uris`.",
+    ),
+    setting(
+        "run-diff-hook",
+        Boolean,
+        Some("false"),
+        "Whether to run the program specified by `diff-hook` when repeated builds produce different results.",
+    ),
+    aliased(
+        setting(
+            "sandbox",
+            Enum(&["true", "false", "relaxed"]),
+            None,
+            "Whether builds are performed in a sandboxed environment.",
+        ),
+        &["build-use-sandbox", "build-use-chroot"],
+    ),
+    setting(
+        "sandbox-build-dir",
+        Path,
+        Some("/build"),
+        "The build directory inside the sandbox.",
+    ),
+    setting(
+        "sandbox-dev-shm-size",
+        SettingType::String,
+        Some("50%"),
+        "The size of `/dev/shm` in the build sandbox on Linux.",
+    ),
+    setting(
+        "sandbox-fallback",
+        Boolean,
+        Some("true"),
+        "Whether to disable sandboxing when the kernel doesn't allow it.",
+    ),
+    aliased(
+        setting(
+            "sandbox-paths",
+            PathList,
+            None,
+            "Paths to bind-mount into the build sandbox.",
+        ),
+        &["build-sandbox-paths", "build-chroot-dirs"],
+    ),
+    setting(
+        "secret-key-files",
+        PathList,
+        Some(""),
+        "Files containing secret signing keys used to sign locally-built paths.",
+    ),
+    setting(
+        "show-trace",
+        Boolean,
+        Some("false"),
+        "Whether Nix should print out a stack trace in case of evaluation errors.",
+    ),
+    setting(
+        "ssl-cert-file",
+        Path,
+        None,
+        "The path of a file containing CA certificates used to authenticate `https://` downloads.",
+    ),
+    setting(
+        "stalled-download-timeout",
+        Integer,
+        Some("300"),
+        "The timeout (in seconds) for receiving data from servers during download.",
+    ),
+    setting(
+        "start-id",
+        Integer,
+        Some("872415232"),
+        "The first UID and GID to use for dynamic ID allocation.",
+    ),
+    setting(
+        "store",
+        SettingType::String,
+        Some("auto"),
+        "The URL of the Nix store to use for most operations.",
+    ),
+    aliased(
+        setting(
+            "substitute",
+            Boolean,
+            Some("true"),
+            "Whether to use substitutes.",
+        ),
+        &["build-use-substitutes"],
+    ),
+    aliased(
+        setting(
+            "substituters",
+            StringList,
+            Some("https://cache.nixos.org/"),
+            "The URLs of the substituters to be used.",
+        ),
+        &["binary-caches"],
+    ),
+    setting(
+        "sync-before-registering",
+        Boolean,
+        Some("false"),
+        "Whether to call sync() before registering a path as valid.",
+    ),
+    setting(
+        "system",
+        SettingType::String,
+        None,
+        "The system type of the current Nix installation.",
+    ),
+    setting(
+        "system-features",
+        StringList,
+        None,
+        "Optional features that the system this Nix is running on supports, for use with `requiredSystemFeatures`.",
+    ),
+    setting(
+        "tarball-ttl",
+        Integer,
+        Some("3600"),
+        "The number of seconds a downloaded tarball is considered fresh.",
+    ),
+    aliased(
+        setting(
+            "timeout",
+            Integer,
+            Some("0"),
+            "The number of seconds a builder can run before it is killed, or 0 for no limit.",
+        ),
+        &["build-timeout"],
+    ),
+    setting(
+        "trace-function-calls",
+        Boolean,
+        Some("false"),
+        "Whether to log function calls during evaluation.",
+    ),
+    setting(
+        "trace-verbose",
+        Boolean,
+        Some("false"),
+        "Whether `builtins.traceVerbose` should trace its first argument when evaluated.",
+    ),
+    aliased(
+        setting(
+            "trusted-public-keys",
+            StringList,
+            Some("cache.nixos.org-1:6NCHdD59X431o0gWypbMrAURkbJ16ZPMQFGspcDShjY="),
+            "The public keys used to verify signatures of store paths.",
+        ),
+        &["binary-cache-public-keys"],
+    ),
+    aliased(
+        setting(
+            "trusted-substituters",
+            StringList,
+            Some(""),
+            "Substituters that unprivileged users may additionally use.",
+        ),
+        &["trusted-binary-caches"],
+    ),
+    setting(
+        "trusted-users",
+        StringList,
+        Some("root"),
+        "The users and groups with additional rights when connecting to the Nix daemon.",
+    ),
+    setting(
+        "use-case-hack",
+        Boolean,
+        None,
+        "Whether to enable a macOS-specific hack for dealing with file name case collisions.",
+    ),
+    setting(
+        "use-cgroups",
+        Boolean,
+        Some("false"),
+        "Whether to execute builds inside cgroups on Linux.",
+    ),
+    setting(
+        "use-registries",
+        Boolean,
+        Some("true"),
+        "Whether to use flake registries to resolve flake references.",
+    ),
+    setting(
+        "use-sqlite-wal",
+        Boolean,
+        Some("true"),
+        "Whether SQLite should use WAL mode.",
+    ),
+    setting(
+        "use-xdg-base-directories",
+        Boolean,
+        Some("false"),
+        "Whether to place user state in XDG base directories instead of legacy dotfiles.",
+    ),
+    setting(
+        "user-agent-suffix",
+        SettingType::String,
+        Some(""),
+        "A string appended to the user agent in HTTP requests.",
+    ),
+    setting(
+        "warn-dirty",
+        Boolean,
+        Some("true"),
+        "Whether to warn about dirty Git/Mercurial trees.",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_names_aliases_and_extra_prefixes() {
+        assert_eq!(
+            SettingInfo::lookup("build-use-chroot").map(SettingInfo::name),
+            Some("sandbox")
+        );
+        assert_eq!(
+            SettingInfo::lookup("extra-substituters").map(SettingInfo::name),
+            Some("substituters")
+        );
+        assert_eq!(
+            SettingInfo::lookup("extra-platforms").map(SettingInfo::name),
+            Some("extra-platforms")
+        );
+        assert_eq!(SettingInfo::lookup("extra-cores"), None);
+        assert_eq!(SettingInfo::lookup("not-a-setting"), None);
+    }
+
+    #[test]
+    fn filters_by_dialect() {
+        assert!(SettingInfo::lookup_in("lazy-trees", Dialect::Determinate).is_some());
+        assert_eq!(SettingInfo::lookup_in("lazy-trees", Dialect::Nix), None);
+        assert_eq!(
+            SettingInfo::lookup_in("extra-repl-overlays", Dialect::Lix).map(SettingInfo::name),
+            Some("repl-overlays")
+        );
+        assert_eq!(SettingInfo::lookup_in("repl-overlays", Dialect::Nix), None);
+        assert!(SettingInfo::lookup_in("cores", Dialect::Lix).is_some());
+    }
+
+    #[test]
+    fn registry_is_consistent() {
+        for (i, info) in SettingInfo::all().iter().enumerate() {
+            for name in std::iter::once(&info.name).chain(info.aliases) {
+                assert_eq!(
+                    SettingInfo::all()
+                        .iter()
+                        .position(|other| other.name == *name || other.aliases.contains(name)),
+                    Some(i),
+                    "'{name}' is registered more than once"
+                );
+            }
+
+            assert!(!info.description.is_empty());
+        }
+    }
+
+    #[test]
+    fn looks_up_registered_settings() {
+        static HOSTS: SettingInfo = SettingInfo::new(
+            "registry-test-hosts",
+            StringList,
+            Some(""),
+            "Hosts of a made-up plugin.",
+        )
+        .with_aliases(&["registry-test-old-hosts"])
+        .with_dialects(&[Dialect::Lix]);
+        static CONFLICTING: SettingInfo =
+            SettingInfo::new("registry-test-cores", Integer, None, "").with_aliases(&["cores"]);
+
+        assert_eq!(SettingInfo::lookup("registry-test-hosts"), None);
+        assert!(SettingInfo::register(&HOSTS));
+        assert!(!SettingInfo::register(&CONFLICTING));
+
+        assert_eq!(
+            SettingInfo::lookup("extra-registry-test-old-hosts"),
+            Some(&HOSTS)
+        );
+        assert_eq!(
+            SettingInfo::lookup_in("registry-test-hosts", Dialect::Nix),
+            None
+        );
+        assert_eq!(SettingInfo::lookup("registry-test-cores"), None);
+        assert!(SettingInfo::registered().contains(&&HOSTS));
+    }
+}
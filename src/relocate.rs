@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+
+use crate::{NixConfig, SettingInfo, SettingType, SettingValue};
+
+impl NixConfig {
+    /// Rewrite the paths under `old_prefix` to be under `new_prefix` instead,
+    /// for example after moving a home directory, returning the names of the
+    /// settings that changed.
+    ///
+    /// Only settings the [registry](SettingInfo) knows to hold paths are
+    /// touched, along with kept `include` directives (see
+    /// [`IncludeMode::Keep`](crate::IncludeMode::Keep)). Prefixes match whole
+    /// path components, so `/nix` matches `/nix/store` but not `/nixos`. Both
+    /// sides of a `sandbox-paths` entry like `/bin/sh=/nix/store/...-bash/bin/sh?`
+    /// are rewritten.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let mut nix_conf = NixConfig::parse_string(
+    ///     "netrc-file = /home/alice/.netrc\nsecret-key-files = /home/alice/key /home/alicia/key\nbuild-hook-user = /home/alice"
+    ///         .into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.relocate_paths("/home/alice", "/Users/alice"),
+    ///     ["netrc-file", "secret-key-files"]
+    /// );
+    /// assert_eq!(nix_conf.settings().get("netrc-file").unwrap(), "/Users/alice/.netrc");
+    /// assert_eq!(
+    ///     nix_conf.settings().get("secret-key-files").unwrap(),
+    ///     "/Users/alice/key /home/alicia/key"
+    /// );
+    /// assert_eq!(nix_conf.settings().get("build-hook-user").unwrap(), "/home/alice");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn relocate_paths(&mut self, old_prefix: &str, new_prefix: &str) -> Vec<String> {
+        let old_prefix = old_prefix.trim_end_matches('/');
+        let new_prefix = new_prefix.trim_end_matches('/');
+        let mut changed = Vec::new();
+
+        for (name, value) in self.settings_mut() {
+            let Some(info) = SettingInfo::lookup(name) else {
+                continue;
+            };
+
+            let relocated = match info.setting_type() {
+                SettingType::Path => relocate(value.as_str(), old_prefix, new_prefix),
+                SettingType::PathList => {
+                    let items = value
+                        .as_list()
+                        .into_iter()
+                        .map(|item| {
+                            relocate_entry(item, old_prefix, new_prefix)
+                                .unwrap_or_else(|| item.to_owned())
+                        })
+                        .collect::<Vec<_>>();
+                    let items = items.join(" ");
+                    (items != value.as_list().join(" ")).then_some(items)
+                }
+                _ => None,
+            };
+
+            if let Some(relocated) = relocated {
+                *value = SettingValue::from(relocated);
+                changed.push(name.clone());
+            }
+        }
+
+        for include in &mut self.includes {
+            if let Some(relocated) =
+                relocate(&include.path.to_string_lossy(), old_prefix, new_prefix)
+            {
+                include.path = PathBuf::from(relocated);
+            }
+        }
+
+        changed
+    }
+}
+
+/// `path` with `old_prefix` replaced by `new_prefix`, if it starts with it.
+fn relocate(path: &str, old_prefix: &str, new_prefix: &str) -> Option<String> {
+    let rest = path.strip_prefix(old_prefix)?;
+    (rest.is_empty() || rest.starts_with('/')).then(|| format!("{new_prefix}{rest}"))
+}
+
+/// Like [`relocate`], for an entry of a path list, which may be
+/// `target=source`, optionally followed by `?`.
+fn relocate_entry(entry: &str, old_prefix: &str, new_prefix: &str) -> Option<String> {
+    let (entry, optional) = match entry.strip_suffix('?') {
+        Some(entry) => (entry, "?"),
+        None => (entry, ""),
+    };
+
+    let relocated = match entry.split_once('=') {
+        Some((target, source)) => {
+            let new_target = relocate(target, old_prefix, new_prefix);
+            let new_source = relocate(source, old_prefix, new_prefix);
+            if new_target.is_none() && new_source.is_none() {
+                return None;
+            }
+            format!(
+                "{}={}",
+                new_target.as_deref().unwrap_or(target),
+                new_source.as_deref().unwrap_or(source)
+            )
+        }
+        None => relocate(entry, old_prefix, new_prefix)?,
+    };
+
+    Some(format!("{relocated}{optional}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IncludeMode, ParseOptions};
+
+    #[test]
+    fn relocates_sandbox_paths_and_includes() {
+        let mut nix_conf = NixConfig::parse_string_with_options(
+            "sandbox-paths = /bin/sh=/nix/store/abc-bash/bin/sh /nix/var/cache? /nixos\ninclude /nix/etc/extra.conf\nnix-path = nixpkgs=/nix/var/nixpkgs".into(),
+            None,
+            &ParseOptions::new().includes(IncludeMode::Keep),
+        )
+        .unwrap();
+
+        assert_eq!(
+            nix_conf.relocate_paths("/nix/", "/opt/nix"),
+            ["sandbox-paths"]
+        );
+        assert_eq!(
+            nix_conf.settings().get("sandbox-paths").unwrap(),
+            "/bin/sh=/opt/nix/store/abc-bash/bin/sh /opt/nix/var/cache? /nixos"
+        );
+        assert_eq!(
+            nix_conf.settings().get("nix-path").unwrap(),
+            "nixpkgs=/nix/var/nixpkgs"
+        );
+        assert_eq!(
+            nix_conf.includes()[0].path(),
+            std::path::Path::new("/opt/nix/etc/extra.conf")
+        );
+
+        assert!(nix_conf.relocate_paths("/nix", "/opt/nix").is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+use std::io::Read;
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
+
+use crate::wipe::wipe_bytes;
+use crate::ParseError;
+
+/// How `include https://...` directives are fetched, see
+/// [`ParseOptions::remote_includes`](crate::ParseOptions::remote_includes).
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use nix_config_parser::{ParseOptions, RemoteIncludes};
+///
+/// let options = ParseOptions::new().remote_includes(
+///     RemoteIncludes::new()
+///         .timeout(Duration::from_secs(5))
+///         .max_size(64 * 1024)
+///         .pin(
+///             "https://configs.example.com/nix.conf",
+///             "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+///         )
+///         .require_pins(true),
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteIncludes {
+    timeout: Duration,
+    max_size: u64,
+    pins: IndexMap<String, String>,
+    require_pins: bool,
+    allow_http: bool,
+}
+
+impl Default for RemoteIncludes {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_size: 1024 * 1024,
+            pins: IndexMap::new(),
+            require_pins: false,
+            allow_http: false,
+        }
+    }
+}
+
+impl RemoteIncludes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long fetching a file may take in total. Defaults to 10 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The largest file to accept, in bytes. Defaults to 1 MiB.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Only accept the file at `url` if its SHA-256 hash is `sha256`, in hex.
+    /// A mismatch fails the parse, even for an `!include`.
+    pub fn pin(mut self, url: impl Into<String>, sha256: impl Into<String>) -> Self {
+        self.pins
+            .insert(url.into(), sha256.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Refuse to fetch URLs that have no [pin](RemoteIncludes::pin).
+    pub fn require_pins(mut self, enabled: bool) -> Self {
+        self.require_pins = enabled;
+        self
+    }
+
+    /// Also fetch `http://` URLs, for example from a mirror on the local
+    /// network. Consider [pinning](RemoteIncludes::pin) them.
+    pub fn allow_http(mut self, enabled: bool) -> Self {
+        self.allow_http = enabled;
+        self
+    }
+
+    /// Whether the include of `path` is a URL to fetch.
+    pub(crate) fn handles(&self, path: &str) -> bool {
+        path.starts_with("https://") || (self.allow_http && path.starts_with("http://"))
+    }
+
+    pub(crate) fn fetch(&self, url: &str) -> Result<String, ParseError> {
+        let failed = |message: String| ParseError::RemoteIncludeFailed(url.to_owned(), message);
+
+        let pin = self.pins.get(url);
+        if pin.is_none() && self.require_pins {
+            return Err(failed("no checksum is pinned for it".into()));
+        }
+
+        let body = self.get(url)?;
+
+        if let Some(pin) = pin {
+            let actual = format!("{:x}", Sha256::digest(&body));
+            if *pin != actual {
+                wipe_bytes(body);
+                return Err(ParseError::ChecksumMismatch(
+                    url.to_owned(),
+                    pin.clone(),
+                    actual,
+                ));
+            }
+        }
+
+        String::from_utf8(body).map_err(|e| {
+            wipe_bytes(e.into_bytes());
+            failed("not valid UTF-8".into())
+        })
+    }
+
+    /// The detached signatures of the file at `url`, see
+    /// [`TrustedKeys`](crate::TrustedKeys).
+    #[cfg(feature = "signatures")]
+    pub(crate) fn fetch_signatures(&self, url: &str) -> Result<String, ParseError> {
+        let url = format!("{url}.sig");
+        String::from_utf8(self.get(&url)?)
+            .map_err(|_| ParseError::RemoteIncludeFailed(url, "not valid UTF-8".into()))
+    }
+
+    /// Download the file at `url`, within the limits.
+    fn get(&self, url: &str) -> Result<Vec<u8>, ParseError> {
+        let failed = |message: String| ParseError::RemoteIncludeFailed(url.to_owned(), message);
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| failed(e.to_string()))?;
+        let response = client
+            .get(url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| failed(e.to_string()))?;
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_size)
+        {
+            return Err(failed(format!("larger than {} bytes", self.max_size)));
+        }
+
+        // Sized up front if possible, as growing it would free parts of it
+        // unwiped.
+        let mut body = Vec::with_capacity(
+            response
+                .content_length()
+                .map_or(0, |len| len.min(self.max_size + 1) as usize),
+        );
+        response
+            .take(self.max_size + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| failed(e.to_string()))?;
+        if body.len() as u64 > self.max_size {
+            wipe_bytes(body);
+            return Err(failed(format!("larger than {} bytes", self.max_size)));
+        }
+
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::path::Path;
+
+    use super::*;
+    use crate::{NixConfig, ParseOptions};
+
+    /// Serve `body` over HTTP to `requests` clients, returning its URL.
+    fn serve(body: &'static str, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/nix.conf", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request).unwrap();
+                // The client hangs up early when the file is too large.
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+
+        url
+    }
+
+    #[test]
+    fn fetches_pinned_includes() {
+        let body = "substituters = https://cache.example.com\n";
+        let url = serve(body, 3);
+        let sha256 = format!("{:x}", Sha256::digest(body));
+        let contents = format!("cores = 4\ninclude {url}\n");
+
+        let remote = RemoteIncludes::new().allow_http(true).require_pins(true);
+        let parse = |remote: RemoteIncludes| {
+            NixConfig::parse_string_with_options(
+                contents.clone(),
+                None,
+                &ParseOptions::new().remote_includes(remote),
+            )
+        };
+
+        let nix_conf = parse(remote.clone().pin(&url, &sha256)).unwrap();
+        assert_eq!(
+            nix_conf.settings().get("substituters").unwrap(),
+            "https://cache.example.com"
+        );
+
+        assert!(matches!(
+            parse(remote.clone().pin(&url, "0".repeat(64))),
+            Err(ParseError::ChecksumMismatch(_, _, actual)) if actual == sha256
+        ));
+        assert!(matches!(
+            parse(remote.clone().pin(&url, &sha256).max_size(8)),
+            Err(ParseError::RemoteIncludeFailed(..))
+        ));
+        assert!(matches!(
+            parse(remote),
+            Err(ParseError::RemoteIncludeFailed(_, message)) if message.contains("pinned")
+        ));
+    }
+
+    #[test]
+    fn reports_errors_inside_remote_includes() {
+        let url = serve("cores 4\n", 1);
+
+        let error = NixConfig::parse_string_with_options(
+            format!("include {url}\n"),
+            Some(Path::new("/etc/nix/nix.conf")),
+            &ParseOptions::new().remote_includes(RemoteIncludes::new().allow_http(true)),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            &error,
+            ParseError::InIncludedFile(path, Some(origin), inner)
+                if *path == Path::new(&url)
+                    && origin == Path::new("/etc/nix/nix.conf")
+                    && matches!(**inner, ParseError::MissingEquals(..))
+        ));
+    }
+}
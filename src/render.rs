@@ -0,0 +1,225 @@
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::{Origin, ParseError};
+
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl ParseError {
+    /// Render the error for humans, quoting the line of `source` (the
+    /// contents that failed to parse) it is about, with carets under the
+    /// problem, like the Rust compiler does, followed by the
+    /// [suggested fix](ParseError::did_you_mean), if any. Errors that aren't
+    /// about a particular line are rendered as just the message.
+    ///
+    /// ```rust
+    /// use nix_config_parser::{NixConfig, ParseOptions};
+    ///
+    /// let source = "cores = 4\ncorse = 8\n";
+    /// let error = NixConfig::parse_string_with_options(
+    ///     source.into(),
+    ///     None,
+    ///     &ParseOptions::strict(),
+    /// )
+    /// .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     error.render(source),
+    ///     "error: <unknown>:2: unknown setting 'corse'\n  |\n2 | corse = 8\n  | ^^^^^"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        self.render_with(source, false)
+    }
+
+    /// Like [`ParseError::render`], colored with ANSI escape codes for
+    /// terminals.
+    pub fn render_colored(&self, source: &str) -> String {
+        self.render_with(source, true)
+    }
+
+    fn render_with(&self, source: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{style}{text}{RESET}")
+            } else {
+                text.to_owned()
+            }
+        };
+
+        let mut out = format!(
+            "{}{}",
+            paint(BOLD_RED, "error"),
+            paint(BOLD, &format!(": {self}"))
+        );
+
+        let Some((line_number, range)) = self.location(source) else {
+            return out;
+        };
+        let line = source.lines().nth(line_number - 1).unwrap_or_default();
+        let range = range.start.min(line.len())..range.end.min(line.len());
+
+        // Keep tabs before the problem, so the carets line up.
+        let indent = line[..range.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let carets = "^".repeat(line[range].chars().count().max(1));
+
+        let number = line_number.to_string();
+        let gutter = " ".repeat(number.len());
+        let bar = paint(BOLD_BLUE, "|");
+        let _ = write!(
+            out,
+            "\n{gutter} {bar}\n{} {bar} {line}\n{gutter} {bar} {indent}{}",
+            paint(BOLD_BLUE, &number),
+            paint(BOLD_RED, &carets),
+        );
+        if let Some(suggestion) = self.did_you_mean() {
+            let _ = write!(
+                out,
+                "\n{gutter} {} {}: did you mean `{suggestion}`?",
+                paint(BOLD_BLUE, "="),
+                paint(BOLD, "help")
+            );
+        }
+
+        out
+    }
+
+    /// The 1-based line of `source` the error is about, and the range of the
+    /// problem in it.
+    fn location(&self, source: &str) -> Option<(usize, Range<usize>)> {
+        match self {
+            ParseError::IllegalConfiguration(text, _)
+            | ParseError::MissingEquals(text, _)
+            | ParseError::MissingValue(text, _)
+            | ParseError::MalformedInclude(text, _)
+            | ParseError::EmptyKey(text, _) => find(source, text.trim()),
+            ParseError::IncludedFileNotFound(path, _)
+            | ParseError::IncludedFileUnreadable(path, _, _)
+            | ParseError::InIncludedFile(path, _, _)
+            | ParseError::IncludeNotAllowed(path, _) => {
+                find_include(source, |include| path.ends_with(include))
+            }
+            #[cfg(feature = "signatures")]
+            ParseError::UnverifiedInclude(path, _) => {
+                find_include(source, |include| path.ends_with(include))
+            }
+            #[cfg(feature = "http")]
+            ParseError::RemoteIncludeFailed(url, _) | ParseError::ChecksumMismatch(url, _, _) => {
+                find_include(source, |include| include == Path::new(url))
+            }
+            #[cfg(feature = "s3")]
+            ParseError::ObjectFetchFailed(url, _) => {
+                find_include(source, |include| include == Path::new(url))
+            }
+            ParseError::DirectiveRejected(word, _, _) => {
+                source.lines().enumerate().find_map(|(index, line)| {
+                    let trimmed = line.trim();
+                    let start = line.len() - line.trim_start().len();
+                    (trimmed.split_whitespace().next() == Some(word.as_str()))
+                        .then(|| (index + 1, start..start + trimmed.len()))
+                })
+            }
+            ParseError::UnknownSetting(name, origin) => {
+                let line_number = origin_line(origin)?;
+                let line = source.lines().nth(line_number - 1)?;
+                let start = line.find(name.as_str())?;
+                Some((line_number, start..start + name.len()))
+            }
+            ParseError::InvalidValue(problem) => {
+                let line_number = origin_line(problem.origin()?)?;
+                let line = source.lines().nth(line_number - 1)?;
+                let value = line.split_once('=').map_or(line, |(_, value)| value).trim();
+                let start = line.rfind(value)?;
+                Some((line_number, start..start + value.len()))
+            }
+            ParseError::InvalidEncoding(offset, _) if source.is_char_boundary(*offset) => {
+                let line_start = source[..*offset].rfind('\n').map_or(0, |index| index + 1);
+                let line_number = source[..line_start].matches('\n').count() + 1;
+                let column = offset - line_start;
+                Some((line_number, column..column + 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The first line of `source` containing `needle`, and where.
+fn find(source: &str, needle: &str) -> Option<(usize, Range<usize>)> {
+    if needle.is_empty() {
+        return None;
+    }
+
+    source.lines().enumerate().find_map(|(index, line)| {
+        let start = line.find(needle)?;
+        Some((index + 1, start..start + needle.len()))
+    })
+}
+
+/// The first `include` or `!include` line of `source` whose path `matches`,
+/// and where the path is.
+fn find_include(source: &str, matches: impl Fn(&Path) -> bool) -> Option<(usize, Range<usize>)> {
+    source.lines().enumerate().find_map(|(index, line)| {
+        let mut tokens = line.split_whitespace();
+        if !matches!(tokens.next(), Some("include" | "!include")) {
+            return None;
+        }
+
+        let include = tokens.next()?;
+        if !matches(Path::new(include)) {
+            return None;
+        }
+        let start = line.rfind(include)?;
+        Some((index + 1, start..start + include.len()))
+    })
+}
+
+fn origin_line(origin: &Origin) -> Option<usize> {
+    match origin {
+        Origin::File { line, .. } | Origin::Environment { line, .. } => Some(*line),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NixConfig;
+
+    #[test]
+    fn quotes_illegal_lines_and_missing_includes() {
+        let source = "cores = 4\n\tbad line # comment\n";
+        let error = NixConfig::parse_string(source.into(), None).unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "error: missing ' = ' between the name and the value in line 'bad line' in '<unknown>'\n  |\n2 | \tbad line # comment\n  | \t^^^^^^^^"
+        );
+        assert!(error
+            .render_colored(source)
+            .starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: missing"));
+
+        let source = "max-jobs: 4\n";
+        let error = NixConfig::parse_string(source.into(), None).unwrap_err();
+        assert!(error
+            .render(source)
+            .ends_with("1 | max-jobs: 4\n  | ^^^^^^^^^^^\n  = help: did you mean `max-jobs = 4`?"));
+
+        let source = "include /does/not/exist.conf\n";
+        let error = NixConfig::parse_string(source.into(), None).unwrap_err();
+        assert!(error
+            .render(source)
+            .ends_with("1 | include /does/not/exist.conf\n  |         ^^^^^^^^^^^^^^^^^^^^"));
+
+        let error = ParseError::FileNotFound("/etc/nix/nix.conf".into());
+        assert_eq!(
+            error.render(""),
+            "error: file '/etc/nix/nix.conf' not found"
+        );
+    }
+}
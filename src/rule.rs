@@ -0,0 +1,232 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{Diagnostic, NixConfig, NixConfigDocument, SettingValue, Severity};
+
+/// A check of setting values, such as an organization's policy, that runs
+/// alongside the built-in checks of [`NixConfig::lint`]. Register rules in a
+/// [`RuleRegistry`] and run them with [`NixConfig::lint_with`].
+///
+/// ```rust
+/// use nix_config_parser::{NixConfig, SettingValue, ValidationRule};
+///
+/// /// Only allow substituters hosted by the organization.
+/// struct InternalSubstitutersOnly;
+///
+/// impl ValidationRule for InternalSubstitutersOnly {
+///     fn name(&self) -> &str {
+///         "internal-substituters-only"
+///     }
+///
+///     fn applies_to(&self, name: &str) -> bool {
+///         name == "substituters" || name == "extra-substituters"
+///     }
+///
+///     fn check(&self, name: &str, value: &SettingValue, _: &NixConfig) -> Result<(), String> {
+///         match value
+///             .as_list()
+///             .into_iter()
+///             .find(|url| !url.starts_with("https://cache.example.com"))
+///         {
+///             Some(url) => Err(format!("'{name}' includes the external cache {url}")),
+///             None => Ok(()),
+///         }
+///     }
+/// }
+/// ```
+pub trait ValidationRule: Send + Sync {
+    /// The short, stable identifier of the rule, used as the
+    /// [code](Diagnostic::code) of its diagnostics.
+    fn name(&self) -> &str;
+
+    /// Whether the rule checks the setting `name`, as it is written in the
+    /// config. All settings are checked by default.
+    fn applies_to(&self, name: &str) -> bool {
+        let _ = name;
+        true
+    }
+
+    /// Check the setting `name`, returning a message describing the problem
+    /// if there is one. `config` is the config the setting is part of.
+    fn check(&self, name: &str, value: &SettingValue, config: &NixConfig) -> Result<(), String>;
+}
+
+/// A set of [`ValidationRule`]s to run with [`NixConfig::lint_with`]. Rules run
+/// in the order they were added. Cloning a registry shares its rules.
+#[derive(Clone, Default)]
+pub struct RuleRegistry {
+    rules: Vec<Arc<dyn ValidationRule>>,
+}
+
+impl RuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `rule`, replacing a rule of the same name if there is one.
+    pub fn with(mut self, rule: impl ValidationRule + 'static) -> Self {
+        self.register(rule);
+        self
+    }
+
+    /// Add `rule`, replacing a rule of the same name if there is one.
+    pub fn register(&mut self, rule: impl ValidationRule + 'static) {
+        self.rules
+            .retain(|registered| registered.name() != rule.name());
+        self.rules.push(Arc::new(rule));
+    }
+
+    /// The names of the registered rules, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.name())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Run every rule on every setting of `config` it applies to.
+    pub(crate) fn check(&self, config: &NixConfig) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for (name, value) in config.settings() {
+            for rule in &self.rules {
+                if !rule.applies_to(name) {
+                    continue;
+                }
+
+                if let Err(message) = rule.check(name, value, config) {
+                    diagnostics.push(Diagnostic {
+                        code: rule.name().to_owned(),
+                        severity: Severity::Warning,
+                        name: name.clone(),
+                        message,
+                        origin: config.origin(name).cloned(),
+                        fix: None,
+                    });
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+impl fmt::Debug for RuleRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+impl NixConfig {
+    /// Like [`NixConfig::lint`], followed by the diagnostics of the `rules`,
+    /// which are ordered by setting and then by rule.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, RuleRegistry, SettingValue, ValidationRule};
+    ///
+    /// struct NoSandboxFallback;
+    ///
+    /// impl ValidationRule for NoSandboxFallback {
+    ///     fn name(&self) -> &str {
+    ///         "no-sandbox-fallback"
+    ///     }
+    ///
+    ///     fn applies_to(&self, name: &str) -> bool {
+    ///         name == "sandbox-fallback"
+    ///     }
+    ///
+    ///     fn check(&self, _: &str, value: &SettingValue, _: &NixConfig) -> Result<(), String> {
+    ///         match value.as_bool() {
+    ///             Some(true) => Err("builds must not run unsandboxed".into()),
+    ///             _ => Ok(()),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let rules = RuleRegistry::new().with(NoSandboxFallback);
+    /// let nix_conf = NixConfig::parse_string("sandbox-fallback = true".into(), None)?;
+    ///
+    /// let diagnostics = nix_conf.lint_with(&rules);
+    /// assert_eq!(
+    ///     diagnostics[0].to_string(),
+    ///     "<unknown>:1: builds must not run unsandboxed [no-sandbox-fallback]"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lint_with(&self, rules: &RuleRegistry) -> Vec<Diagnostic> {
+        let mut diagnostics = self.lint();
+        diagnostics.extend(rules.check(self));
+        diagnostics
+    }
+}
+
+impl NixConfigDocument {
+    /// Like [`NixConfigDocument::lint`], followed by the diagnostics of the
+    /// `rules`, see [`NixConfig::lint_with`].
+    pub fn lint_with(&self, rules: &RuleRegistry) -> Vec<Diagnostic> {
+        let mut diagnostics = self.lint();
+        if !rules.is_empty() {
+            diagnostics.extend(self.without_ignored(rules.check(&self.to_config())));
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MaxValue(&'static str, i64);
+
+    impl ValidationRule for MaxValue {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn applies_to(&self, name: &str) -> bool {
+            name == "cores" || name == "max-jobs"
+        }
+
+        fn check(&self, name: &str, value: &SettingValue, _: &NixConfig) -> Result<(), String> {
+            match value.as_int() {
+                Some(n) if n > self.1 => Err(format!("'{name}' must be at most {}", self.1)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn runs_rules_alongside_builtin_lints() {
+        let rules = RuleRegistry::new()
+            .with(MaxValue("max-value", 8))
+            .with(MaxValue("at-most-16", 16))
+            .with(MaxValue("max-value", 4));
+        assert_eq!(
+            rules.names().collect::<Vec<_>>(),
+            ["at-most-16", "max-value"]
+        );
+
+        let doc = NixConfigDocument::parse_string(
+            "cores = 4\nmax-jobs = 32\nbuild-cores = 8\nsandbox = true".into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.lint_with(&rules)
+                .iter()
+                .map(|diagnostic| (diagnostic.code(), diagnostic.name()))
+                .collect::<Vec<_>>(),
+            [
+                ("deprecated-alias", "build-cores"),
+                ("at-most-16", "max-jobs"),
+                ("max-value", "max-jobs"),
+            ]
+        );
+    }
+}
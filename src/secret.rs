@@ -0,0 +1,43 @@
+use crate::SettingValue;
+
+/// What is left of a secret value once it is redacted, in a
+/// [support bundle](crate::support) or when a [`NixConfig`](crate::NixConfig)
+/// is displayed.
+pub const REDACTED: &str = "<redacted>";
+
+/// Whether the value of `name` is a secret, like the tokens in
+/// `access-tokens`. Settings holding paths to secrets, such as
+/// `secret-key-files`, are not.
+pub fn is_secret(name: &str) -> bool {
+    let name = name.strip_prefix("extra-").unwrap_or(name);
+    if name.ends_with("-file") || name.ends_with("-files") {
+        return false;
+    }
+
+    name == "access-tokens"
+        || ["password", "token", "secret"]
+            .iter()
+            .any(|word| name.contains(word))
+}
+
+/// Redact `value` if `name` is a secret. For `access-tokens`, the hosts are
+/// kept.
+pub(crate) fn redact(name: &str, value: &SettingValue) -> SettingValue {
+    if !is_secret(name) || value.is_empty() {
+        return value.clone();
+    }
+
+    if name.ends_with("access-tokens") {
+        let items = value
+            .as_list()
+            .into_iter()
+            .map(|item| match item.split_once('=') {
+                Some((host, _)) => format!("{host}={REDACTED}"),
+                None => REDACTED.to_owned(),
+            })
+            .collect::<Vec<_>>();
+        return items.join(" ").into();
+    }
+
+    REDACTED.into()
+}
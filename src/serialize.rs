@@ -0,0 +1,587 @@
+use thiserror::Error;
+
+use crate::{IncludeMode, NixConfig, ParseOptions, SettingName};
+
+/// Options that influence how a [`NixConfig`] is rendered back into the
+/// `nix.conf` format.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, rename_all = "kebab-case")
+)]
+pub struct SerializeOptions {
+    pub(crate) quoting: bool,
+    pub(crate) hash_escapes: bool,
+    pub(crate) annotate: bool,
+    pub(crate) sorted: bool,
+    pub(crate) resolve_env: bool,
+}
+
+impl SerializeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap values that would not survive Nix's whitespace tokenization in
+    /// `"..."`, as understood by [`ParseOptions::quoting`](crate::ParseOptions::quoting).
+    pub fn quoting(mut self, enabled: bool) -> Self {
+        self.quoting = enabled;
+        self
+    }
+
+    /// Write `#` in values as `\#`, as understood by
+    /// [`ParseOptions::hash_escapes`](crate::ParseOptions::hash_escapes). Without
+    /// this, values containing a `#` are refused, as Nix would read everything
+    /// after it as a comment.
+    pub fn hash_escapes(mut self, enabled: bool) -> Self {
+        self.hash_escapes = enabled;
+        self
+    }
+
+    /// End each setting's line with a comment saying where its value came
+    /// from, such as `# from /etc/nix/nix.conf:12`, for settings with a known
+    /// [`Origin`](crate::Origin).
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, SerializeOptions};
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "cores = 4".into(),
+    ///     Some(std::path::Path::new("/etc/nix/nix.conf")),
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.to_string_with_options(&SerializeOptions::new().annotate(true))?,
+    ///     "cores = 4  # from /etc/nix/nix.conf:1\n"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn annotate(mut self, enabled: bool) -> Self {
+        self.annotate = enabled;
+        self
+    }
+
+    /// Render settings sorted by name, as [`NixConfig::sorted`] does, so the
+    /// output only depends on the settings and not the order they were added
+    /// in. This is meant for golden files and snapshot tests.
+    ///
+    /// The sorted output for a given config (and otherwise equal options) is
+    /// stable: it only changes in a release with a breaking version bump.
+    pub fn sorted(mut self, enabled: bool) -> Self {
+        self.sorted = enabled;
+        self
+    }
+
+    /// Replace references to environment variables such as `@env:GITHUB_TOKEN`
+    /// in values with the variables' values, see [`NixConfig::resolve_env`].
+    pub fn resolve_env(mut self, enabled: bool) -> Self {
+        self.resolve_env = enabled;
+        self
+    }
+}
+
+impl NixConfig {
+    /// Render the settings in the `nix.conf` format, one `name = value` line per
+    /// setting, in insertion order. Include directives kept with
+    /// [`IncludeMode::Keep`] are written last, so the included files still
+    /// take precedence over the settings here.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, ParseOptions, SerializeOptions};
+    ///
+    /// let mut nix_conf = NixConfig::new();
+    /// nix_conf.insert("netrc-file".try_into()?, "/Users/First  Last/.netrc");
+    ///
+    /// let rendered = nix_conf.to_string_with_options(&SerializeOptions::new().quoting(true))?;
+    /// assert_eq!(rendered, "netrc-file = \"/Users/First  Last/.netrc\"\n");
+    ///
+    /// let reparsed =
+    ///     NixConfig::parse_string_with_options(rendered, None, &ParseOptions::new().quoting(true))?;
+    /// assert_eq!(reparsed, nix_conf);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_string_with_options(
+        &self,
+        options: &SerializeOptions,
+    ) -> Result<String, SerializeError> {
+        if options.resolve_env {
+            let options = SerializeOptions {
+                resolve_env: false,
+                ..options.clone()
+            };
+            return self.resolve_env()?.to_string_with_options(&options);
+        }
+
+        if options.sorted {
+            let options = SerializeOptions {
+                sorted: false,
+                ..options.clone()
+            };
+            return self.sorted().to_string_with_options(&options);
+        }
+
+        let mut out = String::new();
+
+        let mut groups: Vec<&str> = Vec::new();
+        for name in self.settings().keys() {
+            match self.group(name) {
+                Some(group) if !groups.contains(&group) => groups.push(group),
+                _ => {}
+            }
+        }
+
+        for group in std::iter::once(None).chain(groups.into_iter().map(Some)) {
+            if let Some(group) = group {
+                if group.contains(['\n', '\r']) {
+                    return Err(SerializeError::InvalidGroupLabel(group.to_owned()));
+                }
+
+                if !out.is_empty() {
+                    out.push('\n');
+                }
+                out.push_str(&format!("# --- {group} ---\n"));
+            }
+
+            for (name, value) in self.settings() {
+                if self.group(name) != group {
+                    continue;
+                }
+
+                out.push_str(name);
+                out.push_str(" =");
+
+                if !value.is_empty() {
+                    out.push(' ');
+                    out.push_str(&render_value(name, value, options)?);
+                }
+
+                if let Some(origin) = self.origin(name).filter(|_| options.annotate) {
+                    let origin = origin.to_string().replace(['\n', '\r'], " ");
+                    out.push_str(&format!("  # from {origin}"));
+                }
+
+                out.push('\n');
+            }
+        }
+
+        for include in self.includes() {
+            let directive = if include.is_optional() {
+                "!include"
+            } else {
+                "include"
+            };
+            let path = include.path().to_str().ok_or_else(|| {
+                SerializeError::UnrepresentableValue(
+                    directive.to_owned(),
+                    include.path().display().to_string(),
+                )
+            })?;
+
+            out.push_str(&format!(
+                "{directive} {}\n",
+                render_value(directive, path, options)?
+            ));
+        }
+
+        Ok(out)
+    }
+
+    /// A copy of this config with its settings sorted by name. Groups are
+    /// rendered in the order of their first setting, so they end up sorted by
+    /// that as well.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, SerializeOptions};
+    ///
+    /// let a = NixConfig::parse_string("max-jobs = 2\ncores = 4".into(), None)?;
+    /// let b = NixConfig::parse_string("cores = 4\nmax-jobs = 2".into(), None)?;
+    ///
+    /// assert_eq!(a.sorted().settings().keys().next().unwrap(), "cores");
+    ///
+    /// let options = SerializeOptions::new().sorted(true);
+    /// assert_eq!(
+    ///     a.to_string_with_options(&options)?,
+    ///     b.to_string_with_options(&options)?
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sorted(&self) -> NixConfig {
+        let mut sorted = self.clone();
+        sorted.settings.sort_keys();
+        sorted.origins.sort_keys();
+        sorted.groups.sort_keys();
+        sorted
+    }
+
+    /// Render the settings in the `nix.conf` format like
+    /// [`NixConfig::to_string_with_options`] does with the default options, and
+    /// verify that the result parses back into an identical config.
+    ///
+    /// Setting names that aren't a valid [`SettingName`] are refused up front.
+    ///
+    /// ```rust
+    /// use nix_config_parser::{NixConfig, SerializeError};
+    ///
+    /// let mut nix_conf = NixConfig::new();
+    /// nix_conf
+    ///     .settings_mut()
+    ///     .insert("trusted users".into(), "root".into());
+    ///
+    /// assert!(matches!(
+    ///     nix_conf.to_string_checked(),
+    ///     Err(SerializeError::InvalidSettingName(_))
+    /// ));
+    /// ```
+    pub fn to_string_checked(&self) -> Result<String, SerializeError> {
+        for name in self.settings().keys() {
+            if !SettingName::is_valid(name) {
+                return Err(SerializeError::InvalidSettingName(name.to_owned()));
+            }
+        }
+
+        let rendered = self.to_string_with_options(&SerializeOptions::default())?;
+        let reparsed = NixConfig::parse_string_with_options(
+            rendered.clone(),
+            None,
+            &ParseOptions::new().includes(IncludeMode::Keep),
+        )
+        .map_err(SerializeError::Reparse)?;
+
+        if let Some((name, _)) = self
+            .settings()
+            .iter()
+            .find(|(name, value)| reparsed.settings().get(*name) != Some(value))
+        {
+            return Err(SerializeError::RoundTripMismatch(name.to_owned()));
+        }
+
+        if let Some(name) = reparsed
+            .settings()
+            .keys()
+            .find(|name| !self.settings().contains_key(*name))
+        {
+            return Err(SerializeError::RoundTripMismatch(name.to_owned()));
+        }
+
+        Ok(rendered)
+    }
+}
+
+pub(crate) fn render_value(
+    name: &str,
+    value: &str,
+    options: &SerializeOptions,
+) -> Result<String, SerializeError> {
+    if value.contains(['\n', '\r']) {
+        return Err(SerializeError::UnrepresentableValue(
+            name.to_owned(),
+            value.to_owned(),
+        ));
+    }
+
+    if value.contains('#') && !options.hash_escapes {
+        return Err(SerializeError::UnrepresentableValue(
+            name.to_owned(),
+            value.to_owned(),
+        ));
+    }
+
+    let mut rendered = if !needs_quoting(value, options.quoting) {
+        value.to_owned()
+    } else if options.quoting {
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push('"');
+        for c in value.chars() {
+            if c == '"' || c == '\\' {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    } else {
+        return Err(SerializeError::UnrepresentableValue(
+            name.to_owned(),
+            value.to_owned(),
+        ));
+    };
+
+    if options.hash_escapes {
+        rendered = rendered.replace('#', "\\#");
+    }
+
+    Ok(rendered)
+}
+
+/// Whether `value` would come back differently after being split on whitespace
+/// and re-joined with single spaces, or, if it is read back with `quoting`, has
+/// a token that would be mistaken for a quoted one.
+fn needs_quoting(value: &str, quoting: bool) -> bool {
+    let mut previous_was_space = true;
+    for c in value.chars() {
+        match c {
+            '"' if quoting && previous_was_space => return true,
+            ' ' if previous_was_space => return true,
+            ' ' => previous_was_space = true,
+            '\t' => return true,
+            _ => previous_was_space = false,
+        }
+    }
+
+    previous_was_space && !value.is_empty()
+}
+
+/// An error that occurred while attempting to render a [`NixConfig`].
+#[derive(Debug, Error)]
+pub enum SerializeError {
+    #[error("value '{1}' of setting '{0}' cannot be represented in nix.conf")]
+    UnrepresentableValue(String, String),
+    #[error("group label '{0}' cannot be represented in nix.conf")]
+    InvalidGroupLabel(String),
+    #[error("setting name '{0}' cannot be represented in nix.conf")]
+    InvalidSettingName(String),
+    #[error("setting '{0}' does not survive being rendered and parsed again")]
+    RoundTripMismatch(String),
+    #[error("rendered config could not be parsed again: {0}")]
+    Reparse(#[source] crate::ParseError),
+    #[error("environment variable '{1}' referred to by setting '{0}' is not set")]
+    MissingEnvironmentVariable(String, String),
+    #[error("environment variable '{1}' referred to by setting '{0}' is not valid unicode")]
+    InvalidEnvironmentVariable(String, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn renders_in_insertion_order() {
+        let nix_conf = NixConfig::parse_string(
+            "cores = 4242\nexperimental-features = flakes nix-command\nsubstituters =".into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            nix_conf
+                .to_string_with_options(&SerializeOptions::new())
+                .unwrap(),
+            "cores = 4242\nexperimental-features = flakes nix-command\nsubstituters =\n"
+        );
+    }
+
+    #[test]
+    fn annotated_output_still_parses() {
+        let nix_conf = NixConfig::parse_string(
+            "cores = 4\nsubstituters =\n".into(),
+            Some(std::path::Path::new("/etc/nix/nix.conf")),
+        )
+        .unwrap();
+
+        let annotated = nix_conf
+            .to_string_with_options(&SerializeOptions::new().annotate(true))
+            .unwrap();
+        assert_eq!(
+            annotated,
+            "cores = 4  # from /etc/nix/nix.conf:1\nsubstituters =  # from /etc/nix/nix.conf:2\n"
+        );
+        assert_eq!(
+            NixConfig::parse_string(annotated, None).unwrap().settings(),
+            nix_conf.settings()
+        );
+    }
+
+    #[test]
+    fn sorted_output_ignores_construction_order() {
+        let mut a = NixConfig::new();
+        a.settings_mut().insert("warn-dirty".into(), "false".into());
+        a.settings_mut()
+            .insert("substituters".into(), "https://cache.nixos.org".into());
+        a.settings_mut().insert("cores".into(), "4".into());
+        a.set_group("substituters", "caching");
+
+        let mut b = NixConfig::new();
+        b.settings_mut()
+            .insert("substituters".into(), "https://cache.nixos.org".into());
+        b.settings_mut().insert("cores".into(), "4".into());
+        b.settings_mut().insert("warn-dirty".into(), "false".into());
+        b.set_group("substituters", "caching");
+
+        let options = SerializeOptions::new().sorted(true);
+        let rendered = a.to_string_with_options(&options).unwrap();
+        assert_eq!(rendered, b.to_string_with_options(&options).unwrap());
+        assert_eq!(
+            rendered,
+            "cores = 4\nwarn-dirty = false\n\n# --- caching ---\nsubstituters = https://cache.nixos.org\n"
+        );
+    }
+
+    #[test]
+    fn renders_groups_under_headers() {
+        let mut nix_conf = NixConfig::parse_string(
+            "substituters = https://cache.nixos.org\ncores = 4\nkeep-outputs = true\ntrusted-public-keys = cache.nixos.org-1:6NCHdD59X431o0gWypbMrAURkbJ16ZPMQFGspcDShjY=\nmax-jobs = auto".into(),
+            None,
+        )
+        .unwrap();
+        nix_conf.set_group("substituters", "caching");
+        nix_conf.set_group("keep-outputs", "development");
+        nix_conf.set_group("trusted-public-keys", "caching");
+
+        let rendered = nix_conf.to_string_checked().unwrap();
+        assert_eq!(
+            rendered,
+            "cores = 4\n\
+             max-jobs = auto\n\
+             \n\
+             # --- caching ---\n\
+             substituters = https://cache.nixos.org\n\
+             trusted-public-keys = cache.nixos.org-1:6NCHdD59X431o0gWypbMrAURkbJ16ZPMQFGspcDShjY=\n\
+             \n\
+             # --- development ---\n\
+             keep-outputs = true\n"
+        );
+
+        nix_conf.set_group("cores", "two\nlines");
+        match nix_conf.to_string_with_options(&SerializeOptions::new()) {
+            Err(SerializeError::InvalidGroupLabel(group)) => assert_eq!(group, "two\nlines"),
+            _ => panic!("a group label with a line break should be refused"),
+        }
+    }
+
+    #[test]
+    fn requotes_values_when_needed() {
+        let mut nix_conf = NixConfig::new();
+        let settings = nix_conf.settings_mut();
+        settings.insert("plain".into(), "/Users/First Last/.netrc".into());
+        settings.insert("spaces".into(), " leading  and double ".into());
+        settings.insert("quotes".into(), "\"quoted\" \\ value".into());
+        settings.insert("middle".into(), "a \"b\"".into());
+
+        let rendered = nix_conf
+            .to_string_with_options(&SerializeOptions::new().quoting(true))
+            .unwrap();
+
+        assert_eq!(
+            rendered,
+            "plain = /Users/First Last/.netrc\n\
+             spaces = \" leading  and double \"\n\
+             quotes = \"\\\"quoted\\\" \\\\ value\"\n\
+             middle = \"a \\\"b\\\"\"\n"
+        );
+
+        let reparsed = NixConfig::parse_string_with_options(
+            rendered,
+            None,
+            &ParseOptions::new().quoting(true),
+        )
+        .unwrap();
+        assert_eq!(reparsed, nix_conf);
+    }
+
+    #[test]
+    fn refuses_unrepresentable_values() {
+        let mut nix_conf = NixConfig::new();
+        nix_conf
+            .settings_mut()
+            .insert("netrc-file".into(), "/Users/First  Last/.netrc".into());
+
+        match nix_conf.to_string_with_options(&SerializeOptions::new()) {
+            Err(SerializeError::UnrepresentableValue(name, _)) => assert_eq!(name, "netrc-file"),
+            _ => panic!("double spaces without quoting should be unrepresentable"),
+        }
+
+        nix_conf
+            .settings_mut()
+            .insert("netrc-file".into(), "first\nsecond".into());
+
+        match nix_conf.to_string_with_options(&SerializeOptions::new().quoting(true)) {
+            Err(SerializeError::UnrepresentableValue(name, _)) => assert_eq!(name, "netrc-file"),
+            _ => panic!("line breaks should be unrepresentable even with quoting"),
+        }
+    }
+
+    #[test]
+    fn checked_rendering_round_trips() {
+        let nix_conf = NixConfig::parse_string(
+            "cores = 4242\nexperimental-features = flakes nix-command\nsubstituters =".into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            nix_conf.to_string_checked().unwrap(),
+            nix_conf
+                .to_string_with_options(&SerializeOptions::new())
+                .unwrap()
+        );
+
+        for (name, value) in [
+            ("", "value"),
+            ("has space", "value"),
+            ("has#hash", "value"),
+            ("include", "/etc/nix/other.conf"),
+            ("cores", "#42"),
+            ("cores", "4\n2"),
+        ] {
+            let mut nix_conf = NixConfig::new();
+            nix_conf.settings_mut().insert(name.into(), value.into());
+
+            assert!(
+                nix_conf.to_string_checked().is_err(),
+                "'{name} = {value}' should have been refused"
+            );
+        }
+    }
+
+    #[test]
+    fn escapes_or_refuses_hashes() {
+        let mut nix_conf = NixConfig::new();
+        let settings = nix_conf.settings_mut();
+        settings.insert(
+            "substituters".into(),
+            "https://cache.example.com/#frag https://cache.nixos.org".into(),
+        );
+        settings.insert("backslash".into(), "a\\#b".into());
+        settings.insert("quoted".into(), "\"#  x".into());
+
+        match nix_conf.to_string_with_options(&SerializeOptions::new().quoting(true)) {
+            Err(SerializeError::UnrepresentableValue(name, _)) => assert_eq!(name, "substituters"),
+            _ => panic!("a # without escapes should be unrepresentable"),
+        }
+
+        for quoting in [false, true] {
+            let mut nix_conf = nix_conf.clone();
+            if !quoting {
+                nix_conf.settings_mut().shift_remove("quoted");
+            }
+
+            let rendered = nix_conf
+                .to_string_with_options(
+                    &SerializeOptions::new().quoting(quoting).hash_escapes(true),
+                )
+                .unwrap();
+
+            let reparsed = NixConfig::parse_string_with_options(
+                rendered,
+                None,
+                &ParseOptions::new().quoting(quoting).hash_escapes(true),
+            )
+            .unwrap();
+            assert_eq!(reparsed, nix_conf);
+        }
+    }
+}
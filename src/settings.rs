@@ -0,0 +1,292 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::{NixConfig, SettingValue};
+
+/// A typed view of some settings of a [`NixConfig`], usually derived with
+/// `#[derive(NixSettings)]` (requires the `derive` feature).
+///
+/// Each field maps to the setting named like the field, with `_` replaced by
+/// `-`, and its type must implement [`SettingField`]. Fields can be annotated
+/// with:
+///
+/// - `#[nix(rename = "...")]` to use a different setting name
+/// - `#[nix(list)]` to also read the items of `extra-<name>`
+/// - `#[nix(default = ...)]` for the value to use if the setting isn't set,
+///   converted with [`Into`]. Without a default, an unset setting is an error,
+///   unless the field is an [`Option`].
+///
+#[cfg_attr(feature = "derive", doc = "```rust")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::{NixConfig, NixSettings};
+///
+/// #[derive(NixSettings)]
+/// struct BuildSettings {
+///     #[nix(rename = "max-jobs", default = "auto")]
+///     jobs: String,
+///     #[nix(default = 0u32)]
+///     cores: u32,
+///     #[nix(list)]
+///     substituters: Vec<String>,
+///     post_build_hook: Option<std::path::PathBuf>,
+/// }
+///
+/// let mut nix_conf = NixConfig::parse_string(
+///     "cores = 4\nsubstituters = https://cache.nixos.org\nextra-substituters = https://example.com"
+///         .into(),
+///     None,
+/// )?;
+///
+/// let mut settings = BuildSettings::from_config(&nix_conf)?;
+/// assert_eq!(settings.jobs, "auto");
+/// assert_eq!(settings.cores, 4);
+/// assert_eq!(settings.substituters.len(), 2);
+/// assert_eq!(settings.post_build_hook, None);
+///
+/// settings.cores = 8;
+/// settings.apply_to(&mut nix_conf);
+/// assert_eq!(nix_conf.settings().get("cores").unwrap(), "8");
+/// assert_eq!(nix_conf.settings().get("max-jobs").unwrap(), "auto");
+/// # Ok(())
+/// # }
+/// ```
+pub trait NixSettings: Sized {
+    /// Read the settings from `config`.
+    fn from_config(config: &NixConfig) -> Result<Self, FromConfigError>;
+
+    /// Write the settings into `config`, replacing their previous values.
+    /// Fields that are [`None`] remove their setting.
+    fn apply_to(&self, config: &mut NixConfig);
+}
+
+/// A type that can hold the value of a setting in a [`NixSettings`] struct.
+pub trait SettingField: Sized {
+    /// Convert a setting's value, or return [`None`] if it is invalid for this
+    /// type.
+    fn from_value(value: &SettingValue) -> Option<Self>;
+
+    /// The value to write, or [`None`] to remove the setting.
+    fn to_value(&self) -> Option<SettingValue>;
+
+    /// The value to use if the setting isn't set at all, or [`None`] if it is
+    /// required.
+    fn when_missing() -> Option<Self> {
+        None
+    }
+}
+
+/// An error reading a [`NixSettings`] struct from a [`NixConfig`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FromConfigError {
+    #[error("required setting '{0}' is not set")]
+    Missing(String),
+    #[error("setting '{0}' has an invalid value '{1}'")]
+    InvalidValue(String, SettingValue),
+}
+
+impl SettingField for String {
+    fn from_value(value: &SettingValue) -> Option<Self> {
+        Some(value.to_string())
+    }
+
+    fn to_value(&self) -> Option<SettingValue> {
+        Some(self.as_str().into())
+    }
+}
+
+impl SettingField for SettingValue {
+    fn from_value(value: &SettingValue) -> Option<Self> {
+        Some(value.clone())
+    }
+
+    fn to_value(&self) -> Option<SettingValue> {
+        Some(self.clone())
+    }
+}
+
+impl SettingField for PathBuf {
+    fn from_value(value: &SettingValue) -> Option<Self> {
+        Some(PathBuf::from(value.as_str()))
+    }
+
+    fn to_value(&self) -> Option<SettingValue> {
+        Some(self.to_string_lossy().as_ref().into())
+    }
+}
+
+impl SettingField for bool {
+    fn from_value(value: &SettingValue) -> Option<Self> {
+        value.as_bool()
+    }
+
+    fn to_value(&self) -> Option<SettingValue> {
+        Some(if *self { "true" } else { "false" }.into())
+    }
+}
+
+macro_rules! impl_setting_field_for_integers {
+    ($($ty:ty),*) => {
+        $(
+            impl SettingField for $ty {
+                fn from_value(value: &SettingValue) -> Option<Self> {
+                    value
+                        .as_str()
+                        .parse()
+                        .ok()
+                        .or_else(|| value.as_int_with_unit()?.try_into().ok())
+                }
+
+                fn to_value(&self) -> Option<SettingValue> {
+                    Some(self.to_string().into())
+                }
+            }
+        )*
+    };
+}
+
+impl_setting_field_for_integers!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl<T: SettingField> SettingField for Vec<T> {
+    fn from_value(value: &SettingValue) -> Option<Self> {
+        value
+            .as_list()
+            .into_iter()
+            .map(|item| T::from_value(&item.into()))
+            .collect()
+    }
+
+    fn to_value(&self) -> Option<SettingValue> {
+        let mut value = SettingValue::default();
+        for item in self.iter().filter_map(T::to_value) {
+            value.push_item(item.as_str());
+        }
+        Some(value)
+    }
+}
+
+impl<T: SettingField> SettingField for Option<T> {
+    fn from_value(value: &SettingValue) -> Option<Self> {
+        T::from_value(value).map(Some)
+    }
+
+    fn to_value(&self) -> Option<SettingValue> {
+        self.as_ref().and_then(T::to_value)
+    }
+
+    fn when_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+/// Read the setting `name` (and `extra-<name>` for lists) for a derived
+/// [`NixSettings::from_config`].
+pub fn read<T: SettingField>(
+    config: &NixConfig,
+    name: &str,
+    list: bool,
+) -> Result<Option<T>, FromConfigError> {
+    let mut value = config.get_owned(name);
+    if list {
+        if let Some(extra) = config.settings().get(&format!("extra-{name}")) {
+            let value = value.get_or_insert_with(SettingValue::default);
+            for item in extra.as_list() {
+                value.push_item(item);
+            }
+        }
+    }
+
+    match value {
+        Some(value) => T::from_value(&value)
+            .map(Some)
+            .ok_or_else(|| FromConfigError::InvalidValue(name.to_owned(), value)),
+        None => Ok(None),
+    }
+}
+
+/// The value of a field whose setting `name` isn't set, for a derived
+/// [`NixSettings::from_config`].
+pub fn missing<T: SettingField>(name: &str) -> Result<T, FromConfigError> {
+    T::when_missing().ok_or_else(|| FromConfigError::Missing(name.to_owned()))
+}
+
+/// Write a field to the setting `name` for a derived [`NixSettings::apply_to`].
+pub fn write<T: SettingField>(config: &mut NixConfig, name: &str, list: bool, field: &T) {
+    if list {
+        config.settings_mut().shift_remove(&format!("extra-{name}"));
+    }
+
+    match field.to_value() {
+        Some(value) => {
+            config.settings_mut().insert(name.to_owned(), value);
+        }
+        None => {
+            config.settings_mut().shift_remove(name);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use super::*;
+    use crate::NixSettings;
+
+    #[derive(Debug, PartialEq, NixSettings)]
+    struct Settings {
+        sandbox: bool,
+        #[nix(list)]
+        trusted_users: Vec<String>,
+        #[nix(rename = "build-cores")]
+        cores: Option<u32>,
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_settings() {
+        let nix_conf = NixConfig::parse_string("trusted-users = root".into(), None).unwrap();
+        assert_eq!(
+            Settings::from_config(&nix_conf),
+            Err(FromConfigError::Missing("sandbox".into()))
+        );
+
+        let nix_conf =
+            NixConfig::parse_string("sandbox = maybe\nbuild-cores = 4".into(), None).unwrap();
+        assert_eq!(
+            Settings::from_config(&nix_conf),
+            Err(FromConfigError::InvalidValue(
+                "sandbox".into(),
+                "maybe".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn round_trips_through_a_config() {
+        let mut nix_conf = NixConfig::parse_string(
+            "sandbox = false\nextra-trusted-users = @wheel\nbuild-cores = 4".into(),
+            None,
+        )
+        .unwrap();
+
+        let mut settings = Settings::from_config(&nix_conf).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                sandbox: false,
+                trusted_users: vec!["@wheel".into()],
+                cores: Some(4),
+            }
+        );
+
+        settings.trusted_users.insert(0, "root".into());
+        settings.cores = None;
+        settings.apply_to(&mut nix_conf);
+
+        assert_eq!(
+            nix_conf.to_string_checked().unwrap(),
+            "sandbox = false\ntrusted-users = root @wheel\n"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{NixConfig, Origin, SettingValue};
+
+/// An assignment that was overridden by a later assignment of the same
+/// setting, typically one in an `include`d file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShadowedSetting {
+    pub(crate) name: String,
+    pub(crate) value: SettingValue,
+    pub(crate) origin: Origin,
+    pub(crate) shadowed_by: Origin,
+}
+
+impl ShadowedSetting {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The value that was overridden.
+    pub fn value(&self) -> &SettingValue {
+        &self.value
+    }
+
+    /// Where the overridden value was set.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// Where the overriding value was set.
+    pub fn shadowed_by(&self) -> &Origin {
+        &self.shadowed_by
+    }
+}
+
+impl NixConfig {
+    /// Every assignment that was overridden by a later one while parsing, in
+    /// the order they were overridden. A setting assigned three times shows up
+    /// twice.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::{NixConfig, Origin};
+    ///
+    /// let nix_conf = NixConfig::parse_string("cores = 4\ncores = 8".into(), None)?;
+    ///
+    /// let shadowed = &nix_conf.shadowed_settings()[0];
+    /// assert_eq!(shadowed.name(), "cores");
+    /// assert_eq!(shadowed.value(), "4");
+    /// assert_eq!(shadowed.origin(), &Origin::File { path: None, line: 1 });
+    /// assert_eq!(shadowed.shadowed_by(), &Origin::File { path: None, line: 2 });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shadowed_settings(&self) -> &[ShadowedSetting] {
+        &self.shadowed
+    }
+
+    /// Every value assigned to `name` while parsing, oldest first, along with
+    /// where it was assigned (if known). The last one is the value in
+    /// [`NixConfig::settings`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "substituters = https://a.example.com\nsubstituters = https://b.example.com".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let values = nix_conf
+    ///     .all_values("substituters")
+    ///     .into_iter()
+    ///     .map(|(value, _)| value.as_str())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(values, ["https://a.example.com", "https://b.example.com"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn all_values(&self, name: &str) -> Vec<(&SettingValue, Option<&Origin>)> {
+        let Some(current) = self.settings().get(name) else {
+            return Vec::new();
+        };
+
+        self.shadowed
+            .iter()
+            .filter(|shadowed| shadowed.name == name)
+            .map(|shadowed| (&shadowed.value, Some(&shadowed.origin)))
+            .chain(std::iter::once((current, self.origin(name))))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_settings_shadowed_by_includes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main_conf = temp_dir.path().join("nix.conf");
+        let included_conf = temp_dir.path().join("included.conf");
+
+        std::fs::write(&included_conf, "max-jobs = 8\n").unwrap();
+        std::fs::write(
+            &main_conf,
+            format!(
+                "max-jobs = 2\ncores = 4\ninclude {}\n",
+                included_conf.display()
+            ),
+        )
+        .unwrap();
+
+        let nix_conf = NixConfig::parse_file(&main_conf).unwrap();
+
+        assert_eq!(
+            nix_conf.shadowed_settings(),
+            &[ShadowedSetting {
+                name: "max-jobs".into(),
+                value: "2".into(),
+                origin: Origin::File {
+                    path: Some(main_conf),
+                    line: 1
+                },
+                shadowed_by: Origin::File {
+                    path: Some(included_conf),
+                    line: 1
+                },
+            }]
+        );
+        assert!(nix_conf.subset(["cores"]).shadowed_settings().is_empty());
+
+        assert_eq!(
+            nix_conf
+                .all_values("max-jobs")
+                .into_iter()
+                .map(|(value, _)| value.as_str())
+                .collect::<Vec<_>>(),
+            ["2", "8"]
+        );
+        assert_eq!(nix_conf.all_values("cores").len(), 1);
+        assert!(nix_conf.all_values("sandbox").is_empty());
+    }
+}
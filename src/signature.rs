@@ -0,0 +1,199 @@
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use indexmap::IndexMap;
+
+/// The public keys that may sign included files, see
+/// [`ParseOptions::verify_includes`](crate::ParseOptions::verify_includes).
+///
+/// Keys are written like in `trusted-public-keys`, as `name:base64`, and the
+/// detached signature of a file is in the file of the same name with `.sig`
+/// appended, as lines of `name:base64`. A file is accepted if any of its
+/// signatures is valid and made by a trusted key.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::{sign_fragment, TrustedKeys};
+///
+/// let secret_key = "fleet-1:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8DoQe/884Qvh1w3RjnS8CZZ+TWMJulDV8d3IZkElUxuA==";
+/// let keys = TrustedKeys::parse("fleet-1:A6EHv/POEL4dcN0Y50vAmWfk1jCbpQ1fHdyGZBJVMbg=")?;
+///
+/// let fragment = b"substituters = https://cache.example.com\n";
+/// let signature = sign_fragment(fragment, secret_key)?;
+/// keys.verify(fragment, &signature)?;
+/// assert!(keys.verify(b"substituters = https://evil.example.com\n", &signature).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrustedKeys {
+    keys: IndexMap<String, VerifyingKey>,
+}
+
+/// Why an included file wasn't accepted, or a key couldn't be read.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("key '{0}' is not of the form 'name:base64'")]
+    MalformedKey(String),
+    #[error("signature '{0}' is not of the form 'name:base64'")]
+    MalformedSignature(String),
+    #[error("it is not signed")]
+    Unsigned,
+    #[error("it is only signed by untrusted keys: {}", .0.join(", "))]
+    UntrustedKeys(Vec<String>),
+    #[error("its signature by '{0}' is invalid, it may have been tampered with")]
+    Invalid(String),
+}
+
+impl TrustedKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the whitespace-separated keys in `keys`, such as the value of
+    /// `trusted-public-keys`.
+    pub fn parse(keys: &str) -> Result<Self, SignatureError> {
+        keys.split_whitespace()
+            .try_fold(Self::new(), |trusted, key| trusted.with_key(key))
+    }
+
+    /// Also trust `key`, given as `name:base64`.
+    pub fn with_key(mut self, key: &str) -> Result<Self, SignatureError> {
+        let malformed = || SignatureError::MalformedKey(key.to_owned());
+        let (name, bytes) = split_key(key).ok_or_else(malformed)?;
+        let bytes = bytes.try_into().map_err(|_| malformed())?;
+        let key = VerifyingKey::from_bytes(&bytes).map_err(|_| malformed())?;
+
+        self.keys.insert(name.to_owned(), key);
+        Ok(self)
+    }
+
+    /// The names of the trusted keys.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(String::as_str)
+    }
+
+    /// Check that one of the `signatures` (lines of `name:base64`) is a valid
+    /// signature of `contents` by a trusted key.
+    pub fn verify(&self, contents: &[u8], signatures: &str) -> Result<(), SignatureError> {
+        let mut untrusted = Vec::new();
+        let mut invalid = None;
+
+        for line in signatures.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            let malformed = || SignatureError::MalformedSignature(line.to_owned());
+            let (name, bytes) = split_key(line).ok_or_else(malformed)?;
+            let signature = Signature::from_slice(&bytes).map_err(|_| malformed())?;
+
+            match self.keys.get(name) {
+                Some(key) if key.verify(contents, &signature).is_ok() => return Ok(()),
+                Some(_) => invalid = Some(name.to_owned()),
+                None => untrusted.push(name.to_owned()),
+            }
+        }
+
+        Err(match invalid {
+            Some(name) => SignatureError::Invalid(name),
+            None if untrusted.is_empty() => SignatureError::Unsigned,
+            None => SignatureError::UntrustedKeys(untrusted),
+        })
+    }
+
+    /// Verify `contents`, read from `path`, against the signatures in the
+    /// file next to it.
+    pub(crate) fn verify_file(&self, path: &Path, contents: &[u8]) -> Result<(), SignatureError> {
+        match std::fs::read_to_string(signature_path(path)) {
+            Ok(signatures) => self.verify(contents, &signatures),
+            Err(_) => Err(SignatureError::Unsigned),
+        }
+    }
+}
+
+/// Sign `contents` with `secret_key`, given in the format of
+/// `nix key generate-secret` (`name:base64`), returning a line for its `.sig`
+/// file.
+pub fn sign_fragment(contents: &[u8], secret_key: &str) -> Result<String, SignatureError> {
+    let malformed = || SignatureError::MalformedKey(secret_key.to_owned());
+    let (name, bytes) = split_key(secret_key).ok_or_else(malformed)?;
+    // Nix stores the seed followed by the public key.
+    let seed = bytes
+        .get(..32)
+        .filter(|_| bytes.len() == 64)
+        .ok_or_else(malformed)?;
+    let key = SigningKey::from_bytes(seed.try_into().map_err(|_| malformed())?);
+
+    Ok(format!(
+        "{name}:{}",
+        BASE64.encode(key.sign(contents).to_bytes())
+    ))
+}
+
+/// The file holding the signatures of the file at `path`.
+pub(crate) fn signature_path(path: &Path) -> PathBuf {
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(".sig");
+    signature_path.into()
+}
+
+fn split_key(key: &str) -> Option<(&str, Vec<u8>)> {
+    let (name, base64) = key.split_once(':')?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, BASE64.decode(base64).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NixConfig, ParseError, ParseOptions};
+
+    const SECRET_KEY: &str = "fleet-1:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8DoQe/884Qvh1w3RjnS8CZZ+TWMJulDV8d3IZkElUxuA==";
+    const PUBLIC_KEY: &str = "fleet-1:A6EHv/POEL4dcN0Y50vAmWfk1jCbpQ1fHdyGZBJVMbg=";
+
+    #[test]
+    fn verifies_included_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let fragment = temp_dir.path().join("fleet.conf");
+        let path = temp_dir.path().join("nix.conf");
+        let contents = "max-jobs = 8\n";
+        std::fs::write(&fragment, contents).unwrap();
+        std::fs::write(
+            &path,
+            format!("cores = 4\ninclude {}\n", fragment.display()),
+        )
+        .unwrap();
+
+        let options = ParseOptions::new().verify_includes(TrustedKeys::parse(PUBLIC_KEY).unwrap());
+        let parse = || NixConfig::parse_file_with_options(&path, &options);
+
+        assert!(matches!(
+            parse(),
+            Err(ParseError::UnverifiedInclude(file, SignatureError::Unsigned)) if file == fragment
+        ));
+
+        let signature = sign_fragment(contents.as_bytes(), SECRET_KEY).unwrap();
+        std::fs::write(signature_path(&fragment), &signature).unwrap();
+        assert_eq!(parse().unwrap().settings().get("max-jobs").unwrap(), "8");
+
+        std::fs::write(&fragment, "max-jobs = 64\n").unwrap();
+        assert!(matches!(
+            parse(),
+            Err(ParseError::UnverifiedInclude(_, SignatureError::Invalid(name))) if name == "fleet-1"
+        ));
+
+        let other = signature.replacen("fleet-1", "other-1", 1);
+        assert_eq!(
+            TrustedKeys::parse(PUBLIC_KEY)
+                .unwrap()
+                .verify(contents.as_bytes(), &other),
+            Err(SignatureError::UntrustedKeys(vec!["other-1".into()]))
+        );
+    }
+}
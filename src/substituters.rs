@@ -0,0 +1,102 @@
+use crate::{NixConfig, SettingValue};
+
+impl NixConfig {
+    /// Trust the binary cache at `url`, whose signatures can be checked with
+    /// `public_key` (such as `cache.example.org-1:...`), returning whether
+    /// anything changed.
+    ///
+    /// The URL and the key are added together, so a config never lists a
+    /// cache without its key (failing every substitution from it) or the
+    /// other way around. Each is appended to the base setting if it is set,
+    /// and to its `extra-` variant otherwise, so the built-in default, such
+    /// as `https://cache.nixos.org` and its key, stays in effect. Entries
+    /// that are already listed in either are not added again, and the
+    /// updated settings are deduplicated, keeping the order of the entries.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let mut nix_conf = NixConfig::parse_string(
+    ///     "substituters = https://cache.nixos.org https://cache.nixos.org".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert!(nix_conf.add_substituter("https://cache.example.org/", "cache.example.org-1:AAAA"));
+    /// assert!(!nix_conf.add_substituter("https://cache.example.org", "cache.example.org-1:AAAA"));
+    ///
+    /// assert_eq!(
+    ///     nix_conf.settings().get("substituters").unwrap(),
+    ///     "https://cache.nixos.org https://cache.example.org/"
+    /// );
+    /// assert_eq!(
+    ///     nix_conf.settings().get("extra-trusted-public-keys").unwrap(),
+    ///     "cache.example.org-1:AAAA"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_substituter(&mut self, url: &str, public_key: &str) -> bool {
+        let same_url = |item: &str| item.trim_end_matches('/') == url.trim_end_matches('/');
+        let added_url = self.add_list_item("substituters", url, same_url);
+        let added_key =
+            self.add_list_item("trusted-public-keys", public_key, |item| item == public_key);
+
+        added_url || added_key
+    }
+
+    /// Add `item` to `name` if it is set, or to `extra-<name>` otherwise,
+    /// unless an item `matches` in either of them.
+    fn add_list_item(&mut self, name: &str, item: &str, matches: impl Fn(&str) -> bool) -> bool {
+        let extra_name = format!("extra-{name}");
+        let listed = [name, extra_name.as_str()]
+            .iter()
+            .filter_map(|name| self.settings().get(*name))
+            .any(|value| value.as_list().into_iter().any(&matches));
+        if listed {
+            return false;
+        }
+
+        let target = if self.settings().contains_key(name) {
+            name.to_owned()
+        } else {
+            extra_name
+        };
+        let value = self
+            .settings_mut()
+            .entry(target)
+            .or_insert_with(|| SettingValue::from(""));
+        value.push_item(item);
+        value.dedup();
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_missing_key_of_listed_substituter() {
+        let mut nix_conf = NixConfig::parse_string(
+            "extra-substituters = https://cache.example.org\ntrusted-public-keys = cache.nixos.org-1:BBBB"
+                .into(),
+            None,
+        )
+        .unwrap();
+
+        assert!(nix_conf.add_substituter("https://cache.example.org", "cache.example.org-1:AAAA"));
+        assert_eq!(
+            nix_conf.settings().get("extra-substituters").unwrap(),
+            "https://cache.example.org"
+        );
+        assert_eq!(
+            nix_conf.settings().get("trusted-public-keys").unwrap(),
+            "cache.nixos.org-1:BBBB cache.example.org-1:AAAA"
+        );
+        assert!(!nix_conf.settings().contains_key("substituters"));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{parse_line, Directive, ParseError, ParseOptions, SettingInfo};
+
+impl ParseError {
+    /// A corrected version of the illegal line an error is about, for common
+    /// mistakes, which tools can offer as a fix:
+    ///
+    /// - a missing `=` after a known setting, as in `cores 4` or `cores=4`
+    /// - a colon instead of `=`, as in `cores: 4`
+    /// - a quoted name, as in `"cores" = 4` (with
+    ///   [quoting](ParseOptions::quoting) enabled)
+    /// - whitespace inside the name of a known setting, as in `max jobs = 4`
+    ///
+    /// The suggestion is only made if it parses as an assignment. Comments
+    /// on the line are not part of it.
+    ///
+    /// ```rust
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let error = NixConfig::parse_string("cores: 4".into(), None).unwrap_err();
+    /// assert_eq!(error.did_you_mean().as_deref(), Some("cores = 4"));
+    ///
+    /// let error = NixConfig::parse_string("!!!".into(), None).unwrap_err();
+    /// assert_eq!(error.did_you_mean(), None);
+    /// ```
+    pub fn did_you_mean(&self) -> Option<String> {
+        let (ParseError::IllegalConfiguration(line, _) | ParseError::MissingEquals(line, _)) = self
+        else {
+            return None;
+        };
+
+        let suggestion = suggest(line.trim())?;
+        match parse_line(&suggestion, &ParseOptions::default()) {
+            Ok(parsed) if matches!(parsed.directive, Directive::Assignment { .. }) => {
+                Some(suggestion)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn suggest(line: &str) -> Option<String> {
+    let assignment = |name: &str, value: &str| format!("{name} = {}", value.trim());
+
+    if let Some((name, value)) = line.split_once('=') {
+        let name = name.trim();
+        // `"cores" = 4`
+        if let Some(name) = name
+            .strip_prefix('"')
+            .and_then(|name| name.strip_suffix('"'))
+        {
+            return is_name(name).then(|| assignment(name, value));
+        }
+
+        // `max jobs = 4`, or `cores=4`
+        let name = name.split_whitespace().collect::<Vec<_>>().join("-");
+        if is_known(&name) {
+            return Some(assignment(&name, value));
+        }
+    }
+
+    // `cores: 4`
+    if let Some((name, value)) = line.split_once(':') {
+        if is_name(name.trim()) {
+            return Some(assignment(name.trim(), value));
+        }
+    }
+
+    // `cores 4`
+    let (name, value) = line.split_once(char::is_whitespace)?;
+    is_known(name).then(|| assignment(name, value))
+}
+
+/// Whether `word` looks like the name of a setting.
+fn is_name(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether `name` is a setting known to the [registry](SettingInfo), where
+/// a missing or misplaced `=` is only a guess.
+fn is_known(name: &str) -> bool {
+    SettingInfo::lookup(name).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NixConfig;
+
+    #[test]
+    fn suggests_fixes_for_common_mistakes() {
+        // Quoted names are only rejected when quoting is enabled.
+        let options = ParseOptions::new().quoting(true);
+        let did_you_mean = |line: &str| {
+            NixConfig::parse_string_with_options(line.into(), None, &options)
+                .unwrap_err()
+                .did_you_mean()
+        };
+
+        for (line, suggestion) in [
+            ("cores 4", Some("cores = 4")),
+            ("cores=4 # four", Some("cores = 4")),
+            ("\"cores\" = 4", Some("cores = 4")),
+            ("bad line", None),
+            ("max\tjobs = 4", Some("max-jobs = 4")),
+            (
+                "substituters: https://cache.nixos.org",
+                Some("substituters = https://cache.nixos.org"),
+            ),
+            ("cores", None),
+            ("\"cores = 4", None),
+        ] {
+            assert_eq!(did_you_mean(line).as_deref(), suggestion, "{line}");
+        }
+    }
+}
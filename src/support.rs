@@ -0,0 +1,384 @@
+//! Everything needed to debug someone else's Nix configuration, collected in
+//! one go for bug reports.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::secret::redact;
+pub use crate::secret::{is_secret, REDACTED};
+use crate::{
+    json, parse_contents, parse_line, paths, read_file, Directive, EffectiveConfig, ParseError,
+    ParseOptions,
+};
+
+/// The environment variables that change which configuration Nix reads.
+const ENVIRONMENT_VARIABLES: &[&str] = &[
+    "NIX_CONF_DIR",
+    "NIX_USER_CONF_FILES",
+    "NIX_CONFIG",
+    "XDG_CONFIG_HOME",
+    "XDG_CONFIG_DIRS",
+];
+
+/// A snapshot of the configuration files Nix reads, the files they include and
+/// the effective configuration, with secrets redacted. See [`collect`].
+#[derive(Clone, Debug)]
+pub struct SupportBundle {
+    files: Vec<BundledFile>,
+    environment: Vec<(String, String)>,
+    effective: Result<EffectiveConfig, String>,
+}
+
+impl SupportBundle {
+    /// The configuration files, each followed by the files it includes.
+    pub fn files(&self) -> &[BundledFile] {
+        &self.files
+    }
+
+    /// The environment variables that affect the configuration and are set,
+    /// with secrets in `NIX_CONFIG` redacted.
+    pub fn environment(&self) -> &[(String, String)] {
+        &self.environment
+    }
+
+    /// The effective configuration with secrets redacted, or why it could not
+    /// be resolved.
+    pub fn effective(&self) -> Result<&EffectiveConfig, &str> {
+        self.effective.as_ref().map_err(String::as_str)
+    }
+
+    /// Render the bundle as JSON: an object with `files`, `environment` (an
+    /// object) and either `effective` (see
+    /// [`EffectiveConfig::to_report_json`]) or `effective-error`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from(r#"{"files":["#);
+
+        for (index, file) in self.files.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+
+            out.push_str(r#"{"path":"#);
+            json::string(&mut out, &file.path.display().to_string());
+            let _ = write!(out, r#","included":{}"#, file.included);
+            out.push_str(r#","mode":"#);
+            let mode = file.mode.map(|mode| format!("{mode:04o}"));
+            json::optional_string(&mut out, mode.as_deref());
+            for (key, id) in [("uid", file.uid), ("gid", file.gid)] {
+                match id {
+                    Some(id) => write!(out, r#","{key}":{id}"#),
+                    None => write!(out, r#","{key}":null"#),
+                }
+                .expect("writing to a String can't fail");
+            }
+            out.push_str(r#","sha256":"#);
+            json::optional_string(&mut out, file.sha256.as_deref());
+            out.push_str(r#","contents":"#);
+            json::optional_string(&mut out, file.contents.as_deref());
+            out.push_str(r#","error":"#);
+            json::optional_string(&mut out, file.error.as_deref());
+            out.push('}');
+        }
+
+        out.push_str(r#"],"environment":{"#);
+        for (index, (name, value)) in self.environment.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            json::string(&mut out, name);
+            out.push(':');
+            json::string(&mut out, value);
+        }
+        out.push('}');
+
+        match &self.effective {
+            Ok(effective) => {
+                out.push_str(r#","effective":"#);
+                out.push_str(&effective.to_report_json());
+            }
+            Err(e) => {
+                out.push_str(r#","effective-error":"#);
+                json::string(&mut out, e);
+            }
+        }
+
+        out.push('}');
+        out
+    }
+}
+
+/// A configuration file in a [`SupportBundle`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BundledFile {
+    path: PathBuf,
+    included: bool,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    sha256: Option<String>,
+    contents: Option<String>,
+    error: Option<String>,
+}
+
+impl BundledFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file is included by another configuration file, rather
+    /// than read by Nix directly.
+    pub fn is_included(&self) -> bool {
+        self.included
+    }
+
+    /// The permission bits of the file, on Unix.
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// The user owning the file, on Unix.
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    /// The group owning the file, on Unix.
+    pub fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    /// The SHA-256 hash of the file as it is on disk (before redaction), in
+    /// lowercase hex.
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+
+    /// The contents of the file, with secrets redacted.
+    pub fn contents(&self) -> Option<&str> {
+        self.contents.as_deref()
+    }
+
+    /// Why the file could not be read, if it couldn't.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Collect a [`SupportBundle`] for the configuration Nix would use on this
+/// machine: [`paths::system_config_file`], [`paths::user_config_files`] and the
+/// files they include, and the environment.
+///
+/// Collecting never fails, as a bundle is most useful when the configuration
+/// is broken; problems are recorded in the bundle instead.
+pub fn collect() -> SupportBundle {
+    let mut files = vec![paths::system_config_file()];
+    files.extend(paths::user_config_files());
+
+    collect_from(&files, std::env::vars())
+}
+
+/// Like [`collect`], but for the given `files` and `env`, which are
+/// interpreted like [`EffectiveConfig::compute`] does.
+///
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use nix_config_parser::support;
+///
+/// let bundle = support::collect_from(
+///     &["/does/not/exist/nix.conf"],
+///     [("NIX_CONFIG", "access-tokens = github.com=ghp_secret\ncores = 4")],
+/// );
+///
+/// assert!(bundle.files()[0].error().is_some());
+/// assert_eq!(
+///     bundle.environment(),
+///     [(
+///         "NIX_CONFIG".to_string(),
+///         "access-tokens = github.com=<redacted>\ncores = 4".to_string()
+///     )]
+/// );
+///
+/// let effective = bundle.effective()?;
+/// assert_eq!(
+///     effective.config().settings().get("access-tokens").unwrap(),
+///     "github.com=<redacted>"
+/// );
+/// assert!(!bundle.to_json().contains("ghp_secret"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn collect_from<P, E, K, V>(files: &[P], env: E) -> SupportBundle
+where
+    P: AsRef<Path>,
+    E: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let env = env
+        .into_iter()
+        .map(|(k, v)| (k.as_ref().to_owned(), v.as_ref().to_owned()))
+        .collect::<Vec<_>>();
+    let var = |name: &str| env.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+
+    let mut bundled = Vec::new();
+    for file in files.iter().map(AsRef::as_ref) {
+        let included = bundle_file(file, false, &mut bundled);
+        for file in included {
+            bundle_file(&file, true, &mut bundled);
+        }
+    }
+
+    let environment = ENVIRONMENT_VARIABLES
+        .iter()
+        .filter_map(|name| {
+            let value = var(name)?;
+            let value = if *name == "NIX_CONFIG" {
+                redact_contents(value)
+            } else {
+                value.to_owned()
+            };
+            Some((name.to_string(), value))
+        })
+        .collect();
+
+    let effective = EffectiveConfig::compute(files, env.iter().cloned(), &[] as &[&str])
+        .map(|mut effective| {
+            effective.map_values(redact);
+            effective
+        })
+        .map_err(|e| e.to_string());
+
+    SupportBundle {
+        files: bundled,
+        environment,
+        effective,
+    }
+}
+
+/// Add `path` to `bundled`, returning the files it includes.
+fn bundle_file(path: &Path, included: bool, bundled: &mut Vec<BundledFile>) -> Vec<PathBuf> {
+    let mut file = BundledFile {
+        path: path.to_owned(),
+        included,
+        mode: None,
+        uid: None,
+        gid: None,
+        sha256: None,
+        contents: None,
+        error: None,
+    };
+
+    #[cfg(unix)]
+    if let Ok(metadata) = std::fs::metadata(path) {
+        use std::os::unix::fs::MetadataExt;
+
+        file.mode = Some(metadata.mode() & 0o7777);
+        file.uid = Some(metadata.uid());
+        file.gid = Some(metadata.gid());
+    }
+
+    let mut includes = Vec::new();
+    match read_file(path) {
+        Ok(contents) => {
+            file.sha256 = Some(format!("{:x}", Sha256::digest(contents.as_bytes())));
+            // The included files of a top-level file already list the files
+            // those include in turn.
+            if !included {
+                match parse_contents(&contents, Some(path), &ParseOptions::default()) {
+                    Ok(parsed) => includes = parsed.included_files,
+                    Err(
+                        ParseError::IncludedFileNotFound(include, _)
+                        | ParseError::IncludedFileUnreadable(include, _, _)
+                        | ParseError::InIncludedFile(include, _, _),
+                    ) => includes.push(include),
+                    Err(_) => {}
+                }
+            }
+            file.contents = Some(redact_contents(&contents));
+        }
+        Err(e) => file.error = Some(e.to_string()),
+    }
+
+    bundled.push(file);
+    includes
+}
+
+/// Redact the values of secret settings in a `nix.conf`, leaving every other
+/// line as it is.
+fn redact_contents(contents: &str) -> String {
+    let options = ParseOptions::default();
+
+    contents
+        .split_inclusive('\n')
+        .map(|line| {
+            let text = line.trim_end_matches(['\n', '\r']);
+            let ending = &line[text.len()..];
+            match parse_line(text, &options).map(|line| line.directive) {
+                Ok(Directive::Assignment { name, value }) if is_secret(&name) => {
+                    let indent = &text[..text.len() - text.trim_start().len()];
+                    let value = redact(&name, &value);
+                    format!("{indent}{name} = {value}{ending}")
+                }
+                _ => line.to_owned(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundles_files_and_includes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let nix_conf = temp_dir.path().join("nix.conf");
+        let tokens_conf = temp_dir.path().join("tokens.conf");
+
+        std::fs::write(
+            &tokens_conf,
+            "  access-tokens = github.com=ghp_secret gitlab.com=glpat # tokens\r\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &nix_conf,
+            format!("cores = 4\ninclude {}\n", tokens_conf.display()),
+        )
+        .unwrap();
+
+        let bundle = collect_from(&[&nix_conf], [("", "")]);
+
+        assert_eq!(bundle.files().len(), 2);
+        let tokens = &bundle.files()[1];
+        assert_eq!(tokens.path(), tokens_conf);
+        assert!(tokens.is_included());
+        assert_eq!(
+            tokens.contents(),
+            Some("  access-tokens = github.com=<redacted> gitlab.com=<redacted>\r\n")
+        );
+        assert_eq!(
+            tokens.sha256(),
+            Some(format!("{:x}", Sha256::digest(std::fs::read(&tokens_conf).unwrap())).as_str())
+        );
+        #[cfg(unix)]
+        assert!(tokens.mode().is_some());
+
+        let json = bundle.to_json();
+        assert!(!json.contains("ghp_secret"));
+        assert!(json.contains(r#""contents":"cores = 4\ninclude "#));
+    }
+
+    #[test]
+    fn recognizes_secrets() {
+        assert!(is_secret("access-tokens"));
+        assert!(is_secret("extra-access-tokens"));
+        assert!(is_secret("my-plugin-password"));
+        assert!(!is_secret("secret-key-files"));
+        assert!(!is_secret("netrc-file"));
+        assert!(!is_secret("substituters"));
+    }
+}
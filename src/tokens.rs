@@ -0,0 +1,159 @@
+//! Keep the tokens in `access-tokens` out of `nix.conf` files, by referring to
+//! the OS keyring or to environment variables instead, and materializing the
+//! real tokens only when the config is written out or applied.
+//!
+//! A token can be written as:
+//!
+//! - `@keyring`, for the keyring entry of the [`KEYRING_SERVICE`] service
+//!   named after the host
+//! - `@keyring:NAME`, for the entry named `NAME` instead
+//! - `@env:VARNAME`, for the environment variable `VARNAME`
+//!
+//! Nix itself doesn't understand these, so a config using them must go
+//! through [`NixConfig::materialize_tokens`] before Nix reads it.
+
+use std::env::VarError;
+
+use thiserror::Error;
+
+use crate::{NixConfig, SettingValue};
+
+/// The keyring service `@keyring` tokens are stored under.
+pub const KEYRING_SERVICE: &str = "nix-access-tokens";
+
+#[derive(Debug, Error)]
+pub enum TokenError {
+    #[error("environment variable '{1}' for the access token of '{0}' is not set")]
+    MissingVariable(String, String),
+    #[error("environment variable '{1}' for the access token of '{0}' is not valid unicode")]
+    InvalidVariable(String, String),
+    #[error("no access token for '{0}' in the keyring under '{1}'")]
+    NotInKeyring(String, String),
+    #[error("failed to read the access token of '{0}' from the keyring: {1}")]
+    Keyring(String, #[source] keyring::Error),
+}
+
+impl NixConfig {
+    /// A copy of the config with the [references](self) in `access-tokens`
+    /// (and `extra-access-tokens`) replaced by the tokens they refer to,
+    /// ready to be serialized or applied. Other tokens are kept as they are.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// std::env::set_var("EXAMPLE_GITHUB_TOKEN", "ghp_example");
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "access-tokens = github.com=@env:EXAMPLE_GITHUB_TOKEN gitlab.com=glpat_example".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     nix_conf.materialize_tokens()?.settings().get("access-tokens").unwrap(),
+    ///     "github.com=ghp_example gitlab.com=glpat_example"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn materialize_tokens(&self) -> Result<NixConfig, TokenError> {
+        let mut config = self.clone();
+
+        for name in ["access-tokens", "extra-access-tokens"] {
+            let Some(value) = config.settings_mut().get_mut(name) else {
+                continue;
+            };
+
+            let mut materialized = SettingValue::default();
+            for item in value.as_list() {
+                match item.split_once('=') {
+                    Some((host, token)) => match materialize(host, token)? {
+                        Some(token) => materialized.push_item(&format!("{host}={token}")),
+                        None => materialized.push_item(item),
+                    },
+                    None => materialized.push_item(item),
+                }
+            }
+            *value = materialized;
+        }
+
+        Ok(config)
+    }
+}
+
+/// The token `token` for `host` refers to, if it is a reference.
+fn materialize(host: &str, token: &str) -> Result<Option<String>, TokenError> {
+    if let Some(variable) = token.strip_prefix("@env:") {
+        return match std::env::var(variable) {
+            Ok(token) => Ok(Some(token)),
+            Err(VarError::NotPresent) => Err(TokenError::MissingVariable(
+                host.to_owned(),
+                variable.to_owned(),
+            )),
+            Err(VarError::NotUnicode(_)) => Err(TokenError::InvalidVariable(
+                host.to_owned(),
+                variable.to_owned(),
+            )),
+        };
+    }
+
+    let entry_name = match token.strip_prefix("@keyring") {
+        Some("") => host,
+        Some(name) => match name.strip_prefix(':') {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+
+    let failed = |e| TokenError::Keyring(host.to_owned(), e);
+    let entry = keyring::Entry::new(KEYRING_SERVICE, entry_name).map_err(failed)?;
+    match entry.get_password() {
+        Ok(token) => Ok(Some(token)),
+        Err(keyring::Error::NoEntry) => Err(TokenError::NotInKeyring(
+            host.to_owned(),
+            entry_name.to_owned(),
+        )),
+        Err(e) => Err(failed(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn materializes_references() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        std::env::set_var("NIX_CONFIG_PARSER_TEST_TOKEN", "secret");
+
+        let parse = |contents: &str| NixConfig::parse_string(contents.into(), None).unwrap();
+
+        let nix_conf = parse(
+            "extra-access-tokens = a.com=@env:NIX_CONFIG_PARSER_TEST_TOKEN b.com=@keyringish c.com=plain",
+        );
+        assert_eq!(
+            nix_conf
+                .materialize_tokens()
+                .unwrap()
+                .settings()
+                .get("extra-access-tokens"),
+            Some(&"a.com=secret b.com=@keyringish c.com=plain".into())
+        );
+
+        let nix_conf = parse("access-tokens = a.com=@env:NIX_CONFIG_PARSER_TEST_UNSET");
+        assert!(matches!(
+            nix_conf.materialize_tokens(),
+            Err(TokenError::MissingVariable(host, variable))
+                if host == "a.com" && variable == "NIX_CONFIG_PARSER_TEST_UNSET"
+        ));
+
+        // Entries of the mock keyring are always empty.
+        let nix_conf = parse("access-tokens = github.com=@keyring:work");
+        assert!(matches!(
+            nix_conf.materialize_tokens(),
+            Err(TokenError::NotInKeyring(host, name)) if host == "github.com" && name == "work"
+        ));
+    }
+}
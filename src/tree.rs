@@ -0,0 +1,366 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use thiserror::Error;
+
+use crate::include::{include_failed, is_unreadable, resolve_include};
+use crate::{Directive, NixConfigDocument, ParseError, ParseOptions, SerializeError, SettingValue};
+
+/// A `nix.conf` together with every file it (transitively) includes, each
+/// parsed as a [`NixConfigDocument`], for editing settings in the file where
+/// they belong.
+#[derive(Clone, Debug)]
+pub struct ConfigTree {
+    root: PathBuf,
+    writable: PathBuf,
+    documents: IndexMap<PathBuf, NixConfigDocument>,
+    originals: IndexMap<PathBuf, String>,
+}
+
+/// The new contents of a single file, as produced by [`ConfigTree::patches`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilePatch {
+    path: PathBuf,
+    original: String,
+    contents: String,
+}
+
+impl FilePatch {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The contents of the file before any edits (empty for new files).
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The contents of the file after the edits.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Write the new contents to the file, after checking it can be written
+    /// with [`FilePatch::check_writable`].
+    pub fn apply(&self) -> Result<(), WriteError> {
+        self.check_writable()?;
+
+        std::fs::write(&self.path, &self.contents).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                WriteError::ReadOnly(self.path.clone())
+            } else {
+                WriteError::FailedToWriteFile(self.path.clone(), e)
+            }
+        })
+    }
+
+    /// Check whether the file can be edited: it must not live in the Nix store
+    /// (like `/etc/nix/nix.conf` on NixOS, which is a symlink into the store),
+    /// and must not be read-only.
+    pub fn check_writable(&self) -> Result<(), WriteError> {
+        if is_in_nix_store(&self.path) {
+            return Err(WriteError::InNixStore(self.path.clone()));
+        }
+
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) if metadata.permissions().readonly() => {
+                Err(WriteError::ReadOnly(self.path.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether `path`, or whatever it is a symlink to, is in the Nix store.
+fn is_in_nix_store(path: &Path) -> bool {
+    let store = Path::new("/nix/store");
+
+    path.starts_with(store)
+        || std::fs::canonicalize(path).is_ok_and(|canonical| canonical.starts_with(store))
+}
+
+/// An error that occurred while attempting to write an edited file.
+#[derive(Debug, Error)]
+pub enum WriteError {
+    #[error("'{0}' is managed through the Nix store and can't be edited; edit the user-level config (usually ~/.config/nix/nix.conf) instead")]
+    InNixStore(PathBuf),
+    #[error(
+        "'{0}' is read-only; edit the user-level config (usually ~/.config/nix/nix.conf) instead"
+    )]
+    ReadOnly(PathBuf),
+    #[error("failed to write '{0}': {1}")]
+    FailedToWriteFile(PathBuf, #[source] std::io::Error),
+}
+
+impl ConfigTree {
+    /// Parse the `nix.conf` at `root` and every file it includes. Optional
+    /// includes (`!include`) that can't be read are skipped, like Nix does.
+    pub fn parse_file(root: &Path) -> Result<Self, ParseError> {
+        Self::parse_file_with_options(root, &ParseOptions::default())
+    }
+
+    /// Parse the `nix.conf` at `root` and every file it includes, using the
+    /// provided [`ParseOptions`].
+    pub fn parse_file_with_options(
+        root: &Path,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let mut tree = Self {
+            root: root.to_owned(),
+            writable: root.to_owned(),
+            documents: IndexMap::new(),
+            originals: IndexMap::new(),
+        };
+
+        let document = NixConfigDocument::parse_file_with_options(root, options)?;
+        tree.add(root, document, options)?;
+
+        Ok(tree)
+    }
+
+    /// Put settings that aren't set anywhere yet into `path` instead of the
+    /// root file. The file is created if it doesn't exist, so it should be
+    /// included by the root file (or one of its includes) to have any effect.
+    pub fn writable_file(mut self, path: &Path) -> Self {
+        self.writable = path.to_owned();
+        self
+    }
+
+    /// The path of the root file.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The document parsed from `path`, if it is part of the tree.
+    pub fn document(&self, path: &Path) -> Option<&NixConfigDocument> {
+        self.documents.get(path)
+    }
+
+    /// The value Nix would use for `name`, considering includes.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<SettingValue> {
+        let name = name.as_ref();
+        let path = self.defining_file(name)?;
+        self.documents[path].get(name)
+    }
+
+    /// Set `name` to `value`, in the file that holds its effective assignment,
+    /// or in the [writable file](ConfigTree::writable_file) if it isn't set
+    /// anywhere. Returns the path of the file that was edited.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::ConfigTree;
+    ///
+    /// let temp_dir = tempfile::TempDir::new()?;
+    /// let root = temp_dir.path().join("nix.conf");
+    /// let local = temp_dir.path().join("local.conf");
+    /// std::fs::write(&local, "cores = 8\n")?;
+    /// std::fs::write(&root, format!("cores = 2\ninclude {}\n", local.display()))?;
+    ///
+    /// let mut tree = ConfigTree::parse_file(&root)?;
+    /// assert_eq!(tree.set("cores", "4")?, local);
+    /// assert_eq!(tree.set("max-jobs", "auto")?, root);
+    ///
+    /// for patch in tree.patches() {
+    ///     patch.apply()?;
+    /// }
+    /// assert_eq!(std::fs::read_to_string(&local)?, "cores = 4\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set(&mut self, name: &str, value: &str) -> Result<PathBuf, SerializeError> {
+        let path = match self.defining_file(name) {
+            Some(path) => path.to_owned(),
+            None => self.writable.clone(),
+        };
+
+        let document = match self.documents.get_mut(&path) {
+            Some(document) => document,
+            None => {
+                let options = self.documents[&self.root].options().clone();
+                let document = NixConfigDocument::parse_string_with_options(
+                    String::new(),
+                    Some(&path),
+                    &options,
+                )
+                .expect("an empty document is always valid");
+                self.originals.insert(path.clone(), String::new());
+                self.documents.entry(path.clone()).or_insert(document)
+            }
+        };
+        document.set(name, value)?;
+
+        Ok(path)
+    }
+
+    /// The files whose contents changed because of edits, in the order they
+    /// were first read.
+    pub fn patches(&self) -> Vec<FilePatch> {
+        self.documents
+            .iter()
+            .filter_map(|(path, document)| {
+                let contents = document.to_string();
+                let original = &self.originals[path];
+                (contents != *original).then(|| FilePatch {
+                    path: path.clone(),
+                    original: original.clone(),
+                    contents,
+                })
+            })
+            .collect()
+    }
+
+    fn add(
+        &mut self,
+        path: &Path,
+        document: NixConfigDocument,
+        options: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        let includes = document.includes();
+        self.originals.insert(path.to_owned(), document.to_string());
+        self.documents.insert(path.to_owned(), document);
+
+        for include in includes {
+            let include_path =
+                resolve_include(&include.path().to_string_lossy(), Some(path), options);
+            if self.documents.contains_key(&include_path) {
+                continue;
+            }
+
+            match NixConfigDocument::parse_file_with_options(&include_path, options) {
+                Ok(document) => self.add(&include_path, document, options)?,
+                Err(e)
+                    if (include.is_optional() && is_unreadable(&e))
+                        || options.include_errors.skips(&include_path, &e) => {}
+                Err(e) => return Err(include_failed(include_path, Some(path), e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The file holding the assignment of `name` that Nix would use, which is
+    /// the last one in the order the files are read.
+    fn defining_file(&self, name: &str) -> Option<&Path> {
+        let mut defining = None;
+        self.visit(&self.root, name, &mut HashSet::new(), &mut defining);
+        defining
+    }
+
+    fn visit<'a>(
+        &'a self,
+        path: &'a Path,
+        name: &str,
+        visited: &mut HashSet<&'a Path>,
+        defining: &mut Option<&'a Path>,
+    ) {
+        let Some((path, document)) = self.documents.get_key_value(path) else {
+            return;
+        };
+        if !visited.insert(path) {
+            return;
+        }
+
+        for directive in document.directives() {
+            match directive {
+                Directive::Assignment { name: n, .. } if n == name => {
+                    *defining = Some(path);
+                }
+                Directive::Unset { name: n } if n == name => {
+                    *defining = None;
+                }
+                Directive::Include { path: include, .. } => {
+                    let include = resolve_include(&include, Some(path), document.options());
+                    if let Some((include, _)) = self.documents.get_key_value(&include) {
+                        self.visit(include, name, visited, defining);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        visited.remove(path.as_path());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_the_defining_or_writable_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().join("nix.conf");
+        let first = temp_dir.path().join("first.conf");
+        let second = temp_dir.path().join("second.conf");
+        let writable = temp_dir.path().join("writable.conf");
+
+        std::fs::write(&first, "cores = 8\nsandbox = true\n").unwrap();
+        std::fs::write(&second, "cores = 16\n").unwrap();
+        std::fs::write(
+            &root,
+            format!(
+                "include {}\nsandbox = false\ninclude {}\n!include {}\n",
+                first.display(),
+                second.display(),
+                writable.display(),
+            ),
+        )
+        .unwrap();
+
+        let mut tree = ConfigTree::parse_file(&root)
+            .unwrap()
+            .writable_file(&writable);
+
+        assert_eq!(tree.get("cores"), Some("16".into()));
+        assert_eq!(tree.set("cores", "4").unwrap(), second);
+        assert_eq!(tree.set("sandbox", "relaxed").unwrap(), root);
+        assert_eq!(tree.set("max-jobs", "auto").unwrap(), writable);
+
+        let patches = tree.patches();
+        assert_eq!(
+            patches.iter().map(FilePatch::path).collect::<Vec<_>>(),
+            [root.as_path(), second.as_path(), writable.as_path()]
+        );
+        assert_eq!(patches[1].original(), "cores = 16\n");
+        assert_eq!(patches[1].contents(), "cores = 4\n");
+        assert_eq!(patches[2].contents(), "max-jobs = auto\n");
+        assert_eq!(
+            tree.document(&first).unwrap().get("cores"),
+            Some("8".into())
+        );
+    }
+
+    #[test]
+    fn refuses_read_only_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().join("nix.conf");
+        std::fs::write(&root, "cores = 2\n").unwrap();
+
+        let mut permissions = std::fs::metadata(&root).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&root, permissions).unwrap();
+
+        let mut tree = ConfigTree::parse_file(&root).unwrap();
+        tree.set("cores", "4").unwrap();
+
+        match tree.patches()[0].apply() {
+            Err(WriteError::ReadOnly(path)) => assert_eq!(path, root),
+            _ => panic!("read-only file should have returned WriteError::ReadOnly"),
+        }
+        assert_eq!(std::fs::read_to_string(&root).unwrap(), "cores = 2\n");
+
+        let store_patch = FilePatch {
+            path: "/nix/store/00000000000000000000000000000000-nix.conf".into(),
+            original: String::new(),
+            contents: String::new(),
+        };
+        assert!(matches!(
+            store_patch.check_writable(),
+            Err(WriteError::InNixStore(_))
+        ));
+    }
+}
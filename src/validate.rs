@@ -0,0 +1,280 @@
+use std::fmt;
+
+use crate::{NixConfig, Origin, SettingInfo, SettingType, SettingValue};
+
+/// A setting whose value Nix would reject or misinterpret, see
+/// [`NixConfig::validate_values`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValueProblem {
+    pub(crate) name: String,
+    pub(crate) value: SettingValue,
+    pub(crate) message: String,
+    pub(crate) origin: Option<Origin>,
+}
+
+impl ValueProblem {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &SettingValue {
+        &self.value
+    }
+
+    /// What is wrong with the value.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where the value was set, if known.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+}
+
+impl fmt::Display for ValueProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{origin}: ")?;
+        }
+        write!(
+            f,
+            "invalid value '{}' for '{}': {}",
+            self.value, self.name, self.message
+        )
+    }
+}
+
+impl NixConfig {
+    /// Check the value of every setting known to the registry against its
+    /// type, returning every problem found, in the order of the settings:
+    ///
+    /// - booleans must be `true` or `false` (or another spelling Nix accepts,
+    ///   see [`SettingValue::as_bool`])
+    /// - integers such as `cores` must be integers, and `max-jobs` may also be
+    ///   `auto`
+    /// - paths such as `netrc-file` and `ssl-cert-file` must be absolute,
+    ///   starting with `/` (also on Windows)
+    /// - values of settings with a fixed set of values must be one of those
+    /// - substituters must be store URLs such as `https://cache.nixos.org`
+    /// - public keys must be `name:key`, with a base64-encoded 32 byte key
+    ///
+    /// Unknown settings are not checked.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use nix_config_parser::NixConfig;
+    ///
+    /// let nix_conf = NixConfig::parse_string(
+    ///     "cores = four\nmax-jobs = auto\nnetrc-file = .netrc\nsandbox = relaxed".into(),
+    ///     None,
+    /// )?;
+    ///
+    /// let problems = nix_conf.validate_values();
+    /// assert_eq!(
+    ///     problems.iter().map(|problem| problem.name()).collect::<Vec<_>>(),
+    ///     ["cores", "netrc-file"]
+    /// );
+    /// assert_eq!(problems[1].message(), "expected an absolute path");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_values(&self) -> Vec<ValueProblem> {
+        let mut problems = Vec::new();
+
+        for (name, value) in self.settings() {
+            let Some(info) = SettingInfo::lookup(name) else {
+                continue;
+            };
+
+            if let Err(message) = check_value(info, value) {
+                problems.push(ValueProblem {
+                    name: name.clone(),
+                    value: value.clone(),
+                    message,
+                    origin: self.origin(name).cloned(),
+                });
+            }
+        }
+
+        problems
+    }
+}
+
+pub(crate) fn check_value(info: &SettingInfo, value: &SettingValue) -> Result<(), String> {
+    match info.name() {
+        "substituters" | "trusted-substituters" => {
+            return check_items(value, is_store_url, "expected a store URL");
+        }
+        "trusted-public-keys" => {
+            return check_items(value, is_public_key, "expected a public key");
+        }
+        _ => {}
+    }
+
+    match info.setting_type() {
+        SettingType::Boolean => value
+            .as_bool()
+            .map(drop)
+            .ok_or_else(|| "expected 'true' or 'false'".to_owned()),
+        SettingType::Integer => value
+            .as_int_with_unit()
+            .map(drop)
+            .ok_or_else(|| "expected an integer".to_owned()),
+        SettingType::IntegerOrAuto if value == "auto" => Ok(()),
+        SettingType::IntegerOrAuto => value
+            .as_int_with_unit()
+            .map(drop)
+            .ok_or_else(|| "expected an integer or 'auto'".to_owned()),
+        SettingType::Path if value.is_empty() || value.starts_with('/') => Ok(()),
+        SettingType::Path => Err("expected an absolute path".to_owned()),
+        // Sandbox paths can be `target=source` and end with `?` if optional
+        SettingType::PathList => check_items(
+            value,
+            |item| item.starts_with('/'),
+            "expected absolute paths",
+        ),
+        SettingType::Enum(values) if values.contains(&value.as_str()) => Ok(()),
+        SettingType::Enum(values) => Err(format!("expected one of '{}'", values.join("', '"))),
+        SettingType::String | SettingType::StringList | SettingType::StringMap => Ok(()),
+    }
+}
+
+fn check_items(
+    value: &SettingValue,
+    valid: impl Fn(&str) -> bool,
+    expected: &str,
+) -> Result<(), String> {
+    let invalid = value
+        .as_list()
+        .into_iter()
+        .filter(|item| !valid(item))
+        .collect::<Vec<_>>();
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{expected}, not '{}'", invalid.join("', '")))
+    }
+}
+
+/// Whether `url` looks like a store URL Nix can substitute from: a
+/// `scheme://...` URL, or a local store path.
+fn is_store_url(url: &str) -> bool {
+    if url.starts_with('/') {
+        return true;
+    }
+
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+                && !rest.is_empty()
+        }
+        None => false,
+    }
+}
+
+/// Whether `key` is a `name:key` public key with a base64-encoded ed25519 key.
+fn is_public_key(key: &str) -> bool {
+    match key.split_once(':') {
+        Some((name, key)) => !name.is_empty() && base64_len(key) == Some(32),
+        None => false,
+    }
+}
+
+/// The number of bytes the padded base64 string `encoded` decodes to, or
+/// [`None`] if it isn't valid base64.
+pub(crate) fn base64_len(encoded: &str) -> Option<usize> {
+    if encoded.is_empty() || !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let data = encoded.trim_end_matches('=');
+    let padding = encoded.len() - data.len();
+    if padding > 2
+        || !data
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+    {
+        return None;
+    }
+
+    Some(encoded.len() / 4 * 3 - padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_problem() {
+        let nix_conf = NixConfig::parse_string(
+            r#"
+                sandbox = yes
+                keep-outputs = maybe
+                max-jobs = many
+                extra-substituters = https://example.com ftp:/example.com
+                trusted-public-keys = cache.nixos.org-1:6NCHdD59X431o0gWypbMrAURkbJ16ZPMQFGspcDShjY= example.com-1:c2hvcnQ=
+                extra-sandbox-paths = /bin/sh=/nix/store/foo-bash/bin/sh /dev/kvm? relative
+                ssl-cert-file =
+                not-a-setting = whatever
+            "#
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            nix_conf
+                .validate_values()
+                .iter()
+                .map(|problem| (problem.name(), problem.message()))
+                .collect::<Vec<_>>(),
+            [
+                ("sandbox", "expected one of 'true', 'false', 'relaxed'"),
+                ("keep-outputs", "expected 'true' or 'false'"),
+                ("max-jobs", "expected an integer or 'auto'"),
+                (
+                    "extra-substituters",
+                    "expected a store URL, not 'ftp:/example.com'"
+                ),
+                (
+                    "trusted-public-keys",
+                    "expected a public key, not 'example.com-1:c2hvcnQ='"
+                ),
+                (
+                    "extra-sandbox-paths",
+                    "expected absolute paths, not 'relative'"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_unit_suffixes_on_integers() {
+        let contents = "min-free = 1G
+max-free = 500M
+cores = 4";
+        let nix_conf = NixConfig::parse_string(contents.into(), None).unwrap();
+        assert!(nix_conf.validate_values().is_empty());
+
+        assert!(NixConfig::parse_string_with_options(
+            contents.into(),
+            None,
+            &crate::ParseOptions::strict()
+        )
+        .is_ok());
+
+        let nix_conf = NixConfig::parse_string("min-free = 1X".into(), None).unwrap();
+        assert_eq!(
+            nix_conf.validate_values()[0].message(),
+            "expected an integer"
+        );
+    }
+}
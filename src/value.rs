@@ -0,0 +1,248 @@
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+/// The value of a Nix setting, exactly as written in the `nix.conf` (with
+/// whitespace between tokens collapsed to single spaces).
+///
+/// Nix interprets values based on the type of the setting, so this is a thin
+/// wrapper around a string with helpers for the common interpretations. It
+/// dereferences to [`str`], so it can be used wherever a `&str` is expected.
+///
+/// Values are stored as a `Box<str>` without spare capacity, as they are rarely
+/// modified after parsing, which keeps large or many configs compact.
+///
+/// With the `zeroize` feature, the buffer of a value is wiped when the value is
+/// dropped or changed, for embedders handling secrets such as the tokens in
+/// `access-tokens`. A value doesn't know which setting it belongs to, so this
+/// applies to every value, not just secret ones. Parsing also wipes the
+/// contents it reads, fetches or decodes (including those of included files)
+/// once it is done with them.
+///
+/// ```rust
+/// use nix_config_parser::SettingValue;
+///
+/// let mut value = SettingValue::from("flakes nix-command");
+/// value.push_item("flakes");
+/// value.dedup();
+///
+/// assert_eq!(value.as_list(), vec!["flakes", "nix-command"]);
+/// assert_eq!(SettingValue::from("true").as_bool(), Some(true));
+/// assert_eq!(SettingValue::from("16").as_int(), Some(16));
+/// ```
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct SettingValue(Box<str>);
+
+impl SettingValue {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self::from(value.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(mut self) -> String {
+        std::mem::take(&mut self.0).into_string()
+    }
+
+    /// Interpret the value as a whitespace-separated list, like Nix does for
+    /// settings such as `substituters` or `experimental-features`.
+    pub fn as_list(&self) -> Vec<&str> {
+        self.0.split_whitespace().collect()
+    }
+
+    /// Interpret the value as a boolean, accepting the same spellings as Nix
+    /// (`true`/`yes`/`1` and `false`/`no`/`0`).
+    pub fn as_bool(&self) -> Option<bool> {
+        match &*self.0 {
+            "true" | "yes" | "1" => Some(true),
+            "false" | "no" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Interpret the value as an integer. Note that some integer settings (such
+    /// as `max-jobs`) also accept `auto`, for which this returns [`None`].
+    pub fn as_int(&self) -> Option<i64> {
+        self.0.parse().ok()
+    }
+
+    /// Interpret the value as an integer like Nix does for integer settings,
+    /// which may end in a `K`, `M`, `G` or `T` suffix (in either case) for
+    /// multiples of 1024, as in `min-free = 1G`.
+    ///
+    /// ```rust
+    /// use nix_config_parser::SettingValue;
+    ///
+    /// assert_eq!(SettingValue::from("1G").as_int_with_unit(), Some(1 << 30));
+    /// assert_eq!(SettingValue::from("500m").as_int_with_unit(), Some(500 << 20));
+    /// assert_eq!(SettingValue::from("1X").as_int_with_unit(), None);
+    /// ```
+    pub fn as_int_with_unit(&self) -> Option<i64> {
+        let shift = match self.0.chars().last()?.to_ascii_uppercase() {
+            'K' => 10,
+            'M' => 20,
+            'G' => 30,
+            'T' => 40,
+            _ => return self.as_int(),
+        };
+
+        let number = self.0[..self.0.len() - 1].parse::<i64>().ok()?;
+        number.checked_mul(1 << shift)
+    }
+
+    /// Append an item to the value, treating it as a whitespace-separated list.
+    pub fn push_item(&mut self, item: &str) {
+        let mut value = String::with_capacity(self.0.len() + 1 + item.len());
+        value.push_str(&self.0);
+        if !value.is_empty() {
+            value.push(' ');
+        }
+        value.push_str(item);
+        self.replace(value);
+    }
+
+    /// Remove duplicate items from the value, treating it as a
+    /// whitespace-separated list. The first occurrence of each item is kept.
+    pub fn dedup(&mut self) {
+        let mut seen = Vec::new();
+        for item in self.0.split_whitespace() {
+            if !seen.contains(&item) {
+                seen.push(item);
+            }
+        }
+
+        let value = seen.join(" ");
+        self.replace(value);
+    }
+
+    /// Replace the buffer, dropping the old one like a value so that it is
+    /// wiped with the `zeroize` feature.
+    fn replace(&mut self, value: String) {
+        drop(std::mem::replace(self, Self::from(value)));
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SettingValue {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+impl Deref for SettingValue {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SettingValue {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for SettingValue {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for SettingValue {
+    fn from(value: String) -> Self {
+        // Shrinking the buffer would free the old one without wiping it.
+        #[cfg(feature = "zeroize")]
+        if value.capacity() != value.len() {
+            let shrunk = Self(value.as_str().into());
+            let mut value = value;
+            zeroize::Zeroize::zeroize(&mut value);
+            return shrunk;
+        }
+
+        Self(value.into_boxed_str())
+    }
+}
+
+impl From<&str> for SettingValue {
+    fn from(value: &str) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<SettingValue> for String {
+    fn from(value: SettingValue) -> Self {
+        value.into_inner()
+    }
+}
+
+impl PartialEq<str> for SettingValue {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SettingValue {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for SettingValue {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interprets_values() {
+        let value = SettingValue::from("  https://cache.nixos.org   https://example.com ");
+        assert_eq!(
+            value.as_list(),
+            vec!["https://cache.nixos.org", "https://example.com"]
+        );
+        assert_eq!(value.as_bool(), None);
+        assert_eq!(value.as_int(), None);
+
+        assert_eq!(SettingValue::from("no").as_bool(), Some(false));
+        assert_eq!(SettingValue::from("auto").as_int(), None);
+        assert_eq!(SettingValue::from("-1").as_int(), Some(-1));
+        assert!(SettingValue::default().as_list().is_empty());
+    }
+
+    #[test]
+    fn edits_lists() {
+        let mut value = SettingValue::default();
+        value.push_item("a");
+        value.push_item("b");
+        value.push_item("a");
+        assert_eq!(value, "a b a");
+
+        value.dedup();
+        assert_eq!(value, "a b");
+        assert_eq!(value.into_inner().capacity(), 3);
+    }
+
+    #[test]
+    fn is_compact() {
+        assert_eq!(
+            std::mem::size_of::<SettingValue>(),
+            2 * std::mem::size_of::<usize>()
+        );
+    }
+}
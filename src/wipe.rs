@@ -0,0 +1,81 @@
+//! Wiping the buffers that held (part of) a config before they are freed, with
+//! the `zeroize` feature. Values wipe themselves, see
+//! [`SettingValue`](crate::SettingValue).
+
+use std::{borrow::Borrow, borrow::Cow, ops::Deref};
+
+/// Text that is wiped when it is dropped, if it is owned. Without the
+/// `zeroize` feature, this is just a [`Cow`].
+#[derive(Debug, Default)]
+pub(crate) struct WipeOnDrop<'a>(Cow<'a, str>);
+
+impl<'a> WipeOnDrop<'a> {
+    pub(crate) fn into_inner(mut self) -> Cow<'a, str> {
+        std::mem::take(&mut self.0)
+    }
+
+    /// The text, if it is borrowed rather than owned.
+    pub(crate) fn borrowed(&self) -> Option<&'a str> {
+        match self.0 {
+            Cow::Borrowed(text) => Some(text),
+            Cow::Owned(_) => None,
+        }
+    }
+
+    pub(crate) fn to_mut(&mut self) -> &mut String {
+        self.0.to_mut()
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for WipeOnDrop<'a> {
+    fn from(text: Cow<'a, str>) -> Self {
+        Self(text)
+    }
+}
+
+impl<'a> From<&'a str> for WipeOnDrop<'a> {
+    fn from(text: &'a str) -> Self {
+        Self(Cow::Borrowed(text))
+    }
+}
+
+impl From<String> for WipeOnDrop<'_> {
+    fn from(text: String) -> Self {
+        Self(Cow::Owned(text))
+    }
+}
+
+impl Deref for WipeOnDrop<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for WipeOnDrop<'_> {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for WipeOnDrop<'_> {
+    fn drop(&mut self) {
+        if let Cow::Owned(text) = &mut self.0 {
+            zeroize::Zeroize::zeroize(text);
+        }
+    }
+}
+
+/// Wipe `bytes` before freeing them.
+#[cfg(feature = "http")]
+pub(crate) fn wipe_bytes(bytes: Vec<u8>) {
+    #[cfg(feature = "zeroize")]
+    {
+        let mut bytes = bytes;
+        zeroize::Zeroize::zeroize(&mut bytes);
+    }
+    #[cfg(not(feature = "zeroize"))]
+    drop(bytes);
+}